@@ -22,6 +22,7 @@ struct VertexInput {
     @location(7) a_num_frames: i32,
     @location(8) a_light: i32,
     @location(9) a_texture_alt_index: u32,
+    @location(10) a_num_frames_alt: i32,
 }
 
 struct VertexOutput {
@@ -40,21 +41,24 @@ fn main_vs(in: VertexInput) -> VertexOutput {
     var out: VertexOutput;
     out.v_tile_uv = in.a_tile_uv + vec2(u_time * in.a_scroll_rate, 0.0);
     var atlas_uv: vec2<f32>;
+    var num_frames: i32;
     if u_texture_alt[in.a_texture_alt_index] == 0 {
         atlas_uv = in.a_atlas_uv;
+        num_frames = in.a_num_frames;
     } else {
         atlas_uv = in.a_atlas_uv_alt;
+        num_frames = in.a_num_frames_alt;
     };
-    if in.a_num_frames == 1 {
+    if num_frames == 1 {
         out.v_atlas_uv = atlas_uv;
     } else {
-        let frame_index = floor((u_time / ANIM_FPS) % f32(in.a_num_frames));
+        let frame_index = floor((u_time / ANIM_FPS) % f32(num_frames));
 
-        var atlas_u = in.a_atlas_uv.x + frame_index * in.a_tile_size.x;
+        var atlas_u = atlas_uv.x + frame_index * in.a_tile_size.x;
         let n_rows_down = ceil((atlas_u + in.a_tile_size.x) / u_atlas_size.x) - 1.0;
-        atlas_u += (u_atlas_size.x - in.a_atlas_uv.x) % in.a_tile_size.x * n_rows_down;
+        atlas_u += (u_atlas_size.x - atlas_uv.x) % in.a_tile_size.x * n_rows_down;
 
-        let atlas_v = in.a_atlas_uv.y + n_rows_down * in.a_row_height;
+        let atlas_v = atlas_uv.y + n_rows_down * in.a_row_height;
         out.v_atlas_uv = vec2(atlas_u, atlas_v);
     }
     out.v_tile_size = in.a_tile_size;
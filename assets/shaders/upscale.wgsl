@@ -0,0 +1,21 @@
+@group(0) @binding(0) var u_tex: texture_2d<f32>;
+@group(0) @binding(1) var u_sampler: sampler;
+
+struct VertexOutput {
+    @location(0) v_uv: vec2<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+}
+
+// A single triangle covering the whole screen, with the texture over its visible part.
+@vertex
+fn main_vs(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    out.v_uv = vec2(f32((index << 1u) & 2u), f32(index & 2u));
+    out.clip_position = vec4(out.v_uv.x * 2.0 - 1.0, 1.0 - out.v_uv.y * 2.0, 0.0, 1.0);
+    return out;
+}
+
+@fragment
+fn main_fs(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(u_tex, u_sampler, in.v_uv);
+}
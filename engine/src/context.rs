@@ -4,6 +4,7 @@ use super::type_list::{Cons, Nil, Peek, Pluck, PluckInto};
 use super::window::Window;
 use anyhow::{Context as _, Result};
 use std::{marker::PhantomData, time::Instant};
+use winit::event::{Event, WindowEvent};
 use winit::event_loop::ControlFlow as WinitControlFlow;
 
 pub trait Context {
@@ -163,6 +164,9 @@ where
     fn run(mut self) -> Result<()> {
         let event_loop = {
             let window: &mut Window = self.systems_mut().peek_mut();
+            if window.is_headless() {
+                return self.run_headless();
+            }
             window.take_event_loop().expect("none event loop in window")
         };
 
@@ -171,6 +175,14 @@ where
                 if target.exiting() {
                     return;
                 }
+                if let Event::WindowEvent {
+                    event: WindowEvent::Resized(size),
+                    ..
+                } = event
+                {
+                    let window: &mut Window = self.systems_mut().peek_mut();
+                    window.resize(size.width, size.height);
+                }
                 let input: &mut Input = self.systems_mut().peek_mut();
                 if !input.handle_event(event) {
                     return;
@@ -228,6 +240,28 @@ where
     }
 }
 
+impl<SystemListT, ControlFlowIndexT, WindowIndexT, InputIndexT, IndicesT>
+    ContextObject<SystemListT, (ControlFlowIndexT, WindowIndexT, InputIndexT, IndicesT)>
+where
+    Self: Context,
+    SystemListT: Peek<ControlFlow, ControlFlowIndexT> + Peek<Input, InputIndexT>,
+{
+    /// Steps until a quit is requested, without any events to wait for. There's nothing to keep
+    /// in time with either, so steps run back to back.
+    fn run_headless(&mut self) -> Result<()> {
+        loop {
+            self.step()?;
+            let input: &mut Input = self.systems_mut().peek_mut();
+            input.reset();
+            let control_flow: &mut ControlFlow = self.systems_mut().peek_mut();
+            control_flow.sleep_until = None;
+            if control_flow.quit_requested {
+                return self.destroy();
+            }
+        }
+    }
+}
+
 pub trait DependenciesFrom<ContextT, IndicesT>: Sized {
     fn dependencies_from(context: ContextT) -> Self;
 }
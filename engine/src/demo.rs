@@ -0,0 +1,263 @@
+use super::context::ControlFlow;
+use super::input::{Input, NUM_RAW_BUTTONS};
+use super::random::Random;
+use super::system::System;
+use crate::internal_derive::DependenciesFrom;
+use anyhow::{bail, Context, Result};
+use log::info;
+use math::Vec2f;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+pub struct Config {
+    pub mode: DemoMode,
+    /// The level recorded demos start on, written to their header.
+    pub level_index: u32,
+}
+
+pub enum DemoMode {
+    Off,
+    /// Records the input of every update to a file.
+    Record(PathBuf),
+    /// Replaces the input of every update with that recorded in a file, quitting at its end.
+    Play(PathBuf),
+}
+
+/// What a demo needs to start the same way it was recorded.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DemoHeader {
+    pub seed: u64,
+    pub level_index: u32,
+}
+
+impl DemoHeader {
+    /// Reads just the header of a demo file, to set up a context to play it back.
+    pub fn read(path: &Path) -> Result<Self> {
+        let mut reader = BufReader::new(
+            File::open(path).with_context(|| format!("Could not open demo {:?}", path))?,
+        );
+        Self::read_from(&mut reader).with_context(|| format!("Invalid demo {:?}", path))
+    }
+
+    fn read_from(reader: &mut impl Read) -> Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            bail!("Not a demo file.");
+        }
+        let version = read_bytes::<1>(reader)?[0];
+        if version != VERSION {
+            bail!(
+                "Unsupported demo version {}, expected {}.",
+                version,
+                VERSION
+            );
+        }
+        Ok(DemoHeader {
+            seed: u64::from_le_bytes(read_bytes(reader)?),
+            level_index: u32::from_le_bytes(read_bytes(reader)?),
+        })
+    }
+
+    fn write_to(&self, writer: &mut impl Write) -> Result<()> {
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        writer.write_all(&self.seed.to_le_bytes())?;
+        writer.write_all(&self.level_index.to_le_bytes())?;
+        Ok(())
+    }
+}
+
+#[derive(DependenciesFrom)]
+pub struct Dependencies<'context> {
+    config: &'context Config,
+    input: &'context mut Input,
+    random: &'context Random,
+    control_flow: &'context mut ControlFlow,
+}
+
+/// Records input to a demo file, or plays one back in place of the window's events.
+///
+/// Every update is stored as the mouse motion followed by the buttons pressed and released
+/// during it, so playback reproduces exactly what `Input` saw, triggers included. Along with the
+/// random seed in the header, that makes the whole run reproducible.
+pub struct Demo {
+    mode: Mode,
+    /// Which buttons are held, as of the last update.
+    down: Vec<bool>,
+    updates: u64,
+}
+
+enum Mode {
+    Off,
+    Recording(BufWriter<File>),
+    Playing(BufReader<File>),
+    Finished,
+}
+
+impl Demo {
+    fn record(&mut self, input: &Input) -> Result<()> {
+        let Mode::Recording(ref mut writer) = self.mode else {
+            return Ok(());
+        };
+        let mut changes = Vec::new();
+        for (index, down) in self.down.iter_mut().enumerate() {
+            match input.raw_button(index) {
+                Some(true) => {
+                    changes.push(index as u16);
+                    *down = true;
+                }
+                None if *down => {
+                    changes.push(index as u16 | RELEASED);
+                    *down = false;
+                }
+                _ => {}
+            }
+        }
+        let motion = input.mouse_motion();
+        writer.write_all(&motion.x.to_le_bytes())?;
+        writer.write_all(&motion.y.to_le_bytes())?;
+        writer.write_all(&(changes.len() as u16).to_le_bytes())?;
+        for change in changes {
+            writer.write_all(&change.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the input of the current update, returning false at the end of the demo.
+    fn play(&mut self, input: &mut Input) -> Result<bool> {
+        let Mode::Playing(ref mut reader) = self.mode else {
+            return Ok(true);
+        };
+        let motion_x = match read_bytes(reader) {
+            Ok(bytes) => f32::from_le_bytes(bytes),
+            Err(error) if error.kind() == ErrorKind::UnexpectedEof => return Ok(false),
+            Err(error) => return Err(error.into()),
+        };
+        let motion_y = f32::from_le_bytes(read_bytes(reader)?);
+        input.set_mouse_motion(Vec2f::new(motion_x, motion_y));
+
+        let mut pressed = vec![false; NUM_RAW_BUTTONS];
+        let num_changes = u16::from_le_bytes(read_bytes(reader)?);
+        for _ in 0..num_changes {
+            let change = u16::from_le_bytes(read_bytes(reader)?);
+            let index = usize::from(change & !RELEASED);
+            if index >= NUM_RAW_BUTTONS {
+                bail!("Invalid button {} in demo.", index);
+            }
+            self.down[index] = change & RELEASED == 0;
+            pressed[index] = self.down[index];
+        }
+        for (index, (&down, &pressed)) in self.down.iter().zip(&pressed).enumerate() {
+            input.set_raw_button(index, if down { Some(pressed) } else { None });
+        }
+        Ok(true)
+    }
+}
+
+impl<'context> System<'context> for Demo {
+    type Dependencies = Dependencies<'context>;
+
+    fn debug_name() -> &'static str {
+        "demo"
+    }
+
+    fn create(deps: Dependencies) -> Result<Self> {
+        let mode = match deps.config.mode {
+            DemoMode::Off => Mode::Off,
+            DemoMode::Record(ref path) => {
+                let mut writer = BufWriter::new(
+                    File::create(path)
+                        .with_context(|| format!("Could not create demo {:?}", path))?,
+                );
+                DemoHeader {
+                    seed: deps.random.seed(),
+                    level_index: deps.config.level_index,
+                }
+                .write_to(&mut writer)?;
+                info!("Recording demo to {:?}.", path);
+                Mode::Recording(writer)
+            }
+            DemoMode::Play(ref path) => {
+                let mut reader = BufReader::new(
+                    File::open(path).with_context(|| format!("Could not open demo {:?}", path))?,
+                );
+                let header = DemoHeader::read_from(&mut reader)
+                    .with_context(|| format!("Invalid demo {:?}", path))?;
+                if header.seed != deps.random.seed() {
+                    bail!(
+                        "Demo {:?} was recorded with seed {}, but the seed is {}.",
+                        path,
+                        header.seed,
+                        deps.random.seed()
+                    );
+                }
+                info!("Playing demo {:?}.", path);
+                Mode::Playing(reader)
+            }
+        };
+        Ok(Demo {
+            mode,
+            down: vec![false; NUM_RAW_BUTTONS],
+            updates: 0,
+        })
+    }
+
+    fn update(&mut self, deps: Dependencies) -> Result<()> {
+        match self.mode {
+            Mode::Off | Mode::Finished => return Ok(()),
+            Mode::Recording(_) => self.record(deps.input).context("Could not record demo")?,
+            Mode::Playing(_) => {
+                if !self.play(deps.input).context("Could not play demo")? {
+                    info!("Demo finished after {} updates.", self.updates);
+                    self.mode = Mode::Finished;
+                    deps.control_flow.quit_requested = true;
+                    return Ok(());
+                }
+            }
+        }
+        self.updates += 1;
+        Ok(())
+    }
+
+    fn destroy(mut self, _deps: Dependencies) -> Result<()> {
+        if let Mode::Recording(ref mut writer) = self.mode {
+            writer.flush().context("Could not finish demo")?;
+            info!("Recorded {} updates.", self.updates);
+        }
+        Ok(())
+    }
+}
+
+fn read_bytes<const N: usize>(reader: &mut impl Read) -> std::io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+const MAGIC: [u8; 4] = *b"RDMO";
+const VERSION: u8 = 1;
+
+/// Set on recorded button indices for releases rather than presses.
+const RELEASED: u16 = 0x8000;
+
+#[cfg(test)]
+mod test {
+    use super::DemoHeader;
+
+    #[test]
+    fn header_round_trips() {
+        let header = DemoHeader {
+            seed: 0x0123_4567_89ab_cdef,
+            level_index: 7,
+        };
+        let mut bytes = Vec::new();
+        header.write_to(&mut bytes).expect("test: write failed");
+        assert_eq!(
+            DemoHeader::read_from(&mut &bytes[..]).expect("test: read failed"),
+            header
+        );
+        assert!(DemoHeader::read_from(&mut &b"RDMO\x02"[..]).is_err());
+    }
+}
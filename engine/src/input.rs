@@ -157,6 +157,42 @@ impl Input {
         }
     }
 
+    /// The state of a key or mouse button by its raw index (keys first, then mouse buttons):
+    /// `None` if it's up, otherwise whether it was pressed during the current update.
+    pub(crate) fn raw_button(&self, index: usize) -> Option<bool> {
+        let state = if index < NUM_SCAN_CODES {
+            self.keyboard_state[index]
+        } else {
+            self.mouse_button_state[index - NUM_SCAN_CODES]
+        };
+        match state {
+            ButtonState::Up => None,
+            ButtonState::Down(pressed_index) => Some(pressed_index == self.current_update_index),
+        }
+    }
+
+    /// Overrides the state of a button by its raw index, see `raw_button`.
+    pub(crate) fn set_raw_button(&mut self, index: usize, state: Option<bool>) {
+        let state = match state {
+            None => ButtonState::Up,
+            Some(true) => ButtonState::Down(self.current_update_index),
+            Some(false) => ButtonState::Down(0),
+        };
+        if index < NUM_SCAN_CODES {
+            self.keyboard_state[index] = state;
+        } else {
+            self.mouse_button_state[index - NUM_SCAN_CODES] = state;
+        }
+    }
+
+    pub(crate) fn mouse_motion(&self) -> Vec2f {
+        self.mouse_rel
+    }
+
+    pub(crate) fn set_mouse_motion(&mut self, motion: Vec2f) {
+        self.mouse_rel = motion;
+    }
+
     pub fn poll_analog2d(&self, motion: &Analog2d) -> Vec2f {
         match *motion {
             Analog2d::Sum { ref analogs } => analogs
@@ -234,15 +270,16 @@ impl<'context> System<'context> for Input {
     fn update(&mut self, deps: Dependencies) -> Result<()> {
         if self.new_mouse_grabbed != self.mouse_grabbed {
             self.mouse_grabbed = self.new_mouse_grabbed;
-            deps.window
-                .window()
-                .set_cursor_grab(if self.mouse_grabbed {
-                    CursorGrabMode::Locked
-                } else {
-                    CursorGrabMode::None
-                })
-                .ok();
-            deps.window.window().set_cursor_visible(!self.mouse_grabbed);
+            if let Some(window) = deps.window.window() {
+                window
+                    .set_cursor_grab(if self.mouse_grabbed {
+                        CursorGrabMode::Locked
+                    } else {
+                        CursorGrabMode::None
+                    })
+                    .ok();
+                window.set_cursor_visible(!self.mouse_grabbed);
+            }
         }
         Ok(())
     }
@@ -251,6 +288,9 @@ impl<'context> System<'context> for Input {
 const NUM_SCAN_CODES: usize = 512;
 const NUM_MOUSE_BUTTONS: usize = 256;
 
+/// The number of raw button indices, see `Input::raw_button`.
+pub(crate) const NUM_RAW_BUTTONS: usize = NUM_SCAN_CODES + NUM_MOUSE_BUTTONS;
+
 type UpdateIndex = u32;
 
 #[derive(Copy, Clone)]
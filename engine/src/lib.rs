@@ -4,6 +4,7 @@ pub mod context;
 pub mod system;
 pub mod type_list;
 
+mod demo;
 mod entities;
 mod frame_timers;
 mod input;
@@ -12,6 +13,7 @@ mod meshes;
 mod pipeline;
 mod platform;
 mod projections;
+mod random;
 mod renderer;
 mod shaders;
 mod text;
@@ -21,6 +23,7 @@ mod uniforms;
 mod window;
 
 pub use self::context::{Context, ContextBuilder, ControlFlow};
+pub use self::demo::{Config as DemoConfig, Demo, DemoHeader, DemoMode};
 pub use self::entities::{Entities, Entity, EntityId};
 pub use self::frame_timers::{FrameTimerId, FrameTimers};
 pub use self::input::{Analog2d, Gesture, Input, MouseButton};
@@ -28,8 +31,12 @@ pub use self::materials::{MaterialId, MaterialRefMut, Materials};
 pub use self::meshes::{Mesh, MeshId, Meshes};
 pub use self::pipeline::RenderPipeline;
 pub use self::projections::{Projection, Projections};
-pub use self::renderer::Renderer;
-pub use self::shaders::{ShaderConfig, ShaderId, ShaderVertex, Shaders, LIGHTS_COUNT};
+pub use self::random::{Config as RandomConfig, Random};
+pub use self::renderer::{Config as RenderConfig, Renderer};
+pub use self::shaders::{
+    ShaderConfig, ShaderId, ShaderVertex, Shaders, LIGHTS_COUNT, SWITCH_ALT_COUNT,
+    TEXTURE_ALT_COUNT,
+};
 pub use self::system::{InfallibleSystem, System};
 pub use self::text::{Text, TextId, TextRenderer};
 pub use self::tick::{Config as TickConfig, Tick, TickIndex};
@@ -38,7 +45,7 @@ pub use self::uniforms::{
     BufferTextureId, FloatUniformId, Mat4UniformId, Texture2dId, UniformId, Uniforms,
     Vec2fUniformId,
 };
-pub use self::window::{Window, WindowConfig};
+pub use self::window::{list_adapters, Backend, Frame, PowerPreference, Window, WindowConfig};
 
 mod internal_derive {
     pub use super::context::DependenciesFrom;
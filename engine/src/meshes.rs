@@ -213,7 +213,7 @@ impl<'a, IndexDataT> MeshAdder<'a, (), IndexDataT> {
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: None,
             contents: bytemuck::cast_slice(vertices),
-            usage: wgpu::BufferUsages::VERTEX,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
         });
         Ok(MeshAdder {
             vertices: OwnedVertexData(vertex_buffer),
@@ -279,7 +279,7 @@ impl<'a, VertexDataT> MeshAdder<'a, VertexDataT, ()> {
         let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: None,
             contents: bytemuck::cast_slice(indices),
-            usage: wgpu::BufferUsages::INDEX,
+            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
         });
         Ok(MeshAdder {
             indices: IndexData(index_buffer),
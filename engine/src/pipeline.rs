@@ -4,10 +4,12 @@ use super::meshes::MeshId;
 use super::system::InfallibleSystem;
 use super::uniforms::{Mat4UniformId, Uniforms};
 use crate::internal_derive::DependenciesFrom;
+use anyhow::Result;
 use idcontain::IdMapVec;
 use log::{debug, error};
 use math::prelude::*;
 use math::Mat4;
+use std::path::PathBuf;
 
 impl RenderPipeline {
     pub fn modelview(&self) -> Mat4UniformId {
@@ -22,6 +24,20 @@ impl RenderPipeline {
         self.camera = Some(camera);
     }
 
+    /// Writes the next rendered frame to a PNG file.
+    pub fn capture_frame(&mut self, path: PathBuf) {
+        self.capture = Some(path);
+    }
+
+    pub fn capture_pending(&self) -> bool {
+        self.capture.is_some()
+    }
+
+    /// Returns where the last captured frame was written, or why it couldn't be, once.
+    pub fn take_capture_result(&mut self) -> Option<Result<PathBuf>> {
+        self.capture_result.take()
+    }
+
     pub fn attach_model(&mut self, entity: EntityId, mesh: MeshId, material: MaterialId) {
         debug!(
             "Attaching model to entity {:?}: mesh={:?} material={:?}",
@@ -50,6 +66,8 @@ pub struct RenderPipeline {
     pub(crate) root: EntityId,
 
     pub(crate) camera: Option<EntityId>,
+    pub(crate) capture: Option<PathBuf>,
+    pub(crate) capture_result: Option<Result<PathBuf>>,
 }
 
 impl<'context> InfallibleSystem<'context> for RenderPipeline {
@@ -75,6 +93,8 @@ impl<'context> InfallibleSystem<'context> for RenderPipeline {
             projection,
             modelview,
             camera: None,
+            capture: None,
+            capture_result: None,
         }
     }
 
@@ -1,5 +1,7 @@
 use super::entities::{Entities, Entity, EntityId};
 use super::system::InfallibleSystem;
+use super::window::Window;
+use crate::internal_derive::DependenciesFrom;
 use idcontain::IdMapVec;
 use log::{debug, error};
 use math::{self, Mat4, Rad};
@@ -12,8 +14,17 @@ pub struct Projection {
     pub far: f32,
 }
 
+#[derive(DependenciesFrom)]
+pub struct Dependencies<'context> {
+    entities: &'context Entities,
+    window: &'context Window,
+}
+
 pub struct Projections {
     map: IdMapVec<Entity, StoredProjection>,
+    /// The window's aspect ratio when projections were last updated, so that they can follow it
+    /// when it's resized.
+    window_aspect_ratio: f32,
 }
 
 impl Projections {
@@ -52,33 +63,51 @@ impl Projections {
     }
 }
 
+impl Projections {
+    fn remove_dead(&mut self, entities: &Entities) {
+        for &entity in entities.last_removed() {
+            if self.map.remove(entity).is_some() {
+                debug!("Removed projection {:?}.", entity);
+            }
+        }
+    }
+}
+
 impl<'context> InfallibleSystem<'context> for Projections {
-    type Dependencies = &'context Entities;
+    type Dependencies = Dependencies<'context>;
 
     fn debug_name() -> &'static str {
         "projections"
     }
 
-    fn create(_: &'context Entities) -> Self {
+    fn create(deps: Dependencies) -> Self {
         Projections {
             map: IdMapVec::with_capacity(128),
+            window_aspect_ratio: deps.window.aspect_ratio(),
         }
     }
 
-    fn update(&mut self, entities: &Entities) {
-        for &entity in entities.last_removed() {
-            if self.map.remove(entity).is_some() {
-                debug!("Removed projection {:?}.", entity);
+    fn update(&mut self, deps: Dependencies) {
+        self.remove_dead(deps.entities);
+
+        // Scale rather than replace aspect ratios, since projections may correct for the window's.
+        let window_aspect_ratio = deps.window.aspect_ratio();
+        if window_aspect_ratio != self.window_aspect_ratio {
+            let scale = window_aspect_ratio / self.window_aspect_ratio;
+            self.window_aspect_ratio = window_aspect_ratio;
+            for stored in self.map.access_mut().iter_mut() {
+                stored.projection.aspect_ratio *= scale;
+                stored.matrix = stored.projection.into();
             }
         }
     }
 
-    fn teardown(&mut self, entities: &Entities) {
-        self.update(entities);
+    fn teardown(&mut self, deps: Dependencies) {
+        self.remove_dead(deps.entities);
     }
 
-    fn destroy(mut self, entities: &Entities) {
-        self.update(entities);
+    fn destroy(mut self, deps: Dependencies) {
+        self.remove_dead(deps.entities);
         if !self.map.is_empty() {
             error!("Projections leaked, {} instances.", self.map.len());
         }
@@ -0,0 +1,157 @@
+use super::system::InfallibleSystem;
+use crate::internal_derive::DependenciesFrom;
+use log::info;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub struct Config {
+    /// Seeds the random numbers, making runs reproducible. Without one, the seed comes from the
+    /// clock.
+    pub seed: Option<u64>,
+}
+
+#[derive(DependenciesFrom)]
+pub struct Dependencies<'context> {
+    config: &'context Config,
+}
+
+/// Random numbers for the whole game.
+///
+/// Gameplay rolls go through the original's table of 256 numbers, with separate indices for
+/// the simulation (`P_Random`) and for everything else (`M_Random`), so that drawing numbers
+/// for presentation never changes what happens in the game. A seeded xorshift generator covers
+/// anything the table is too coarse for.
+pub struct Random {
+    seed: u64,
+    play_index: u8,
+    menu_index: u8,
+    state: u64,
+}
+
+impl Random {
+    /// Creates the generator directly, without a context.
+    pub fn new(seed: u64) -> Self {
+        Random {
+            seed,
+            play_index: seed as u8,
+            menu_index: (seed >> 8) as u8,
+            // Xorshift gets stuck on zero, so mix the seed first.
+            state: seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns a number between 0 and 255 for the simulation, `P_Random` in the original.
+    pub fn play(&mut self) -> i32 {
+        self.play_index = self.play_index.wrapping_add(1);
+        i32::from(RANDOM_TABLE[usize::from(self.play_index)])
+    }
+
+    /// Returns a number between 0 and 255 for anything outside the simulation, `M_Random` in
+    /// the original.
+    pub fn menu(&mut self) -> i32 {
+        self.menu_index = self.menu_index.wrapping_add(1);
+        i32::from(RANDOM_TABLE[usize::from(self.menu_index)])
+    }
+
+    /// Restarts both table indices, like `M_ClearRandom` does when demos start.
+    pub fn clear(&mut self) {
+        self.play_index = 0;
+        self.menu_index = 0;
+    }
+
+    /// The indices into the table, as `(play, menu)`, for saving and restoring them.
+    pub fn indices(&self) -> (u8, u8) {
+        (self.play_index, self.menu_index)
+    }
+
+    pub fn set_indices(&mut self, (play_index, menu_index): (u8, u8)) {
+        self.play_index = play_index;
+        self.menu_index = menu_index;
+    }
+
+    /// Returns a number from the seeded general generator.
+    pub fn next_u32(&mut self) -> u32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state >> 32) as u32
+    }
+
+    /// Returns a number between 0 (inclusive) and 1 (exclusive) from the general generator.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+}
+
+impl<'context> InfallibleSystem<'context> for Random {
+    type Dependencies = Dependencies<'context>;
+
+    fn debug_name() -> &'static str {
+        "random"
+    }
+
+    fn create(deps: Dependencies) -> Self {
+        let seed = deps.config.seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_nanos() as u64)
+        });
+        info!("Random seed is {}.", seed);
+        Random::new(seed)
+    }
+}
+
+/// The original's `rndtable`.
+const RANDOM_TABLE: [u8; 256] = [
+    0, 8, 109, 220, 222, 241, 149, 107, 75, 248, 254, 140, 16, 66, 74, 21, 211, 47, 80, 242, 154,
+    27, 205, 128, 161, 89, 77, 36, 95, 110, 85, 48, 212, 140, 211, 249, 22, 79, 200, 50, 28, 188,
+    52, 140, 202, 120, 68, 145, 62, 70, 184, 190, 91, 197, 152, 224, 149, 104, 25, 178, 252, 182,
+    202, 182, 141, 197, 4, 81, 181, 242, 145, 42, 39, 227, 156, 198, 225, 193, 219, 93, 122, 175,
+    249, 0, 175, 143, 70, 239, 46, 246, 163, 53, 163, 109, 168, 135, 2, 235, 25, 92, 20, 145, 138,
+    77, 69, 166, 78, 176, 173, 212, 166, 113, 94, 161, 41, 50, 239, 49, 111, 164, 70, 60, 2, 37,
+    171, 75, 136, 156, 11, 56, 42, 146, 138, 229, 73, 146, 77, 61, 98, 196, 135, 106, 63, 197, 195,
+    86, 96, 203, 113, 101, 170, 247, 181, 113, 80, 250, 108, 7, 255, 237, 129, 226, 79, 107, 112,
+    166, 103, 241, 24, 223, 239, 120, 198, 58, 60, 82, 128, 3, 184, 66, 143, 224, 145, 224, 81,
+    206, 163, 45, 63, 90, 168, 114, 59, 33, 159, 95, 28, 139, 123, 98, 125, 196, 15, 70, 194, 253,
+    54, 14, 109, 226, 71, 17, 161, 93, 186, 87, 244, 138, 20, 52, 123, 251, 26, 36, 17, 46, 52,
+    231, 232, 76, 31, 221, 84, 37, 216, 165, 212, 106, 197, 242, 98, 43, 39, 175, 254, 145, 190,
+    84, 118, 222, 187, 136, 120, 163, 236, 249,
+];
+
+#[cfg(test)]
+mod test {
+    use super::Random;
+
+    #[test]
+    fn table_indices_are_independent() {
+        let mut random = Random::new(0);
+        assert_eq!(random.play(), 8);
+        assert_eq!(random.play(), 109);
+        assert_eq!(random.menu(), 8);
+        assert_eq!(random.play(), 220);
+
+        random.clear();
+        assert_eq!(random.indices(), (0, 0));
+        assert_eq!(random.play(), 8);
+        for _ in 0..255 {
+            random.play();
+        }
+        assert_eq!(random.indices().0, 0);
+    }
+
+    #[test]
+    fn seeds_are_reproducible() {
+        let mut first = Random::new(1234);
+        let mut second = Random::new(1234);
+        for _ in 0..100 {
+            assert_eq!(first.next_u32(), second.next_u32());
+            assert_eq!(first.play(), second.play());
+        }
+        let value = first.next_f32();
+        assert!((0.0..1.0).contains(&value));
+        assert_ne!(Random::new(1).next_u32(), Random::new(2).next_u32());
+    }
+}
@@ -8,17 +8,40 @@ use super::text::TextRenderer;
 use super::tick::Tick;
 use super::transforms::Transforms;
 use super::uniforms::Uniforms;
-use super::window::Window;
+use super::window::{Backend, PowerPreference, Window};
 use crate::internal_derive::DependenciesFrom;
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use cgmath::Vector3;
 use log::{error, info};
 use math::{prelude::*, Mat4};
+use wgpu::include_wgsl;
 
-pub(crate) const MSAA_SAMPLE_COUNT: u32 = 4;
+pub struct Config {
+    /// Samples per pixel for multisampling: 1 to turn it off, 2, 4 or 8.
+    pub sample_count: u32,
+    pub present_mode: wgpu::PresentMode,
+    /// The scene's resolution relative to the window's, in `(0, 1]`. Lower scales are upscaled
+    /// without filtering, for a chunky look.
+    pub render_scale: f32,
+    pub backend: Backend,
+    pub power_preference: PowerPreference,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            sample_count: 4,
+            present_mode: wgpu::PresentMode::AutoVsync,
+            render_scale: 1.0,
+            backend: Backend::Auto,
+            power_preference: PowerPreference::Any,
+        }
+    }
+}
 
 #[derive(DependenciesFrom)]
 pub struct Dependencies<'context> {
+    config: &'context Config,
     pipe: &'context mut RenderPipeline,
     meshes: &'context Meshes,
     materials: &'context Materials,
@@ -33,10 +56,211 @@ pub struct Dependencies<'context> {
 
 pub struct Renderer {
     removed: Vec<usize>,
-    _texture: wgpu::Texture,
-    view: wgpu::TextureView,
+    upscale: Upscale,
+    targets: Targets,
+}
+
+/// The attachments the scene is rendered to, which follow the window's size.
+struct Targets {
+    window_size: wgpu::Extent3d,
+    /// Resolved into the scene, unless multisampling is off.
+    _multisampled_texture: Option<wgpu::Texture>,
+    multisampled_view: Option<wgpu::TextureView>,
     _depth_texture: wgpu::Texture,
     depth_view: wgpu::TextureView,
+    /// The scene at a lower resolution, to be upscaled to the frame. At full resolution the scene
+    /// is rendered straight to the frame instead.
+    scaled: Option<ScaledTarget>,
+}
+
+struct ScaledTarget {
+    _texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+}
+
+impl Targets {
+    fn new(window: &Window, upscale: &Upscale, render_scale: f32) -> Self {
+        let window_size = window.size();
+        let scale = |length: u32| ((length as f32 * render_scale).round() as u32).max(1);
+        let size = wgpu::Extent3d {
+            width: scale(window_size.width),
+            height: scale(window_size.height),
+            depth_or_array_layers: 1,
+        };
+        let create_texture = |label, sample_count, format, usage| {
+            window.device().create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size,
+                mip_level_count: 1,
+                sample_count,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage,
+                view_formats: &[],
+            })
+        };
+
+        let multisampled_texture = (window.sample_count() > 1).then(|| {
+            create_texture(
+                "Intermediate attachment",
+                window.sample_count(),
+                window.texture_format(),
+                wgpu::TextureUsages::RENDER_ATTACHMENT,
+            )
+        });
+        let depth_texture = create_texture(
+            "Depth atachment",
+            window.sample_count(),
+            wgpu::TextureFormat::Depth32Float,
+            wgpu::TextureUsages::RENDER_ATTACHMENT,
+        );
+        let scaled = (size != window_size).then(|| {
+            let texture = create_texture(
+                "Scaled scene",
+                1,
+                window.texture_format(),
+                wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            );
+            let view = texture.create_view(&Default::default());
+            let bind_group = upscale.bind_group(window, &view);
+            ScaledTarget {
+                _texture: texture,
+                view,
+                bind_group,
+            }
+        });
+        Targets {
+            window_size,
+            multisampled_view: multisampled_texture
+                .as_ref()
+                .map(|texture| texture.create_view(&Default::default())),
+            _multisampled_texture: multisampled_texture,
+            depth_view: depth_texture.create_view(&Default::default()),
+            _depth_texture: depth_texture,
+            scaled,
+        }
+    }
+}
+
+/// Draws a scaled scene over the whole frame, with nearest-neighbour sampling.
+struct Upscale {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+}
+
+impl Upscale {
+    fn new(window: &Window) -> Self {
+        let device = window.device();
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Upscale bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Upscale pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let shader_module =
+            device.create_shader_module(include_wgsl!("../../assets/shaders/upscale.wgsl"));
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Upscale pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader_module,
+                entry_point: Some("main_vs"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader_module,
+                entry_point: Some("main_fs"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: window.texture_format(),
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::all(),
+                })],
+                compilation_options: Default::default(),
+            }),
+            multiview: None,
+            cache: None,
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Upscale sampler"),
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        Upscale {
+            pipeline,
+            bind_group_layout,
+            sampler,
+        }
+    }
+
+    fn bind_group(&self, window: &Window, view: &wgpu::TextureView) -> wgpu::BindGroup {
+        window
+            .device()
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Upscale bind group"),
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                ],
+            })
+    }
+
+    fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        scaled: &ScaledTarget,
+        target_view: &wgpu::TextureView,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Upscale render pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &scaled.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
 }
 
 impl<'context> System<'context> for Renderer {
@@ -47,40 +271,16 @@ impl<'context> System<'context> for Renderer {
     }
 
     fn create(deps: Dependencies) -> Result<Self> {
-        let texture = deps
-            .window
-            .device()
-            .create_texture(&wgpu::TextureDescriptor {
-                label: Some("Intermediate attachment"),
-                size: deps.window.size(),
-                mip_level_count: 1,
-                sample_count: MSAA_SAMPLE_COUNT,
-                dimension: wgpu::TextureDimension::D2,
-                format: deps.window.texture_format(),
-                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-                view_formats: &[],
-            });
-        let view = texture.create_view(&Default::default());
-        let depth_texture = deps
-            .window
-            .device()
-            .create_texture(&wgpu::TextureDescriptor {
-                label: Some("Depth atachment"),
-                size: deps.window.size(),
-                mip_level_count: 1,
-                sample_count: MSAA_SAMPLE_COUNT,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Depth32Float,
-                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-                view_formats: &[],
-            });
-        let depth_view = depth_texture.create_view(&Default::default());
+        let render_scale = deps.config.render_scale;
+        if !(render_scale > 0.0 && render_scale <= 1.0) {
+            bail!("Render scale must be in (0, 1], not {}", render_scale);
+        }
+        let upscale = Upscale::new(deps.window);
+        let targets = Targets::new(deps.window, &upscale, render_scale);
         Ok(Renderer {
             removed: Vec::with_capacity(32),
-            _texture: texture,
-            view,
-            _depth_texture: depth_texture,
-            depth_view,
+            upscale,
+            targets,
         })
     }
 
@@ -90,6 +290,15 @@ impl<'context> System<'context> for Renderer {
             return Ok(());
         }
 
+        if self.targets.window_size != deps.window.size() {
+            info!(
+                "Resizing render targets to {}x{}.",
+                deps.window.width(),
+                deps.window.height()
+            );
+            self.targets = Targets::new(deps.window, &self.upscale, deps.config.render_scale);
+        }
+
         let pipe = deps.pipe;
 
         // If no camera is given, skip rendering.
@@ -126,8 +335,16 @@ impl<'context> System<'context> for Renderer {
         );
 
         // Render all the models in turn.
-        let surface_texture = deps.window.surface_texture()?;
-        let view = surface_texture.texture.create_view(&Default::default());
+        let frame = deps.window.frame()?;
+        let scene_view = self
+            .targets
+            .scaled
+            .as_ref()
+            .map_or(frame.view(), |scaled| &scaled.view);
+        let (view, resolve_target) = match self.targets.multisampled_view {
+            Some(ref multisampled_view) => (multisampled_view, Some(scene_view)),
+            None => (scene_view, None),
+        };
         let mut encoder = deps
             .window
             .device()
@@ -136,15 +353,15 @@ impl<'context> System<'context> for Renderer {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Main render pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &self.view,
-                    resolve_target: Some(&view),
+                    view,
+                    resolve_target,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
                         store: wgpu::StoreOp::Store,
                     },
                 })],
                 depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.depth_view,
+                    view: &self.targets.depth_view,
                     depth_ops: Some(wgpu::Operations {
                         load: wgpu::LoadOp::Clear(1.0),
                         store: wgpu::StoreOp::Store,
@@ -213,11 +430,27 @@ impl<'context> System<'context> for Renderer {
             self.removed.clear();
         }
 
+        if let Some(ref scaled) = self.targets.scaled {
+            self.upscale.render(&mut encoder, scaled, frame.view());
+        }
+
         // Render text. TODO(cristicbz): text should render itself :(
-        deps.text.render(&mut encoder, &self.view, &view);
+        deps.text.render(&mut encoder, frame.view());
 
         deps.window.queue().submit([encoder.finish()]);
-        surface_texture.present();
+        if let Some(path) = pipe.capture.take() {
+            let result = deps
+                .window
+                .read_frame(&frame)
+                .and_then(|image| {
+                    image
+                        .save(&path)
+                        .with_context(|| format!("Could not write {:?}", path))
+                })
+                .map(|()| path);
+            pipe.capture_result = Some(result);
+        }
+        frame.present();
         Ok(())
     }
 }
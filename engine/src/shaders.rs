@@ -2,7 +2,6 @@ use super::entities::{Entities, Entity, EntityId};
 use super::system::InfallibleSystem;
 use super::window::Window;
 use crate::internal_derive::DependenciesFrom;
-use crate::renderer::MSAA_SAMPLE_COUNT;
 
 use anyhow::Result;
 use idcontain::IdMapVec;
@@ -12,6 +11,10 @@ use std::num::NonZeroU64;
 use std::path::PathBuf;
 
 pub const LIGHTS_COUNT: usize = 256;
+/// Entries at the start of the texture alt buffer, one for each sidedef with a switch texture.
+pub const SWITCH_ALT_COUNT: usize = 32_768;
+/// Entries in the texture alt buffer: the switches, followed by one for the floor of each object.
+pub const TEXTURE_ALT_COUNT: usize = SWITCH_ALT_COUNT + 8_192;
 
 #[derive(Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Copy, Clone)]
 pub struct ShaderId(pub EntityId);
@@ -87,7 +90,7 @@ impl Shaders {
                     bias: wgpu::DepthBiasState::default(),
                 }),
                 multisample: wgpu::MultisampleState {
-                    count: MSAA_SAMPLE_COUNT,
+                    count: window.sample_count(),
                     mask: !0,
                     alpha_to_coverage_enabled: false,
                 },
@@ -214,7 +217,7 @@ impl<'context> InfallibleSystem<'context> for Shaders {
                             ty: wgpu::BufferBindingType::Storage { read_only: true },
                             has_dynamic_offset: false,
                             min_binding_size: NonZeroU64::new(
-                                (32768 * std::mem::size_of::<u32>()) as u64,
+                                (TEXTURE_ALT_COUNT * std::mem::size_of::<u32>()) as u64,
                             ),
                         },
                         count: None,
@@ -1,6 +1,5 @@
 #![cfg_attr(feature = "cargo-clippy", allow(clippy::forget_copy))]
 
-use crate::renderer::MSAA_SAMPLE_COUNT;
 use crate::ShaderVertex;
 
 use super::system::System;
@@ -31,6 +30,8 @@ pub struct TextRenderer {
     pixel_buffer: Vec<u16>,
     pipeline: wgpu::RenderPipeline,
     bind_group_layout: wgpu::BindGroupLayout,
+    /// The window size texts were laid out for.
+    window_size: (u32, u32),
 }
 
 impl TextRenderer {
@@ -82,27 +83,20 @@ impl TextRenderer {
                 },
             ],
         });
-        let (w, h) = (
-            width as f32 / win.width() as f32 * 2.0,
-            height as f32 / win.height() as f32 * 2.0,
-        );
-        let (x, y) = (pos.x * 2.0 - 1.0, 1.0 - pos.y * 2.0 - h);
         let buffer = win
             .device()
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some("Text vertex buffer"),
-                contents: bytemuck::cast_slice(&[
-                    vertex(x, y, 0.0, 1.0),
-                    vertex(x, y + h, 0.0, 0.0),
-                    vertex(x + w, y, 1.0, 1.0),
-                    vertex(x + w, y + h, 1.0, 0.0),
-                ]),
-                usage: wgpu::BufferUsages::VERTEX,
+                contents: bytemuck::cast_slice(&quad(win, pos, width, height)),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
             });
         let text = Text {
             buffer,
             bind_group,
             visible: true,
+            pos,
+            width,
+            height,
         };
         let id = self.slab.insert(text);
         debug!("Created text {:?}.", id);
@@ -122,16 +116,11 @@ impl TextRenderer {
         self.slab.get_mut(id.0)
     }
 
-    pub fn render(
-        &self,
-        encoder: &mut wgpu::CommandEncoder,
-        view: &wgpu::TextureView,
-        target_view: &wgpu::TextureView,
-    ) {
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("Text render pass"),
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                resolve_target: Some(target_view),
+                resolve_target: None,
                 ops: wgpu::Operations {
                     load: wgpu::LoadOp::Load,
                     store: wgpu::StoreOp::Store,
@@ -303,11 +292,9 @@ impl<'context> System<'context> for TextRenderer {
                     conservative: false,
                 },
                 depth_stencil: None,
-                multisample: wgpu::MultisampleState {
-                    count: MSAA_SAMPLE_COUNT,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
+                // Text is drawn straight to the frame, after the scene is resolved and upscaled,
+                // so it stays sharp at any render scale.
+                multisample: wgpu::MultisampleState::default(),
                 fragment: Some(wgpu::FragmentState {
                     module: &shader_module,
                     entry_point: Some("main_fs"),
@@ -328,9 +315,26 @@ impl<'context> System<'context> for TextRenderer {
             pixel_buffer: Vec::new(),
             bind_group_layout,
             pipeline,
+            window_size: (window.width(), window.height()),
         })
     }
 
+    fn update(&mut self, window: &Window) -> Result<()> {
+        // Texts keep their size in pixels, so their quads are laid out again on resize.
+        let window_size = (window.width(), window.height());
+        if window_size != self.window_size {
+            self.window_size = window_size;
+            for text in &self.slab {
+                window.queue().write_buffer(
+                    &text.buffer,
+                    0,
+                    bytemuck::cast_slice(&quad(window, text.pos, text.width, text.height)),
+                );
+            }
+        }
+        Ok(())
+    }
+
     fn destroy(self, _window: &Window) -> Result<()> {
         if !self.slab.is_empty() {
             error!("Text leaked, {} instances.", self.slab.len());
@@ -347,6 +351,9 @@ pub struct Text {
     buffer: wgpu::Buffer,
     bind_group: wgpu::BindGroup,
     visible: bool,
+    pos: Pnt2f,
+    width: u32,
+    height: u32,
 }
 
 impl Text {
@@ -424,3 +431,18 @@ fn vertex(x: f32, y: f32, u: f32, v: f32) -> TextVertex {
         a_uv: [u, v],
     }
 }
+
+/// The corners of a text's quad in clip space, as a triangle strip.
+fn quad(win: &Window, pos: Pnt2f, width: u32, height: u32) -> [TextVertex; 4] {
+    let (w, h) = (
+        width as f32 / win.width() as f32 * 2.0,
+        height as f32 / win.height() as f32 * 2.0,
+    );
+    let (x, y) = (pos.x * 2.0 - 1.0, 1.0 - pos.y * 2.0 - h);
+    [
+        vertex(x, y, 0.0, 1.0),
+        vertex(x, y + h, 0.0, 0.0),
+        vertex(x + w, y, 1.0, 1.0),
+        vertex(x + w, y + h, 1.0, 0.0),
+    ]
+}
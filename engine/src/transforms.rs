@@ -43,6 +43,10 @@ impl Transforms {
         }
     }
 
+    pub fn get_local(&self, entity: EntityId) -> Option<&Trans3> {
+        self.map.get(entity).map(|transform| transform.local)
+    }
+
     pub fn get_local_mut(&mut self, entity: EntityId) -> Option<&mut Trans3> {
         self.map.get_mut(entity).map(|transform| transform.local)
     }
@@ -1,26 +1,70 @@
-use std::sync::Arc;
+use std::sync::{mpsc, Arc};
 
+use super::renderer::Config as RenderConfig;
 use super::system::System;
-use anyhow::{anyhow, Context, Result};
+use crate::internal_derive::DependenciesFrom;
+use anyhow::{anyhow, bail, Context, Result};
+use image::RgbaImage;
+use log::info;
+use std::str::FromStr;
 use winit::event_loop::EventLoop;
+use winit::window::Fullscreen;
 
 pub struct WindowConfig {
     pub width: u32,
     pub height: u32,
     pub title: String,
+    /// Renders to an offscreen texture instead of opening a window, so no display is needed.
+    pub headless: bool,
+    /// Starts in borderless fullscreen on the current monitor.
+    pub fullscreen: bool,
+}
+
+#[derive(DependenciesFrom)]
+pub struct Dependencies<'context> {
+    config: &'context WindowConfig,
+    render_config: &'context RenderConfig,
 }
 
 pub struct Window {
     device: wgpu::Device,
     queue: wgpu::Queue,
-    surface: wgpu::Surface<'static>,
+    target: Target,
     texture_format: wgpu::TextureFormat,
-    window: Arc<winit::window::Window>,
+    sample_count: u32,
     event_loop: Option<EventLoop<()>>,
     width: u32,
     height: u32,
 }
 
+/// What a `Window` presents frames to.
+enum Target {
+    Window {
+        window: Arc<winit::window::Window>,
+        surface: wgpu::Surface<'static>,
+        configuration: wgpu::SurfaceConfiguration,
+    },
+    Offscreen(wgpu::Texture),
+}
+
+/// The texture a frame is rendered to, which is shown once the frame is presented.
+pub struct Frame {
+    view: wgpu::TextureView,
+    surface_texture: Option<wgpu::SurfaceTexture>,
+}
+
+impl Frame {
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    pub fn present(self) {
+        if let Some(surface_texture) = self.surface_texture {
+            surface_texture.present();
+        }
+    }
+}
+
 impl Window {
     pub fn width(&self) -> u32 {
         self.width
@@ -54,25 +98,207 @@ impl Window {
         self.texture_format
     }
 
-    pub fn surface_texture(&self) -> Result<wgpu::SurfaceTexture> {
-        self.surface
-            .get_current_texture()
-            .context("Could not get current texture")
+    /// The number of samples per pixel the scene is rendered with, 1 without multisampling.
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    pub fn frame(&self) -> Result<Frame> {
+        Ok(match self.target {
+            Target::Window {
+                ref surface,
+                ref configuration,
+                ..
+            } => {
+                let surface_texture = match surface.get_current_texture() {
+                    // The surface can go stale before the resize event arrives, so reconfigure
+                    // and try once more.
+                    Err(wgpu::SurfaceError::Outdated | wgpu::SurfaceError::Lost) => {
+                        surface.configure(&self.device, configuration);
+                        surface.get_current_texture()
+                    }
+                    result => result,
+                }
+                .context("Could not get current texture")?;
+                Frame {
+                    view: surface_texture.texture.create_view(&Default::default()),
+                    surface_texture: Some(surface_texture),
+                }
+            }
+            Target::Offscreen(ref texture) => Frame {
+                view: texture.create_view(&Default::default()),
+                surface_texture: None,
+            },
+        })
+    }
+
+    /// The window, unless headless.
+    pub fn window(&self) -> Option<&winit::window::Window> {
+        match self.target {
+            Target::Window { ref window, .. } => Some(window),
+            Target::Offscreen(_) => None,
+        }
+    }
+
+    /// The texture frames are rendered to when headless.
+    pub fn offscreen_texture(&self) -> Option<&wgpu::Texture> {
+        match self.target {
+            Target::Window { .. } => None,
+            Target::Offscreen(ref texture) => Some(texture),
+        }
     }
 
-    pub fn window(&self) -> &winit::window::Window {
-        &self.window
+    /// Copies a rendered frame back from the GPU.
+    pub fn read_frame(&self, frame: &Frame) -> Result<RgbaImage> {
+        let texture = match frame.surface_texture {
+            Some(ref surface_texture) => &surface_texture.texture,
+            None => self
+                .offscreen_texture()
+                .context("Frame has no texture to read")?,
+        };
+        if !texture.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+            bail!("The surface does not allow reading frames back");
+        }
+        let (width, height) = (texture.width(), texture.height());
+        let row_bytes = width * 4;
+        let padded_row_bytes = row_bytes.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Frame readback"),
+            size: u64::from(padded_row_bytes * height),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_bytes),
+                    rows_per_image: None,
+                },
+            },
+            texture.size(),
+        );
+        self.queue.submit([encoder.finish()]);
+
+        let slice = buffer.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .context("Frame readback was dropped")?
+            .context("Could not map frame readback")?;
+
+        let mut pixels = Vec::with_capacity((row_bytes * height) as usize);
+        for row in slice
+            .get_mapped_range()
+            .chunks_exact(padded_row_bytes as usize)
+        {
+            pixels.extend_from_slice(&row[..row_bytes as usize]);
+        }
+        buffer.unmap();
+        if matches!(
+            texture.format(),
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        ) {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        RgbaImage::from_raw(width, height, pixels).context("Frame readback has the wrong size")
+    }
+
+    /// Reconfigures the surface for a new size. Other systems pick the size up on their next
+    /// update.
+    pub(crate) fn resize(&mut self, width: u32, height: u32) {
+        // Minimised windows have no area, so keep the last size until they're restored.
+        if width == 0 || height == 0 || (width, height) == (self.width, self.height) {
+            return;
+        }
+        if let Target::Window {
+            ref surface,
+            ref mut configuration,
+            ..
+        } = self.target
+        {
+            configuration.width = width;
+            configuration.height = height;
+            surface.configure(&self.device, configuration);
+            self.width = width;
+            self.height = height;
+        }
+    }
+
+    pub fn is_fullscreen(&self) -> bool {
+        self.window()
+            .is_some_and(|window| window.fullscreen().is_some())
+    }
+
+    /// Switches between a borderless fullscreen window and a normal one.
+    pub fn set_fullscreen(&self, fullscreen: bool) {
+        if let Some(window) = self.window() {
+            window.set_fullscreen(fullscreen.then_some(Fullscreen::Borderless(None)));
+        }
+    }
+
+    pub fn is_headless(&self) -> bool {
+        matches!(self.target, Target::Offscreen(_))
     }
 
     pub(crate) fn take_event_loop(&mut self) -> Option<EventLoop<()>> {
         self.event_loop.take()
     }
+
+    fn create_headless(config: &WindowConfig, render_config: &RenderConfig) -> Result<Self> {
+        let instance = create_instance(render_config.backend);
+        let (device, adapter, queue) =
+            pollster::block_on(create_device(instance, None, render_config))
+                .context("Could not create WGPU device")?;
+        let texture_format = wgpu::TextureFormat::Rgba8UnormSrgb;
+        let sample_count = check_sample_count(&adapter, texture_format, render_config)?;
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen target"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: texture_format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        Ok(Window {
+            device,
+            queue,
+            target: Target::Offscreen(texture),
+            texture_format,
+            sample_count,
+            event_loop: None,
+            width: config.width,
+            height: config.height,
+        })
+    }
 }
 
 impl<'context> System<'context> for Window {
-    type Dependencies = &'context WindowConfig;
+    type Dependencies = Dependencies<'context>;
 
-    fn create(config: &'context WindowConfig) -> Result<Self> {
+    fn create(deps: Dependencies) -> Result<Self> {
+        let Dependencies {
+            config,
+            render_config,
+        } = deps;
+        if config.headless {
+            return Self::create_headless(config, render_config);
+        }
         let events = EventLoop::new().context("Error creating window")?;
 
         let window = Arc::new(
@@ -80,7 +306,8 @@ impl<'context> System<'context> for Window {
                 .create_window(
                     winit::window::Window::default_attributes()
                         .with_inner_size(winit::dpi::LogicalSize::new(config.width, config.height))
-                        .with_title(&config.title),
+                        .with_title(&config.title)
+                        .with_fullscreen(config.fullscreen.then_some(Fullscreen::Borderless(None))),
                 )
                 .context("Error creating window")?,
         );
@@ -89,24 +316,49 @@ impl<'context> System<'context> for Window {
             .set_cursor_grab(winit::window::CursorGrabMode::Confined)
             .context("Error creating window")?;
 
-        let instance = create_instance();
+        let instance = create_instance(render_config.backend);
         let surface = instance
             .create_surface(window.clone())
             .context("Could not create surface")?;
-        let (device, adapter, queue) = pollster::block_on(create_device(instance, &surface))
-            .context("Could not create WGPU device")?;
+        let (device, adapter, queue) =
+            pollster::block_on(create_device(instance, Some(&surface), render_config))
+                .context("Could not create WGPU device")?;
         let (width, height) = (window.inner_size().width, window.inner_size().height);
-        let configuration = surface
+        let mut configuration = surface
             .get_default_config(&adapter, width, height)
             .ok_or(anyhow!("Could not get default surface configuration"))?;
+        let capabilities = surface.get_capabilities(&adapter);
+        // Lets frames be read back for screenshots, where the surface allows it.
+        if capabilities.usages.contains(wgpu::TextureUsages::COPY_SRC) {
+            configuration.usage |= wgpu::TextureUsages::COPY_SRC;
+        }
+        // The automatic modes fall back to whatever is supported, the others must be checked.
+        let present_mode = render_config.present_mode;
+        if !matches!(
+            present_mode,
+            wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync
+        ) && !capabilities.present_modes.contains(&present_mode)
+        {
+            bail!(
+                "Present mode {:?} is not supported, only {:?}",
+                present_mode,
+                capabilities.present_modes
+            );
+        }
+        configuration.present_mode = present_mode;
+        let sample_count = check_sample_count(&adapter, configuration.format, render_config)?;
         surface.configure(&device, &configuration);
 
         Ok(Window {
             device,
             queue,
-            surface,
             texture_format: configuration.format,
-            window,
+            sample_count,
+            target: Target::Window {
+                window,
+                surface,
+                configuration,
+            },
             event_loop: Some(events),
             width,
             height,
@@ -118,35 +370,187 @@ impl<'context> System<'context> for Window {
     }
 }
 
-fn create_instance() -> wgpu::Instance {
-    wgpu::Instance::new(wgpu::InstanceDescriptor::default())
+/// Checks the configured sample count can be rendered with, both to colour and depth.
+fn check_sample_count(
+    adapter: &wgpu::Adapter,
+    format: wgpu::TextureFormat,
+    render_config: &RenderConfig,
+) -> Result<u32> {
+    let sample_count = render_config.sample_count;
+    if ![1, 2, 4, 8].contains(&sample_count) {
+        bail!("Sample count must be 1, 2, 4 or 8, not {}", sample_count);
+    }
+    // Counts other than 1 and 4 depend on the adapter, and only count with its format features.
+    let adapter_specific = adapter
+        .features()
+        .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES);
+    let supported = (adapter_specific || sample_count == 1 || sample_count == 4)
+        && [format, wgpu::TextureFormat::Depth32Float]
+            .iter()
+            .all(|&format| {
+                adapter
+                    .get_texture_format_features(format)
+                    .flags
+                    .sample_count_supported(sample_count)
+            });
+    if !supported {
+        bail!(
+            "{}x multisampling is not supported by {}",
+            sample_count,
+            adapter.get_info().name
+        );
+    }
+    Ok(sample_count)
+}
+
+/// The graphics API to render with.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// Whichever API suits the platform best.
+    #[default]
+    Auto,
+    Vulkan,
+    Metal,
+    Dx12,
+    Gl,
+    /// A software adapter, for machines without a usable GPU.
+    Software,
+}
+
+impl Backend {
+    fn backends(self) -> wgpu::Backends {
+        match self {
+            Backend::Auto | Backend::Software => wgpu::Backends::all(),
+            Backend::Vulkan => wgpu::Backends::VULKAN,
+            Backend::Metal => wgpu::Backends::METAL,
+            Backend::Dx12 => wgpu::Backends::DX12,
+            Backend::Gl => wgpu::Backends::GL,
+        }
+    }
+}
+
+impl FromStr for Backend {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        Ok(match &*name.to_ascii_lowercase() {
+            "auto" => Backend::Auto,
+            "vulkan" => Backend::Vulkan,
+            "metal" => Backend::Metal,
+            "dx12" => Backend::Dx12,
+            "gl" => Backend::Gl,
+            "software" => Backend::Software,
+            _ => bail!(
+                "Unknown backend {:?}, expected auto, vulkan, metal, dx12, gl or software",
+                name
+            ),
+        })
+    }
+}
+
+/// Which adapter to prefer when there's more than one, like integrated and discrete GPUs.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum PowerPreference {
+    #[default]
+    Any,
+    Low,
+    High,
+}
+
+impl From<PowerPreference> for wgpu::PowerPreference {
+    fn from(preference: PowerPreference) -> Self {
+        match preference {
+            PowerPreference::Any => wgpu::PowerPreference::None,
+            PowerPreference::Low => wgpu::PowerPreference::LowPower,
+            PowerPreference::High => wgpu::PowerPreference::HighPerformance,
+        }
+    }
+}
+
+impl FromStr for PowerPreference {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        Ok(match &*name.to_ascii_lowercase() {
+            "any" => PowerPreference::Any,
+            "low" => PowerPreference::Low,
+            "high" => PowerPreference::High,
+            _ => bail!(
+                "Unknown power preference {:?}, expected any, low or high",
+                name
+            ),
+        })
+    }
+}
+
+/// The adapters available with a backend, to pick from or report.
+pub fn list_adapters(backend: Backend) -> Vec<wgpu::AdapterInfo> {
+    create_instance(backend)
+        .enumerate_adapters(backend.backends())
+        .iter()
+        .map(wgpu::Adapter::get_info)
+        .filter(|info| backend != Backend::Software || info.device_type == wgpu::DeviceType::Cpu)
+        .collect()
+}
+
+fn create_instance(backend: Backend) -> wgpu::Instance {
+    wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: backend.backends(),
+        ..Default::default()
+    })
 }
 
 async fn create_device(
     instance: wgpu::Instance,
-    surface: &wgpu::Surface<'static>,
+    surface: Option<&wgpu::Surface<'static>>,
+    render_config: &RenderConfig,
 ) -> Result<(wgpu::Device, wgpu::Adapter, wgpu::Queue)> {
-    let adapter = instance
-        .request_adapter(&wgpu::RequestAdapterOptions {
-            power_preference: wgpu::PowerPreference::default(),
-            compatible_surface: Some(surface),
-            force_fallback_adapter: false,
+    let request = |force_fallback_adapter| {
+        instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: render_config.power_preference.into(),
+            compatible_surface: surface,
+            force_fallback_adapter,
         })
-        .await
-        .unwrap();
+    };
+    let software = render_config.backend == Backend::Software;
+    let mut adapter = request(software).await;
+    // Without a GPU, rendering offscreen can still fall back to a software adapter.
+    if adapter.is_none() && !software && surface.is_none() {
+        adapter = request(true).await;
+    }
+    let adapter = adapter.ok_or_else(|| {
+        anyhow!(
+            "No {:?} graphics adapter found{}, see --list-adapters",
+            render_config.backend,
+            if surface.is_some() {
+                " which can render to the window"
+            } else {
+                ""
+            }
+        )
+    })?;
+    let info = adapter.get_info();
+    info!(
+        "Using adapter {} ({:?}, {:?}).",
+        info.name, info.backend, info.device_type
+    );
 
     let (device, queue) = adapter
         .request_device(
             &wgpu::DeviceDescriptor {
                 label: None,
-                required_features: wgpu::Features::empty(),
-                required_limits: wgpu::Limits::default(),
+                required_features: adapter.features()
+                    & wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
+                // Software and GL adapters may not meet the default limits, which the renderer
+                // doesn't need, but large textures are still used when the adapter has them.
+                required_limits: wgpu::Limits::downlevel_defaults()
+                    .using_resolution(adapter.limits()),
                 memory_hints: Default::default(),
             },
             None,
         )
         .await
-        .unwrap();
+        .with_context(|| format!("Could not create a device on {}", info.name))?;
 
     Ok((device, adapter, queue))
 }
@@ -0,0 +1,696 @@
+use super::game_shaders::GameShaders;
+use super::level::{ActorSpawn, Level, Shooter, TargetId};
+use super::projectiles::ProjectileKind;
+use super::vertex::{SpriteVertex, SPRITE_QUAD_INDICES};
+use super::wad_system::WadSystem;
+use super::weapons;
+use super::world::World;
+use anyhow::{bail, Result};
+use engine::{
+    DependenciesFrom, Entities, EntityId, MeshId, Meshes, Random, RenderPipeline, Shaders, System,
+    Tick, Transforms, Window,
+};
+use log::{debug, info, warn};
+use math::prelude::*;
+use math::{Pnt3f, Quat, Rad, Sphere, Trans3, Vec3f};
+use serde::{Deserialize, Serialize};
+use wad::types::WadCoord;
+use wad::util::from_wad_height;
+use wad::{ThingStates, WadName, SPAWN_STATE};
+
+#[derive(DependenciesFrom)]
+pub struct Dependencies<'context> {
+    tick: &'context Tick,
+    window: &'context Window,
+    entities: &'context mut Entities,
+    transforms: &'context mut Transforms,
+    meshes: &'context mut Meshes,
+    render: &'context mut RenderPipeline,
+    shaders: &'context Shaders,
+    random: &'context mut Random,
+
+    wad: &'context WadSystem,
+    game_shaders: &'context GameShaders,
+    level: &'context mut Level,
+}
+
+/// Brings the monsters of a level to life: they wake up when they see the player, chase and
+/// attack them, flinch when hurt and die.
+pub struct Actors {
+    actors: Vec<Actor>,
+}
+
+struct Actor {
+    entity: EntityId,
+    mesh: MeshId,
+    target: TargetId,
+    animations: Animations,
+    sprite: WadName,
+    light: u8,
+    /// Where the actor's feet are.
+    position: Pnt3f,
+    radius: f32,
+    height: f32,
+    /// Walking speed, in world units per second.
+    speed: f32,
+    /// Out of 256, the chance of being stunned by damage.
+    pain_chance: i32,
+
+    state: State,
+    frame_index: usize,
+    frame_time: f32,
+    /// Time left until the actor next looks for the player or decides whether to attack.
+    think_time: f32,
+    /// The frame currently in the actor's vertex buffer.
+    shown_frame: Option<ActorFrame>,
+    /// The frame on which the actor last attacked, so that it attacks once per frame.
+    fired_frame: Option<usize>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum State {
+    /// Standing still, waiting to see the player.
+    Idle,
+    /// Just woke up, reacting before giving chase.
+    See,
+    Chase,
+    Melee,
+    Missile,
+    Pain,
+    Death,
+    /// Finished dying, only the corpse is left.
+    Dead,
+}
+
+/// What a saved game keeps of an actor. Its health is saved with the level's targets.
+#[derive(Serialize, Deserialize)]
+pub struct ActorState {
+    position: [f32; 3],
+    state: State,
+    frame_index: usize,
+    frame_time: f32,
+    think_time: f32,
+    fired_frame: Option<usize>,
+}
+
+impl<'context> System<'context> for Actors {
+    type Dependencies = Dependencies<'context>;
+
+    fn debug_name() -> &'static str {
+        "actors"
+    }
+
+    fn create(mut deps: Dependencies) -> Result<Self> {
+        let mut actors = Actors { actors: Vec::new() };
+        actors.spawn(&mut deps)?;
+        Ok(actors)
+    }
+
+    fn update(&mut self, mut deps: Dependencies) -> Result<()> {
+        if deps.level.level_changed() {
+            self.spawn(&mut deps)?;
+        }
+        if deps.level.finished().is_some() {
+            return Ok(());
+        }
+
+        let damaged_targets = deps.level.damaged_targets().to_vec();
+        for damage in damaged_targets {
+            if let Some(actor) = self
+                .actors
+                .iter_mut()
+                .find(|actor| actor.target == damage.target)
+            {
+                actor.hurt(damage.health, deps.random);
+            }
+        }
+
+        let timestep = deps.tick.timestep();
+        let atlas = &deps.game_shaders.level_materials().decor;
+        for actor in &mut self.actors {
+            actor.think(timestep, deps.level, deps.random);
+            actor.animate(timestep);
+
+            if let Some(transform) = deps.transforms.get_local_mut(actor.entity) {
+                transform.disp = actor.position.to_vec();
+            }
+            let frame = actor.frame();
+            if actor.shown_frame != Some(frame) {
+                if let Some(frame_bounds) = atlas.sprite_frame(actor.sprite, frame.frame) {
+                    let light = if frame.fullbright {
+                        deps.level.fullbright_light()
+                    } else {
+                        actor.light
+                    };
+                    let quad = SpriteVertex::quad(frame_bounds, light, 0.0);
+                    if let Some(vertices) = deps
+                        .meshes
+                        .get_mut(actor.mesh)
+                        .and_then(|mesh| mesh.vertices)
+                    {
+                        deps.window
+                            .queue()
+                            .write_buffer(vertices, 0, bytemuck::cast_slice(&quad));
+                    }
+                }
+                actor.shown_frame = Some(frame);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Actors {
+    /// Captures where every actor is and what it's doing, for a saved game.
+    pub fn save(&self) -> Vec<ActorState> {
+        self.actors
+            .iter()
+            .map(|actor| ActorState {
+                position: actor.position.into(),
+                state: actor.state,
+                frame_index: actor.frame_index,
+                frame_time: actor.frame_time,
+                think_time: actor.think_time,
+                fired_frame: actor.fired_frame,
+            })
+            .collect()
+    }
+
+    /// Restores the actors of a saved game, which must have just been spawned for its level.
+    pub fn load(&mut self, states: &[ActorState], transforms: &mut Transforms) -> Result<()> {
+        if states.len() != self.actors.len() {
+            bail!("Saved actors do not match the level.");
+        }
+        for (actor, state) in self.actors.iter_mut().zip(states) {
+            actor.position = state.position.into();
+            actor.state = state.state;
+            actor.frame_index = state.frame_index;
+            actor.frame_time = state.frame_time;
+            actor.think_time = state.think_time;
+            actor.fired_frame = state.fired_frame;
+            actor.shown_frame = None;
+            if let Some(transform) = transforms.get_local_mut(actor.entity) {
+                transform.disp = actor.position.to_vec();
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates the actors for the monsters of the current level. The previous level's actors
+    /// were removed along with its root entity.
+    fn spawn(&mut self, deps: &mut Dependencies) -> Result<()> {
+        self.actors.clear();
+        let spawns = deps.level.actor_spawns().to_vec();
+        let atlas = &deps.game_shaders.level_materials().decor;
+        for ActorSpawn {
+            thing_type,
+            position,
+            light,
+        } in spawns
+        {
+            let Some(meta) = deps.wad.archive.metadata().find_thing(thing_type) else {
+                continue;
+            };
+            let Some(animations) = Animations::new(&meta.states) else {
+                warn!("No spawn state for actor {}.", thing_type);
+                continue;
+            };
+            let (Some(height), Some(health)) = (meta.height, meta.health) else {
+                warn!("No height or health for actor {}.", thing_type);
+                continue;
+            };
+            let first_frame = animations.idle[0];
+            let Some(first_bounds) = atlas.sprite_frame(meta.sprite, first_frame.frame) else {
+                warn!("No sprite {} for actor {}.", meta.sprite, thing_type);
+                continue;
+            };
+
+            let entity = deps.entities.add(deps.level.root(), "actor")?;
+            deps.transforms.attach(
+                entity,
+                Trans3 {
+                    disp: position.to_vec(),
+                    ..Trans3::one()
+                },
+            );
+            let mesh = deps
+                .meshes
+                .add(
+                    deps.entities,
+                    entity,
+                    "actor_mesh",
+                    deps.window.device(),
+                    deps.shaders,
+                )
+                .persistent(
+                    &SpriteVertex::quad(first_bounds, light, 0.0),
+                    deps.window.device(),
+                )?
+                .immutable_indices(&SPRITE_QUAD_INDICES, deps.window.device())?
+                .build()?;
+            deps.render.attach_model(entity, mesh, atlas.material);
+
+            let radius = from_wad_height(meta.radius as WadCoord);
+            let height = from_wad_height(height);
+            // Monsters take a step of `speed` units on each walking frame.
+            let step_time = animations.walk[0].duration;
+            let speed = match meta.speed {
+                Some(speed) if step_time > 0.0 => from_wad_height(speed as WadCoord) / step_time,
+                _ => 0.0,
+            };
+            self.actors.push(Actor {
+                entity,
+                mesh,
+                target: deps.level.add_target(entity, radius, height, health, true),
+                animations,
+                sprite: meta.sprite,
+                light,
+                position,
+                radius,
+                height,
+                speed,
+                pain_chance: meta.pain_chance,
+
+                state: State::Idle,
+                frame_index: 0,
+                frame_time: 0.0,
+                think_time: 0.0,
+                shown_frame: Some(first_frame),
+                fired_frame: None,
+            });
+        }
+        info!("Spawned {} actors.", self.actors.len());
+        Ok(())
+    }
+}
+
+impl Actor {
+    fn frame(&self) -> ActorFrame {
+        let frames = self.state_frames();
+        frames[self.frame_index.min(frames.len() - 1)]
+    }
+
+    fn state_frames(&self) -> &[ActorFrame] {
+        let animations = &self.animations;
+        match self.state {
+            State::Idle => &animations.idle,
+            State::See | State::Chase => &animations.walk,
+            State::Melee => &animations.melee,
+            State::Missile => &animations.missile,
+            State::Pain => &animations.pain,
+            State::Death | State::Dead => &animations.death,
+        }
+    }
+
+    fn enter(&mut self, state: State) {
+        debug!("Actor {:?}: {:?} -> {:?}", self.entity, self.state, state);
+        self.state = state;
+        self.frame_index = 0;
+        self.frame_time = 0.0;
+        self.fired_frame = None;
+        self.think_time = match state {
+            State::See => REACTION_TIME,
+            _ => 0.0,
+        };
+    }
+
+    fn hurt(&mut self, health: i32, random: &mut Random) {
+        if health <= 0 {
+            self.enter(State::Death);
+        } else if random.play() < self.pain_chance {
+            self.enter(State::Pain);
+        } else if self.state == State::Idle {
+            self.enter(State::See);
+        }
+    }
+
+    /// Advances the frames of the current state, moving on to the next state when they run out.
+    fn animate(&mut self, delta_time: f32) {
+        if self.state == State::Dead {
+            return;
+        }
+        self.frame_time += delta_time;
+        loop {
+            let frame_duration = self.frame().duration;
+            if self.frame_time < frame_duration {
+                break;
+            }
+            self.frame_time -= frame_duration;
+            self.frame_index += 1;
+            if self.frame_index < self.state_frames().len() {
+                continue;
+            }
+            match self.state {
+                State::Idle | State::See | State::Chase => self.frame_index = 0,
+                State::Melee | State::Missile | State::Pain => self.enter(State::Chase),
+                State::Death => {
+                    self.frame_index -= 1;
+                    self.state = State::Dead;
+                    return;
+                }
+                State::Dead => return,
+            }
+        }
+    }
+
+    fn think(&mut self, delta_time: f32, level: &mut Level, random: &mut Random) {
+        let Some(player) = level.player_volume() else {
+            return;
+        };
+        self.think_time -= delta_time;
+        match self.state {
+            State::Idle => {
+                if self.think_time <= 0.0 {
+                    self.think_time = LOOK_PERIOD;
+                    if self.sees(level.volume(), player.center) {
+                        info!("Actor {:?} saw the player.", self.entity);
+                        self.enter(State::See);
+                    }
+                }
+            }
+            State::See => {
+                if self.think_time <= 0.0 {
+                    self.enter(State::Chase);
+                }
+            }
+            State::Chase => {
+                let offset = player.center - self.position;
+                let planar = Vec3f::new(offset.x, 0.0, offset.z);
+                let distance = planar.magnitude();
+                let in_melee_range = distance <= self.radius + player.radius + MELEE_REACH;
+                if !in_melee_range {
+                    let motion = planar * (self.speed * delta_time / distance);
+                    self.position = self.walk(level.volume(), motion);
+                }
+
+                if self.think_time <= 0.0 {
+                    self.think_time = ATTACK_CHECK_PERIOD;
+                    let has_melee = !self.animations.melee.is_empty();
+                    let has_missile = !self.animations.missile.is_empty();
+                    let attack = if in_melee_range && has_melee {
+                        Some(State::Melee)
+                    } else if has_missile
+                        && (in_melee_range || random.play() < RANGED_ATTACK_CHANCE)
+                    {
+                        Some(State::Missile)
+                    } else {
+                        None
+                    };
+                    if let Some(attack) = attack {
+                        if self.sees(level.volume(), player.center) {
+                            self.enter(attack);
+                        }
+                    }
+                }
+            }
+            State::Melee | State::Missile => {
+                let attack = self
+                    .frame()
+                    .attack
+                    .filter(|_| self.fired_frame != Some(self.frame_index));
+                if let Some(attack) = attack {
+                    self.fired_frame = Some(self.frame_index);
+                    self.fire(attack, level, player, random);
+                }
+            }
+            State::Pain | State::Death | State::Dead => {}
+        }
+    }
+
+    fn fire(&mut self, attack: Attack, level: &mut Level, player: Sphere, random: &mut Random) {
+        match attack {
+            Attack::Melee {
+                dice,
+                multiplier,
+                missile,
+            } => {
+                let offset = player.center - self.position;
+                let distance = (offset.x * offset.x + offset.z * offset.z).sqrt();
+                if distance <= self.radius + player.radius + MELEE_REACH {
+                    let damage = (random.play() % dice + 1) * multiplier;
+                    debug!("Actor {:?} hit the player for {}.", self.entity, damage);
+                    level.damage_player(damage as f32);
+                } else if let Some(kind) = missile {
+                    self.fire_missile(kind, level, player);
+                }
+            }
+            Attack::Missile(kind) => self.fire_missile(kind, level, player),
+            Attack::Blast { damage, splash } => {
+                debug!("Actor {:?} blasted the player for {}.", self.entity, damage);
+                level.damage_player(damage as f32);
+                level.explode(player.center, splash);
+            }
+            Attack::Hitscan { pellets } => {
+                let eye = self.eye();
+                let aim = (player.center - eye).normalize();
+                for _ in 0..pellets {
+                    let spread = Rad(weapons::spread(random, ACTOR_SPREAD_SHIFT));
+                    let direction = Quat::from_angle_y(spread).rotate_vector(aim);
+                    let damage = (random.play() % 5 + 1) * 3;
+                    level.fire_hitscan(
+                        Shooter::Target(self.target),
+                        eye,
+                        direction,
+                        ACTOR_MISSILE_RANGE,
+                        damage,
+                    );
+                }
+            }
+        }
+    }
+
+    fn fire_missile(&self, kind: ProjectileKind, level: &mut Level, player: Sphere) {
+        let eye = self.eye();
+        let aim = (player.center - eye).normalize();
+        level.fire_projectile(Shooter::Target(self.target), kind, eye, aim);
+    }
+
+    fn eye(&self) -> Pnt3f {
+        self.position + Vec3f::new(0.0, self.height * 0.5 + EYE_OFFSET, 0.0)
+    }
+
+    fn sees(&self, world: &World, point: Pnt3f) -> bool {
+        let eye = self.eye();
+        let offset = point - eye;
+        let distance = offset.magnitude();
+        distance > 0.0 && world.cast_ray(eye, offset / distance, distance).is_none()
+    }
+
+    /// Moves the actor horizontally by `motion`, sliding along walls, then puts it on the floor.
+    ///
+    /// Like in the original, actors can step up and down `STEP_HEIGHT`, but won't walk off
+    /// higher ledges.
+    fn walk(&self, world: &World, mut motion: Vec3f) -> Pnt3f {
+        let lift = Vec3f::new(0.0, STEP_HEIGHT + self.radius, 0.0);
+        let mut body = Sphere {
+            center: self.position + lift,
+            radius: self.radius,
+        };
+        for _ in 0..MAX_SLIDES {
+            let length = motion.magnitude();
+            if length <= 0.0 {
+                break;
+            }
+            match world
+                .sweep_body(body, motion)
+                .filter(|contact| contact.time < 1.0)
+            {
+                Some(contact) => {
+                    let time = (contact.time - CONTACT_SKIN / length).max(0.0);
+                    body.center += motion * time;
+                    let normal =
+                        Vec3f::new(contact.normal.x, 0.0, contact.normal.z).normalize_or_zero();
+                    motion *= 1.0 - time;
+                    motion -= normal * normal.dot(motion);
+                }
+                None => {
+                    body.center += motion;
+                    break;
+                }
+            }
+        }
+
+        let probe = STEP_HEIGHT * 2.0 + self.radius;
+        match world.cast_ray(body.center, -Vec3f::unit_y(), probe) {
+            Some(distance) => body.center - Vec3f::new(0.0, distance, 0.0),
+            None => self.position,
+        }
+    }
+}
+
+/// The kind of attack an actor makes on a frame of its melee or missile state.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Attack {
+    /// Bullets with the original's `(P_Random() % 5 + 1) * 3` damage each.
+    Hitscan { pellets: usize },
+    /// A close range hit dealing `(P_Random() % dice + 1) * multiplier` damage, or a missile
+    /// fired at the player when they are out of reach.
+    Melee {
+        dice: i32,
+        multiplier: i32,
+        missile: Option<ProjectileKind>,
+    },
+    /// A missile fired at the player.
+    Missile(ProjectileKind),
+    /// Damage dealt straight to the player, followed by an explosion on them, like the
+    /// Arch-Vile's fire.
+    Blast { damage: i32, splash: i32 },
+}
+
+impl Attack {
+    /// Returns the attack made by one of the original's action functions, `None` for actions
+    /// which don't attack (or aren't supported, like the Pain Elemental spawning Lost Souls).
+    fn from_action(action: &str) -> Option<Self> {
+        let melee = |dice, multiplier, missile| Attack::Melee {
+            dice,
+            multiplier,
+            missile,
+        };
+        Some(match action {
+            "PosAttack" | "CPosAttack" => Attack::Hitscan { pellets: 1 },
+            "SPosAttack" => Attack::Hitscan { pellets: 3 },
+            "TroopAttack" => melee(8, 3, Some(ProjectileKind::ImpFireball)),
+            "SargAttack" => melee(10, 4, None),
+            "HeadAttack" => melee(6, 10, Some(ProjectileKind::CacodemonBall)),
+            "BruisAttack" => melee(8, 10, Some(ProjectileKind::BaronBall)),
+            // Lost Souls charge at their target and hurt it when they hit, which is
+            // approximated by a bite.
+            "SkullAttack" => melee(8, 3, None),
+            "SkelFist" => melee(10, 6, None),
+            "SkelMissile" => Attack::Missile(ProjectileKind::Tracer),
+            "FatAttack1" | "FatAttack2" | "FatAttack3" => Attack::Missile(ProjectileKind::FatShot),
+            "BspiAttack" => Attack::Missile(ProjectileKind::ArachnotronPlasma),
+            "CyberAttack" => Attack::Missile(ProjectileKind::Rocket),
+            "VileAttack" => Attack::Blast {
+                damage: 20,
+                splash: 70,
+            },
+            _ => return None,
+        })
+    }
+}
+
+/// A frame of an actor's animation.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct ActorFrame {
+    frame: u8,
+    /// In seconds, infinite for the last frame of a corpse.
+    duration: f32,
+    fullbright: bool,
+    /// The attack the actor makes on this frame.
+    attack: Option<Attack>,
+}
+
+/// The frames of each state of an actor.
+struct Animations {
+    idle: Vec<ActorFrame>,
+    walk: Vec<ActorFrame>,
+    /// Empty for actors without a melee attack.
+    melee: Vec<ActorFrame>,
+    /// Empty for actors without a ranged attack.
+    missile: Vec<ActorFrame>,
+    pain: Vec<ActorFrame>,
+    death: Vec<ActorFrame>,
+}
+
+impl Animations {
+    /// Takes the frames of each state from the thing's state table. Actors which are missing a
+    /// walking, pain or death sequence show their spawn frames instead, and those without a
+    /// spawn sequence can't be animated at all.
+    fn new(states: &ThingStates) -> Option<Self> {
+        let animation = |label: &str| -> Vec<ActorFrame> {
+            states
+                .get(label)
+                .map(|sequence| {
+                    sequence
+                        .frames
+                        .iter()
+                        .map(|frame| ActorFrame {
+                            frame: frame.frame,
+                            duration: frame.duration(),
+                            fullbright: frame.fullbright,
+                            attack: frame.action.as_deref().and_then(Attack::from_action),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        let idle = animation(SPAWN_STATE);
+        if idle.is_empty() {
+            return None;
+        }
+        let or_idle = |frames: Vec<ActorFrame>| {
+            if frames.is_empty() {
+                idle.clone()
+            } else {
+                frames
+            }
+        };
+        Some(Animations {
+            walk: or_idle(animation("see")),
+            melee: animation("melee"),
+            missile: animation("missile"),
+            pain: or_idle(animation("pain")),
+            death: or_idle(animation("death")),
+            idle,
+        })
+    }
+}
+
+/// How often idle actors look for the player.
+const LOOK_PERIOD: f32 = 0.25;
+
+/// Time between seeing the player and giving chase (8 tics in the original).
+const REACTION_TIME: f32 = 8.0 / 35.0;
+
+/// How often chasing actors decide whether to attack.
+const ATTACK_CHECK_PERIOD: f32 = 0.5;
+
+/// Out of 256, the chance of a ranged attack each time a chasing actor decides.
+const RANGED_ATTACK_CHANCE: i32 = 100;
+
+/// How far beyond touching the player melee attacks reach.
+const MELEE_REACH: f32 = 0.2;
+
+/// Monster bullets spread up to about 22.5 degrees either way.
+const ACTOR_SPREAD_SHIFT: u32 = 20;
+
+/// Range of monster bullets, 2048 units in the original.
+const ACTOR_MISSILE_RANGE: f32 = 20.48;
+
+/// Height above the middle of an actor that it sees and shoots from.
+const EYE_OFFSET: f32 = 0.08;
+
+/// The highest step an actor can climb, 24 units in the original.
+const STEP_HEIGHT: f32 = 0.24;
+
+/// Distance kept between actors and the walls they walk into.
+const CONTACT_SKIN: f32 = 0.001;
+
+const MAX_SLIDES: usize = 3;
+
+#[cfg(test)]
+mod test {
+    use super::{Attack, ProjectileKind};
+
+    #[test]
+    fn attacks_come_from_listed_actions() {
+        assert_eq!(
+            Attack::from_action("SPosAttack"),
+            Some(Attack::Hitscan { pellets: 3 })
+        );
+        for action in ["FatAttack1", "FatAttack2", "FatAttack3"] {
+            assert_eq!(
+                Attack::from_action(action),
+                Some(Attack::Missile(ProjectileKind::FatShot))
+            );
+        }
+        assert_eq!(
+            Attack::from_action("SkelMissile"),
+            Some(Attack::Missile(ProjectileKind::Tracer))
+        );
+        assert_eq!(Attack::from_action("SkelWhoosh"), None);
+        assert_eq!(Attack::from_action("CPosRefire"), None);
+        assert_eq!(Attack::from_action("FaceTarget"), None);
+    }
+}
@@ -1,25 +1,36 @@
+use super::actors::Actors;
 use super::game_shaders::GameShaders;
 use super::hud::{Bindings as HudBindings, Hud};
+use super::intermission::{Bindings as IntermissionBindings, Intermission};
 use super::level::Level;
-use super::player::{Bindings as PlayerBindings, Config as PlayerConfig, Player};
+use super::player::{
+    Bindings as PlayerBindings, CameraPlacement, Collision as PlayerCollision,
+    Config as PlayerConfig, Player,
+};
+use super::projectiles::Projectiles;
+use super::save::{self, Config as SaveConfig};
+use super::vanilla_demo::{Config as VanillaDemoConfig, DemoSource, VanillaDemo};
 use super::wad_system::{Config as WadConfig, WadSystem};
 use super::SHADER_ROOT;
 use anyhow::Context as _;
-use anyhow::Result;
+use anyhow::{bail, Result};
 use engine::type_list::Peek;
 use engine::{
-    Context, ContextBuilder, Entities, FrameTimers, Input, Materials, Meshes, Projections,
-    RenderPipeline, Renderer, ShaderConfig, Shaders, System, TextRenderer, Tick, TickConfig,
-    Transforms, Uniforms, Window, WindowConfig,
+    Backend, Context, ContextBuilder, Demo, DemoConfig, DemoHeader, DemoMode, Entities,
+    FrameTimers, Input, Materials, Meshes, PowerPreference, Projections, Random, RandomConfig,
+    RenderConfig, RenderPipeline, Renderer, ShaderConfig, Shaders, System, TextRenderer, Tick,
+    TickConfig, Transforms, Uniforms, Window, WindowConfig,
 };
 use std::marker::PhantomData;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 pub trait Game {
     fn run(self) -> Result<()>;
     fn destroy(&mut self) -> Result<()>;
     fn num_levels(&self) -> usize;
     fn load_level(&mut self, level_index: usize) -> Result<()>;
+    /// Renders a single frame from the camera's placement and writes it to a PNG file.
+    fn render(&mut self, placement: CameraPlacement, path: &Path) -> Result<()>;
 }
 
 #[derive(Clone)]
@@ -31,9 +42,58 @@ pub struct GameConfig {
     pub height: u32,
     pub version: &'static str,
     pub initial_level_index: usize,
+    /// Collide the player as the original's cylinder, rather than a sphere.
+    pub cylinder_collision: bool,
+    /// Seeds all the random numbers, for reproducible runs.
+    pub seed: Option<u64>,
+    /// Records the input to a demo file.
+    pub record_demo: Option<PathBuf>,
+    /// Plays back a demo file, starting with its seed and level, or an original demo from a
+    /// `.lmp` file or a `DEMOn` lump.
+    pub play_demo: Option<PathBuf>,
+    /// Runs without a window or display, rendering offscreen.
+    pub headless: bool,
+    /// Starts in borderless fullscreen.
+    pub fullscreen: bool,
+    /// Samples per pixel for multisampling, 1 to turn it off.
+    pub sample_count: u32,
+    pub vsync: bool,
+    /// The scene's resolution relative to the window's.
+    pub render_scale: f32,
+    pub backend: Backend,
+    pub power_preference: PowerPreference,
 }
 
 pub fn create(config: &GameConfig) -> Result<impl Game> {
+    let vanilla_demo = config.play_demo.as_deref().and_then(DemoSource::from_path);
+    let (demo_mode, seed, initial_level_index) = match (&config.play_demo, &config.record_demo) {
+        (Some(_), Some(_)) => bail!("Cannot record a demo while playing one back."),
+        // Original demos pick their level once the WAD is open, and always start from the
+        // same seed.
+        (Some(_), None) if vanilla_demo.is_some() => (
+            DemoMode::Off,
+            Some(config.seed.unwrap_or(0)),
+            config.initial_level_index,
+        ),
+        (Some(path), None) => {
+            let header = DemoHeader::read(path)?;
+            (
+                DemoMode::Play(path.clone()),
+                Some(header.seed),
+                header.level_index as usize,
+            )
+        }
+        (None, Some(path)) => (
+            DemoMode::Record(path.clone()),
+            config.seed,
+            config.initial_level_index,
+        ),
+        (None, None) => (DemoMode::Off, config.seed, config.initial_level_index),
+    };
+    let mut player_config = PlayerConfig::default();
+    if config.cylinder_collision {
+        player_config.collision = PlayerCollision::Cylinder;
+    }
     let context = (|| {
         ContextBuilder::new()
             // Engine configs and systems.
@@ -44,14 +104,34 @@ pub fn create(config: &GameConfig) -> Result<impl Game> {
                 width: config.width,
                 height: config.height,
                 title: format!("Rusty Doom v{}", config.version),
+                headless: config.headless,
+                fullscreen: config.fullscreen,
+            })
+            .inject(RenderConfig {
+                sample_count: config.sample_count,
+                present_mode: if config.vsync {
+                    wgpu::PresentMode::AutoVsync
+                } else {
+                    wgpu::PresentMode::AutoNoVsync
+                },
+                render_scale: config.render_scale,
+                backend: config.backend,
+                power_preference: config.power_preference,
             })
             .inject(ShaderConfig {
                 root_path: SHADER_ROOT.into(),
             })
+            .inject(RandomConfig { seed })
+            .inject(DemoConfig {
+                mode: demo_mode,
+                level_index: initial_level_index as u32,
+            })
             .system(Tick::bind())?
+            .system(Random::bind())?
             .system(FrameTimers::bind())?
             .system(Window::bind())?
             .system(Input::bind())?
+            .system(Demo::bind())?
             .system(Entities::bind())?
             .system(Transforms::bind())?
             .system(Projections::bind())?
@@ -65,16 +145,27 @@ pub fn create(config: &GameConfig) -> Result<impl Game> {
             .inject(WadConfig {
                 wad_path: config.wad_file.clone(),
                 metadata_path: config.metadata_file.clone(),
-                initial_level_index: config.initial_level_index,
+                initial_level_index,
             })
             .inject(HudBindings::default())
+            .inject(IntermissionBindings::default())
             .inject(PlayerBindings::default())
-            .inject(PlayerConfig::default())
+            .inject(player_config)
+            .inject(SaveConfig {
+                dir: save::default_dir(),
+            })
+            .inject(VanillaDemoConfig {
+                source: vanilla_demo,
+            })
             .system(WadSystem::bind())?
             .system(GameShaders::bind())?
             .system(Level::bind())?
-            .system(Hud::bind())?
+            .system(VanillaDemo::bind())?
+            .system(Actors::bind())?
+            .system(Projectiles::bind())?
             .system(Player::bind())?
+            .system(Hud::bind())?
+            .system(Intermission::bind())?
             .system(Renderer::bind())?
             .build()
     })()
@@ -83,17 +174,24 @@ pub fn create(config: &GameConfig) -> Result<impl Game> {
     Ok(GameImpl::new(context))
 }
 
-struct GameImpl<WadIndexT, ContextT>
+struct GameImpl<WadIndexT, PlayerIndexT, PipelineIndexT, ContextT>
 where
-    ContextT: Context + Peek<WadSystem, WadIndexT>,
+    ContextT: Context
+        + Peek<WadSystem, WadIndexT>
+        + Peek<Player, PlayerIndexT>
+        + Peek<RenderPipeline, PipelineIndexT>,
 {
     context: Option<ContextT>,
-    phantom: PhantomData<WadIndexT>,
+    phantom: PhantomData<(WadIndexT, PlayerIndexT, PipelineIndexT)>,
 }
 
-impl<WadIndexT, ContextT> GameImpl<WadIndexT, ContextT>
+impl<WadIndexT, PlayerIndexT, PipelineIndexT, ContextT>
+    GameImpl<WadIndexT, PlayerIndexT, PipelineIndexT, ContextT>
 where
-    ContextT: Context + Peek<WadSystem, WadIndexT>,
+    ContextT: Context
+        + Peek<WadSystem, WadIndexT>
+        + Peek<Player, PlayerIndexT>
+        + Peek<RenderPipeline, PipelineIndexT>,
 {
     fn new(context: ContextT) -> Self {
         Self {
@@ -103,9 +201,13 @@ where
     }
 }
 
-impl<WadIndexT, ContextT> Game for GameImpl<WadIndexT, ContextT>
+impl<WadIndexT, PlayerIndexT, PipelineIndexT, ContextT> Game
+    for GameImpl<WadIndexT, PlayerIndexT, PipelineIndexT, ContextT>
 where
-    ContextT: Context + Peek<WadSystem, WadIndexT>,
+    ContextT: Context
+        + Peek<WadSystem, WadIndexT>
+        + Peek<Player, PlayerIndexT>
+        + Peek<RenderPipeline, PipelineIndexT>,
 {
     fn run(mut self) -> Result<()> {
         self.context
@@ -116,18 +218,36 @@ where
     }
 
     fn num_levels(&self) -> usize {
-        self.context.as_ref().unwrap().peek().archive.num_levels()
+        let wad: &WadSystem = self.context.as_ref().unwrap().peek();
+        wad.archive.num_levels()
     }
 
     fn load_level(&mut self, level_index: usize) -> Result<()> {
         let context = self.context.as_mut().unwrap();
-        let wad = context.peek_mut();
+        let wad: &mut WadSystem = context.peek_mut();
         wad.change_level(level_index);
         context.step().context("during load_level first step")?;
         context.step().context("during load_level second step")?;
         Ok(())
     }
 
+    fn render(&mut self, placement: CameraPlacement, path: &Path) -> Result<()> {
+        let context = self.context.as_mut().unwrap();
+        let player: &mut Player = context.peek_mut();
+        player.place(placement);
+        let pipeline: &mut RenderPipeline = context.peek_mut();
+        pipeline.capture_frame(path.to_owned());
+        // Frames are skipped when the simulation falls behind, so it may take a few steps.
+        for _ in 0..MAX_RENDER_STEPS {
+            context.step().context("during render step")?;
+            let pipeline: &mut RenderPipeline = context.peek_mut();
+            if let Some(result) = pipeline.take_capture_result() {
+                return result.map(drop);
+            }
+        }
+        bail!("No frame was rendered in {} steps.", MAX_RENDER_STEPS)
+    }
+
     fn destroy(&mut self) -> Result<()> {
         if let Some(context) = self.context.as_mut() {
             context.destroy().context("during explicit destroy")?;
@@ -136,9 +256,13 @@ where
     }
 }
 
-impl<WadIndexT, ContextT> Drop for GameImpl<WadIndexT, ContextT>
+impl<WadIndexT, PlayerIndexT, PipelineIndexT, ContextT> Drop
+    for GameImpl<WadIndexT, PlayerIndexT, PipelineIndexT, ContextT>
 where
-    ContextT: Context + Peek<WadSystem, WadIndexT>,
+    ContextT: Context
+        + Peek<WadSystem, WadIndexT>
+        + Peek<Player, PlayerIndexT>
+        + Peek<RenderPipeline, PipelineIndexT>,
 {
     fn drop(&mut self) {
         if let Some(mut context) = self.context.take() {
@@ -146,3 +270,5 @@ where
         }
     }
 }
+
+const MAX_RENDER_STEPS: usize = 16;
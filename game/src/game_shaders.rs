@@ -6,20 +6,51 @@ use super::wad_system::WadSystem;
 use anyhow::Result;
 use engine::{
     DependenciesFrom, Entities, EntityId, FloatUniformId, MaterialId, Materials, ShaderId,
-    ShaderVertex, Shaders, System, Tick, Uniforms, Window, LIGHTS_COUNT,
+    ShaderVertex, Shaders, System, Tick, Uniforms, Window, LIGHTS_COUNT, TEXTURE_ALT_COUNT,
 };
 use log::{error, info};
 use math::Vec2;
-use wad::tex::BoundsLookup;
+use wad::tex::{Bounds, BoundsLookup};
 use wad::types::{COLORMAP_SIZE, MAPPED_PALETTE_SIZE};
 use wad::util::{is_sky_flat, is_untextured};
 use wad::{OpaqueImage as WadOpaqueImage, TransparentImage as WadTransparentImage, WadName};
 
+/// The bullet puff sprite, which is always in the decor atlas since it isn't a level thing.
+pub const PUFF_SPRITE: &[u8] = b"PUFFA0";
+
+/// The explosion sprite shown where rockets and other projectiles hit something.
+pub const EXPLOSION_SPRITE: &[u8] = b"MISLB0";
+
+/// The in-flight sprites of projectiles, which are always in the decor atlas when the WAD has
+/// them (the shareware WAD has no plasma rifle, nor most monsters).
+pub const PROJECTILE_SPRITES: &[&[u8]] = &[
+    b"MISLA1", b"BAL1A0", b"PLSSA0", b"BAL2A0", b"BAL7A1", b"MANFA1", b"FATBA1", b"APLSA0",
+    b"BFS1A0",
+];
+
 pub struct AtlasMaterial {
     pub material: MaterialId,
     pub bounds: BoundsLookup,
 }
 
+impl AtlasMaterial {
+    /// Finds the sprite for a frame facing the player, which has rotation `0` or `1`.
+    pub fn sprite_frame(&self, sprite: WadName, frame: u8) -> Option<&Bounds> {
+        facing_frame_names(sprite, frame).find_map(|name| self.bounds.get(&name))
+    }
+}
+
+/// The names of a sprite frame facing the player, with rotation `0` (the same from all sides) or
+/// `1` (seen from the front).
+fn facing_frame_names(sprite: WadName, frame: u8) -> impl Iterator<Item = WadName> {
+    [b'0', b'1'].iter().filter_map(move |&rotation| {
+        let mut name = sprite;
+        name.push(frame).ok()?;
+        name.push(rotation).ok()?;
+        Some(name)
+    })
+}
+
 pub struct LevelMaterials {
     pub flats: AtlasMaterial,
     pub walls: AtlasMaterial,
@@ -154,7 +185,7 @@ impl<'context> Dependencies<'context> {
             self.entities,
             parent,
             "texture_alt_buffer",
-            32768 * std::mem::size_of::<u32>(),
+            TEXTURE_ALT_COUNT * std::mem::size_of::<u32>(),
         )?;
 
         let static_shader = self.load_shader::<StaticVertex>(
@@ -328,19 +359,49 @@ impl<'context> Dependencies<'context> {
         info!("Building sprite decorations atlas...");
         let (image, bounds) = {
             let wad = &self.wad;
+            let meta = wad.archive.metadata();
+            let mut actor_sprites = wad
+                .level
+                .things
+                .iter()
+                .filter(|thing| meta.is_monster(thing.thing_type))
+                .filter_map(|thing| meta.find_thing(thing.thing_type))
+                .map(|actor| actor.sprite)
+                .collect::<Vec<_>>();
+            actor_sprites.sort_unstable();
+            actor_sprites.dedup();
+            // Actors change frames, so all the frames (facing the player) of their sprite are
+            // needed, not just the first one.
+            let actor_frames = actor_sprites
+                .into_iter()
+                .flat_map(|sprite| (b'A'..=b'Z').map(move |frame| (sprite, frame)))
+                .flat_map(|(sprite, frame)| facing_frame_names(sprite, frame))
+                .filter(|&name| wad.textures.texture(name).is_some());
             let names = wad
                 .level
                 .things
                 .iter()
-                .filter_map(|thing| wad.archive.metadata().find_thing(thing.thing_type))
+                .filter_map(|thing| meta.find_thing(thing.thing_type))
+                .filter(|thing| !meta.is_monster(thing.thing_type))
+                // Decorations and pickups may animate, so all the frames in their state tables
+                // are needed.
                 .flat_map(|decor| {
-                    let mut sprite0 = decor.sprite;
-                    let _ = sprite0.push(decor.sequence.as_bytes()[0]);
-                    let mut sprite1 = sprite0;
-                    let sprite0 = sprite0.push(b'0').ok().map(|_| sprite0);
-                    let sprite1 = sprite1.push(b'1').ok().map(|_| sprite1);
-                    sprite0.into_iter().chain(sprite1)
-                });
+                    decor
+                        .states
+                        .frames()
+                        .map(move |frame| (decor.sprite, frame))
+                })
+                .flat_map(|(sprite, frame)| facing_frame_names(sprite, frame))
+                .filter(|&name| wad.textures.texture(name).is_some())
+                .chain(actor_frames)
+                .chain(WadName::from_bytes(PUFF_SPRITE).ok())
+                .chain(
+                    PROJECTILE_SPRITES
+                        .iter()
+                        .chain(Some(&EXPLOSION_SPRITE))
+                        .filter_map(|&name| WadName::from_bytes(name).ok())
+                        .filter(|&name| wad.textures.texture(name).is_some()),
+                );
             wad.textures.build_texture_atlas(names)
         };
         let texture = self.load_wad_texture(
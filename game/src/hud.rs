@@ -1,9 +1,18 @@
+use super::actors::Actors;
+use super::level::Level;
+use super::player::{Config as PlayerConfig, Player};
+use super::save::{Config as SaveConfig, SaveGame};
 use super::wad_system::WadSystem;
+use anyhow::{bail, Result};
 use engine::{
-    ControlFlow, DependenciesFrom, Gesture, InfallibleSystem, Input, TextId, TextRenderer, Window,
+    ControlFlow, DependenciesFrom, Entities, Gesture, InfallibleSystem, Input, Random,
+    RenderPipeline, TextId, TextRenderer, Tick, Transforms, Window,
 };
+use log::{error, info};
 use math::prelude::*;
 use math::Pnt2f;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
 use winit::keyboard::KeyCode;
 
 pub struct Bindings {
@@ -12,6 +21,10 @@ pub struct Bindings {
     pub previous_level: Gesture,
     pub toggle_mouse: Gesture,
     pub toggle_help: Gesture,
+    pub quick_save: Gesture,
+    pub quick_load: Gesture,
+    pub screenshot: Gesture,
+    pub toggle_fullscreen: Gesture,
 }
 
 impl Default for Bindings {
@@ -31,6 +44,10 @@ impl Default for Bindings {
             ]),
             toggle_mouse: Gesture::KeyTrigger(KeyCode::Backquote),
             toggle_help: Gesture::KeyTrigger(KeyCode::KeyH),
+            quick_save: Gesture::KeyTrigger(KeyCode::F6),
+            quick_load: Gesture::KeyTrigger(KeyCode::F9),
+            screenshot: Gesture::KeyTrigger(KeyCode::F12),
+            toggle_fullscreen: Gesture::KeyTrigger(KeyCode::F11),
         }
     }
 }
@@ -42,8 +59,18 @@ pub struct Dependencies<'context> {
     input: &'context mut Input,
     text: &'context mut TextRenderer,
     control_flow: &'context mut ControlFlow,
+    tick: &'context Tick,
+    entities: &'context mut Entities,
+    transforms: &'context mut Transforms,
+    random: &'context mut Random,
+    pipeline: &'context mut RenderPipeline,
 
+    save_config: &'context SaveConfig,
+    player_config: &'context PlayerConfig,
     wad: &'context mut WadSystem,
+    level: &'context mut Level,
+    actors: &'context mut Actors,
+    player: &'context mut Player,
 }
 
 pub struct Hud {
@@ -51,6 +78,15 @@ pub struct Hud {
     current_help: HelpState,
     prompt_text: TextId,
     help_text: TextId,
+    message: Option<Message>,
+    /// A loaded game, applied once its level has been loaded afresh.
+    pending_load: Option<SaveGame>,
+}
+
+/// A message shown for `MESSAGE_TIME`, like the one for picking up an item.
+struct Message {
+    text: TextId,
+    time_left: f32,
 }
 
 impl<'context> InfallibleSystem<'context> for Hud {
@@ -77,6 +113,8 @@ impl<'context> InfallibleSystem<'context> for Hud {
             help_text,
             mouse_grabbed: true,
             current_help: HelpState::Prompt,
+            message: None,
+            pending_load: None,
         }
     }
 
@@ -99,6 +137,10 @@ impl<'context> InfallibleSystem<'context> for Hud {
             input.set_cursor_grabbed(self.mouse_grabbed);
         }
 
+        if input.poll_gesture(&bindings.toggle_fullscreen) {
+            deps.window.set_fullscreen(!deps.window.is_fullscreen());
+        }
+
         if input.poll_gesture(&bindings.toggle_help) {
             self.current_help = match self.current_help {
                 HelpState::Prompt => {
@@ -117,6 +159,96 @@ impl<'context> InfallibleSystem<'context> for Hud {
             };
         }
 
+        let mut status = None;
+        let level_changed = deps.level.level_changed();
+        if let Some(save) = self.pending_load.take_if(|_| level_changed) {
+            let mut loaded = deps.level.load(&save.level, deps.transforms, deps.entities);
+            if loaded.is_ok() {
+                loaded = deps.actors.load(&save.actors, deps.transforms);
+            }
+            status = Some(match loaded {
+                Ok(()) => {
+                    deps.player
+                        .load(&save.player, deps.transforms, deps.player_config);
+                    deps.random.set_indices(save.random);
+                    info!("Game loaded.");
+                    "Game loaded."
+                }
+                Err(error) => {
+                    error!("Could not load the game: {:#}", error);
+                    "Could not load the game."
+                }
+            });
+        }
+        if input.poll_gesture(&bindings.quick_save) {
+            status = Some(if !deps.player.alive() || deps.level.finished().is_some() {
+                "You can't save now."
+            } else {
+                let save = SaveGame {
+                    level_index: deps.wad.level_index(),
+                    level_name: deps.wad.level_name().to_string(),
+                    random: deps.random.indices(),
+                    player: deps.player.save(deps.transforms),
+                    level: deps.level.save(deps.transforms),
+                    actors: deps.actors.save(),
+                };
+                let path = deps.save_config.quick_save_path();
+                match save.write(&path) {
+                    Ok(()) => {
+                        info!("Game saved to {:?}.", path);
+                        "Game saved."
+                    }
+                    Err(error) => {
+                        error!("Could not save the game: {:#}", error);
+                        "Could not save the game."
+                    }
+                }
+            });
+        } else if input.poll_gesture(&bindings.quick_load) {
+            match quick_load(deps.save_config, deps.wad) {
+                Ok(save) => self.pending_load = Some(save),
+                Err(error) => {
+                    error!("Could not load the game: {:#}", error);
+                    status = Some("Could not load the game.");
+                }
+            }
+        }
+
+        if input.poll_gesture(&bindings.screenshot) {
+            deps.pipeline.capture_frame(screenshot_path());
+        }
+        if let Some(result) = deps.pipeline.take_capture_result() {
+            status = Some(match result {
+                Ok(path) => {
+                    info!("Screenshot saved to {:?}.", path);
+                    "Screenshot saved."
+                }
+                Err(error) => {
+                    error!("Could not save the screenshot: {:#}", error);
+                    "Could not save the screenshot."
+                }
+            });
+        }
+
+        if let Some(message) = self.message.as_mut() {
+            message.time_left -= deps.tick.timestep();
+        }
+        let new_message = match status {
+            Some(status) => Some(status),
+            None => deps.player.take_message(),
+        };
+        if let Some(new_message) = new_message {
+            if let Some(message) = self.message.take() {
+                text.remove(message.text);
+            }
+            self.message = Some(Message {
+                text: text.insert(deps.window, new_message, MESSAGE_POSITION, HELP_PADDING),
+                time_left: MESSAGE_TIME,
+            });
+        } else if let Some(message) = self.message.take_if(|message| message.time_left <= 0.0) {
+            text.remove(message.text);
+        }
+
         if input.poll_gesture(&bindings.next_level) {
             let index = deps.wad.level_index();
             deps.wad.change_level(index + 1);
@@ -129,11 +261,68 @@ impl<'context> InfallibleSystem<'context> for Hud {
     }
 
     fn teardown(&mut self, deps: Dependencies) {
+        if let Some(message) = self.message.take() {
+            deps.text.remove(message.text);
+        }
         deps.text.remove(self.help_text);
         deps.text.remove(self.prompt_text);
     }
 }
 
+/// Reads the quick save and starts loading its level, which the rest of it is applied to once
+/// it's been rebuilt.
+fn quick_load(config: &SaveConfig, wad: &mut WadSystem) -> Result<SaveGame> {
+    let save = SaveGame::read(&config.quick_save_path())?;
+    if save.level_index >= wad.archive.num_levels()
+        || wad.archive.level_lump(save.level_index)?.name().as_ref() != save.level_name
+    {
+        bail!(
+            "Saved level {} ({}) is not in this WAD.",
+            save.level_name,
+            save.level_index
+        );
+    }
+    wad.reload_level(save.level_index);
+    Ok(save)
+}
+
+/// A name for a screenshot taken now, `screenshot-YYYYMMDD-HHMMSS-mmm.png` in UTC, so that
+/// screenshots taken in the same second don't overwrite each other.
+fn screenshot_path() -> PathBuf {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let (days, time) = (seconds / 86_400, seconds % 86_400);
+
+    // Converts days since the epoch to a civil date, after Howard Hinnant's `civil_from_days`.
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    PathBuf::from(format!(
+        "screenshot-{:04}{:02}{:02}-{:02}{:02}{:02}-{:03}.png",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60,
+        since_epoch.subsec_millis()
+    ))
+}
+
 enum HelpState {
     Prompt,
     Shown,
@@ -141,16 +330,24 @@ enum HelpState {
 }
 
 const HELP_PADDING: u32 = 6;
+const MESSAGE_POSITION: Pnt2f = Pnt2f::new(0.0, 0.05);
+const MESSAGE_TIME: f32 = 4.0;
 const PROMPT_TEXT: &str = "WASD and mouse, 'E' to push/use, LB to shoot or 'h' for help.";
 const HELP_TEXT: &str = r"Use WASD to move and the mouse or arrow keys to aim.
 Other keys:
     ESC - to quit
     SPACEBAR - jump
     E - push/interact/use
-    Left Click - shoot (only effect is to trigger gun-activated things)
+    Left Click - shoot (hold to keep firing)
+    1-7 - switch to fist/chainsaw, pistol, shotgun/super shotgun, chaingun, rocket launcher,
+          plasma rifle or BFG
     ` - to toggle mouse grab (backtick)
     f - to toggle fly mode
     c - to toggle clipping (wall collisions)
     Ctrl-N - to change to next level (though using the exit will also do this!)
     Ctrl-P - to change to previous level
+    F6 - quick save
+    F9 - quick load
+    F11 - toggle fullscreen
+    F12 - save a screenshot
     h - toggle this help message";
@@ -0,0 +1,165 @@
+use super::level::{Level, LevelStats};
+use super::wad_system::WadSystem;
+use engine::{
+    DependenciesFrom, Gesture, InfallibleSystem, Input, TextId, TextRenderer, Tick, Window,
+};
+use log::info;
+use math::Pnt2f;
+use wad::WadName;
+use winit::keyboard::KeyCode;
+
+pub struct Bindings {
+    pub skip: Gesture,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings {
+            skip: Gesture::AnyOf(vec![
+                Gesture::KeyTrigger(KeyCode::Space),
+                Gesture::KeyTrigger(KeyCode::Enter),
+                Gesture::KeyTrigger(KeyCode::KeyE),
+            ]),
+        }
+    }
+}
+
+#[derive(DependenciesFrom)]
+pub struct Dependencies<'context> {
+    bindings: &'context Bindings,
+    tick: &'context Tick,
+    window: &'context Window,
+    input: &'context Input,
+    text: &'context mut TextRenderer,
+
+    wad: &'context mut WadSystem,
+    level: &'context Level,
+}
+
+/// Shows the stats of a finished level, then changes to the next level once dismissed.
+pub struct Intermission {
+    shown: Option<Shown>,
+    dismissed: bool,
+}
+
+struct Shown {
+    text: TextId,
+    next_level_index: usize,
+    time_shown: f32,
+}
+
+impl<'context> InfallibleSystem<'context> for Intermission {
+    type Dependencies = Dependencies<'context>;
+
+    fn debug_name() -> &'static str {
+        "intermission"
+    }
+
+    fn create(_deps: Dependencies) -> Self {
+        Intermission {
+            shown: None,
+            dismissed: false,
+        }
+    }
+
+    fn update(&mut self, deps: Dependencies) {
+        if deps.level.level_changed() {
+            self.dismissed = false;
+        }
+
+        if let Some(shown) = self.shown.as_mut() {
+            shown.time_shown += deps.tick.timestep();
+            if shown.time_shown >= MIN_TIME_SHOWN && deps.input.poll_gesture(&deps.bindings.skip) {
+                info!("Intermission dismissed.");
+                deps.text.remove(shown.text);
+                deps.wad.change_level(shown.next_level_index);
+                self.shown = None;
+                self.dismissed = true;
+            }
+        } else if let (Some(next_level_index), false) = (deps.level.finished(), self.dismissed) {
+            let text = stats_text(deps.wad.level_name(), deps.level.stats());
+            info!("Showing intermission:\n{}", text);
+            self.shown = Some(Shown {
+                text: deps
+                    .text
+                    .insert(deps.window, &text, TEXT_POSITION, TEXT_PADDING),
+                next_level_index,
+                time_shown: 0.0,
+            });
+        }
+    }
+
+    fn teardown(&mut self, deps: Dependencies) {
+        if let Some(shown) = self.shown.take() {
+            deps.text.remove(shown.text);
+        }
+    }
+}
+
+fn stats_text(level_name: WadName, stats: &LevelStats) -> String {
+    let level_name = level_name.as_ref().trim_end_matches('\0');
+    let par = match par_time(level_name) {
+        Some(par) => format_time(par as f32),
+        None => "-".to_owned(),
+    };
+    format!(
+        "{} finished\n\n\
+         Kills    {:3}%\n\
+         Items    {:3}%\n\
+         Secrets  {:3}%\n\n\
+         Time  {}\n\
+         Par   {}\n\n\
+         Press SPACE to continue.",
+        level_name,
+        percentage(stats.kills, stats.total_kills),
+        percentage(stats.items, stats.total_items),
+        percentage(stats.secrets, stats.total_secrets),
+        format_time(stats.time),
+        par,
+    )
+}
+
+fn percentage(count: usize, total: usize) -> usize {
+    // Like the original, levels without anything to count show up as 0%.
+    count * 100 / total.max(1)
+}
+
+fn format_time(seconds: f32) -> String {
+    let seconds = seconds as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Returns the par time in seconds for levels named `ExMy` or `MAPxx`.
+fn par_time(level_name: &str) -> Option<u32> {
+    if let Some(map) = level_name.strip_prefix("MAP") {
+        let map = map.parse::<usize>().ok()?;
+        COMMERCIAL_PARS.get(map.checked_sub(1)?).copied()
+    } else {
+        match *level_name.as_bytes() {
+            [b'E', episode, b'M', map] => {
+                let episode = (episode as char).to_digit(10)? as usize;
+                let map = (map as char).to_digit(10)? as usize;
+                EPISODE_PARS
+                    .get(episode.checked_sub(1)?)?
+                    .get(map.checked_sub(1)?)
+                    .copied()
+            }
+            _ => None,
+        }
+    }
+}
+
+const MIN_TIME_SHOWN: f32 = 1.0;
+const TEXT_POSITION: Pnt2f = Pnt2f::new(0.4, 0.3);
+const TEXT_PADDING: u32 = 6;
+
+const EPISODE_PARS: [[u32; 9]; 3] = [
+    [30, 75, 120, 90, 165, 180, 180, 30, 165],
+    [90, 90, 90, 120, 90, 360, 240, 30, 170],
+    [90, 45, 90, 150, 90, 90, 165, 30, 135],
+];
+
+const COMMERCIAL_PARS: [u32; 32] = [
+    30, 90, 120, 120, 90, 150, 120, 120, 270, 90, 210, 150, 150, 150, 210, 150, 420, 150, 210, 150,
+    240, 150, 180, 150, 150, 300, 330, 420, 300, 180, 120, 30,
+];
@@ -0,0 +1,405 @@
+use serde::{Deserialize, Serialize};
+use wad::types::ThingType;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Weapon {
+    Fist,
+    Chainsaw,
+    Pistol,
+    Shotgun,
+    SuperShotgun,
+    Chaingun,
+    RocketLauncher,
+    PlasmaRifle,
+    Bfg,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Ammo {
+    Bullets,
+    Shells,
+    Cells,
+    Rockets,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Key {
+    BlueCard,
+    YellowCard,
+    RedCard,
+    BlueSkull,
+    YellowSkull,
+    RedSkull,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Powerup {
+    Invulnerability,
+    Berserk,
+    Invisibility,
+    RadiationSuit,
+    ComputerMap,
+    LightAmp,
+}
+
+/// What touching a pickup thing gives the player.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Pickup {
+    /// Adds `amount` health up to `max`. Unless `always` is set, it's left alone at full health.
+    Health {
+        amount: i32,
+        max: i32,
+        always: bool,
+    },
+    /// Sets the armour to `amount`, unless the player already has at least as much.
+    Armour {
+        amount: i32,
+        armour_type: u8,
+    },
+    ArmourBonus,
+    MegaSphere,
+    Ammo(Ammo, u32),
+    Backpack,
+    Weapon(Weapon),
+    Key(Key),
+    Powerup(Powerup),
+}
+
+impl Pickup {
+    /// Returns the pickup for a thing type and the message shown when picking it up.
+    pub fn from_thing_type(thing_type: ThingType) -> Option<(Pickup, &'static str)> {
+        Some(match thing_type {
+            2011 => (
+                Pickup::Health {
+                    amount: 10,
+                    max: 100,
+                    always: false,
+                },
+                "Picked up a stimpack.",
+            ),
+            2012 => (
+                Pickup::Health {
+                    amount: 25,
+                    max: 100,
+                    always: false,
+                },
+                "Picked up a medikit.",
+            ),
+            2014 => (
+                Pickup::Health {
+                    amount: 1,
+                    max: 200,
+                    always: true,
+                },
+                "Picked up a health bonus.",
+            ),
+            2013 => (
+                Pickup::Health {
+                    amount: 100,
+                    max: 200,
+                    always: true,
+                },
+                "Supercharge!",
+            ),
+            2018 => (
+                Pickup::Armour {
+                    amount: 100,
+                    armour_type: 1,
+                },
+                "Picked up the armor.",
+            ),
+            2019 => (
+                Pickup::Armour {
+                    amount: 200,
+                    armour_type: 2,
+                },
+                "Picked up the MegaArmor!",
+            ),
+            2015 => (Pickup::ArmourBonus, "Picked up an armor bonus."),
+            83 => (Pickup::MegaSphere, "MegaSphere!"),
+
+            2007 => (Pickup::Ammo(Ammo::Bullets, 10), "Picked up a clip."),
+            2048 => (
+                Pickup::Ammo(Ammo::Bullets, 50),
+                "Picked up a box of bullets.",
+            ),
+            2008 => (Pickup::Ammo(Ammo::Shells, 4), "Picked up 4 shotgun shells."),
+            2049 => (
+                Pickup::Ammo(Ammo::Shells, 20),
+                "Picked up a box of shotgun shells.",
+            ),
+            2010 => (Pickup::Ammo(Ammo::Rockets, 1), "Picked up a rocket."),
+            2046 => (
+                Pickup::Ammo(Ammo::Rockets, 5),
+                "Picked up a box of rockets.",
+            ),
+            2047 => (Pickup::Ammo(Ammo::Cells, 20), "Picked up an energy cell."),
+            17 => (
+                Pickup::Ammo(Ammo::Cells, 100),
+                "Picked up an energy cell pack.",
+            ),
+            8 => (Pickup::Backpack, "Picked up a backpack full of ammo!"),
+
+            2005 => (
+                Pickup::Weapon(Weapon::Chainsaw),
+                "A chainsaw!  Find some meat!",
+            ),
+            2001 => (Pickup::Weapon(Weapon::Shotgun), "You got the shotgun!"),
+            82 => (
+                Pickup::Weapon(Weapon::SuperShotgun),
+                "You got the super shotgun!",
+            ),
+            2002 => (Pickup::Weapon(Weapon::Chaingun), "You got the chaingun!"),
+            2003 => (
+                Pickup::Weapon(Weapon::RocketLauncher),
+                "You got the rocket launcher!",
+            ),
+            2004 => (
+                Pickup::Weapon(Weapon::PlasmaRifle),
+                "You got the plasma gun!",
+            ),
+            2006 => (
+                Pickup::Weapon(Weapon::Bfg),
+                "You got the BFG9000!  Oh, yes.",
+            ),
+
+            5 => (Pickup::Key(Key::BlueCard), "Picked up a blue keycard."),
+            6 => (Pickup::Key(Key::YellowCard), "Picked up a yellow keycard."),
+            13 => (Pickup::Key(Key::RedCard), "Picked up a red keycard."),
+            40 => (Pickup::Key(Key::BlueSkull), "Picked up a blue skull key."),
+            39 => (
+                Pickup::Key(Key::YellowSkull),
+                "Picked up a yellow skull key.",
+            ),
+            38 => (Pickup::Key(Key::RedSkull), "Picked up a red skull key."),
+
+            2022 => (
+                Pickup::Powerup(Powerup::Invulnerability),
+                "Invulnerability!",
+            ),
+            2023 => (Pickup::Powerup(Powerup::Berserk), "Berserk!"),
+            2024 => (
+                Pickup::Powerup(Powerup::Invisibility),
+                "Partial Invisibility",
+            ),
+            2025 => (
+                Pickup::Powerup(Powerup::RadiationSuit),
+                "Radiation Shielding Suit",
+            ),
+            2026 => (Pickup::Powerup(Powerup::ComputerMap), "Computer Area Map"),
+            2045 => (
+                Pickup::Powerup(Powerup::LightAmp),
+                "Light Amplification Visor",
+            ),
+            _ => return None,
+        })
+    }
+}
+
+/// The weapons, ammo, keys and powerups carried by the player.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Inventory {
+    weapons: [bool; NUM_WEAPONS],
+    ammo: [u32; NUM_AMMO],
+    max_ammo: [u32; NUM_AMMO],
+    backpack: bool,
+    keys: [bool; NUM_KEYS],
+    /// Seconds left for each powerup, infinite for the ones lasting the whole level.
+    powerups: [f32; NUM_POWERUPS],
+}
+
+impl Inventory {
+    pub fn new() -> Self {
+        let mut inventory = Inventory {
+            weapons: [false; NUM_WEAPONS],
+            ammo: [0; NUM_AMMO],
+            max_ammo: MAX_AMMO,
+            backpack: false,
+            keys: [false; NUM_KEYS],
+            powerups: [0.0; NUM_POWERUPS],
+        };
+        inventory.weapons[Weapon::Fist as usize] = true;
+        inventory.weapons[Weapon::Pistol as usize] = true;
+        inventory.ammo[Ammo::Bullets as usize] = 50;
+        inventory
+    }
+
+    /// Keys and level-long powerups only last for the level they were found in.
+    pub fn end_level(&mut self) {
+        self.keys = [false; NUM_KEYS];
+        self.powerups = [0.0; NUM_POWERUPS];
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        for time_left in &mut self.powerups {
+            *time_left = (*time_left - delta_time).max(0.0);
+        }
+    }
+
+    pub fn has_powerup(&self, powerup: Powerup) -> bool {
+        self.powerups[powerup as usize] > 0.0
+    }
+
+    pub fn has_weapon(&self, weapon: Weapon) -> bool {
+        self.weapons[weapon as usize]
+    }
+
+    /// Uses up the ammo for one attack with `weapon`, returning false if there isn't enough.
+    pub fn use_ammo(&mut self, weapon: Weapon) -> bool {
+        let Some(ammo) = weapon_ammo(weapon) else {
+            return true;
+        };
+        let current = &mut self.ammo[ammo as usize];
+        let needed = ammo_per_attack(weapon);
+        if *current < needed {
+            return false;
+        }
+        *current -= needed;
+        true
+    }
+
+    /// The weapon to switch to when the current one runs out of ammo, in the original's order of
+    /// preference.
+    pub fn best_weapon(&self) -> Weapon {
+        [
+            Weapon::PlasmaRifle,
+            Weapon::SuperShotgun,
+            Weapon::Chaingun,
+            Weapon::Shotgun,
+            Weapon::Pistol,
+            Weapon::Chainsaw,
+            Weapon::RocketLauncher,
+            Weapon::Bfg,
+        ]
+        .iter()
+        .copied()
+        .find(|&weapon| {
+            self.has_weapon(weapon)
+                && weapon_ammo(weapon)
+                    .is_none_or(|ammo| self.ammo[ammo as usize] >= ammo_per_attack(weapon))
+        })
+        .unwrap_or(Weapon::Fist)
+    }
+
+    /// Adds ammo, returning false if the player can't carry any more of it.
+    pub fn give_ammo(&mut self, ammo: Ammo, amount: u32) -> bool {
+        let (current, max) = (&mut self.ammo[ammo as usize], self.max_ammo[ammo as usize]);
+        if *current >= max {
+            return false;
+        }
+        *current = (*current + amount).min(max);
+        true
+    }
+
+    /// Adds a weapon with two clips of its ammo, returning false if neither was needed.
+    pub fn give_weapon(&mut self, weapon: Weapon) -> bool {
+        let gave_ammo = match weapon_ammo(weapon) {
+            Some(ammo) => self.give_ammo(ammo, CLIP_AMMO[ammo as usize] * 2),
+            None => false,
+        };
+        let new_weapon = !self.weapons[weapon as usize];
+        self.weapons[weapon as usize] = true;
+        new_weapon || gave_ammo
+    }
+
+    /// Doubles the ammo capacity (the first time) and adds a clip of every ammo type.
+    pub fn give_backpack(&mut self) {
+        if !self.backpack {
+            self.backpack = true;
+            for max in &mut self.max_ammo {
+                *max *= 2;
+            }
+        }
+        for ammo in [Ammo::Bullets, Ammo::Shells, Ammo::Cells, Ammo::Rockets] {
+            self.give_ammo(ammo, CLIP_AMMO[ammo as usize]);
+        }
+    }
+
+    /// Adds a key, returning false if the player already had it.
+    pub fn give_key(&mut self, key: Key) -> bool {
+        !std::mem::replace(&mut self.keys[key as usize], true)
+    }
+
+    pub fn give_powerup(&mut self, powerup: Powerup) {
+        self.powerups[powerup as usize] = match powerup {
+            Powerup::Invulnerability => 30.0,
+            Powerup::Invisibility | Powerup::RadiationSuit => 60.0,
+            Powerup::LightAmp => 120.0,
+            Powerup::Berserk | Powerup::ComputerMap => f32::INFINITY,
+        };
+    }
+}
+
+fn weapon_ammo(weapon: Weapon) -> Option<Ammo> {
+    match weapon {
+        Weapon::Fist | Weapon::Chainsaw => None,
+        Weapon::Pistol | Weapon::Chaingun => Some(Ammo::Bullets),
+        Weapon::Shotgun | Weapon::SuperShotgun => Some(Ammo::Shells),
+        Weapon::PlasmaRifle | Weapon::Bfg => Some(Ammo::Cells),
+        Weapon::RocketLauncher => Some(Ammo::Rockets),
+    }
+}
+
+/// The super shotgun fires both barrels and the BFG uses 40 cells a shot.
+fn ammo_per_attack(weapon: Weapon) -> u32 {
+    match weapon {
+        Weapon::SuperShotgun => 2,
+        Weapon::Bfg => 40,
+        _ => 1,
+    }
+}
+
+const NUM_WEAPONS: usize = 9;
+const NUM_AMMO: usize = 4;
+const NUM_KEYS: usize = 6;
+const NUM_POWERUPS: usize = 6;
+
+/// Ammo in a single clip, indexed by `Ammo`.
+const CLIP_AMMO: [u32; NUM_AMMO] = [10, 4, 20, 1];
+/// Ammo capacity without a backpack, indexed by `Ammo`.
+const MAX_AMMO: [u32; NUM_AMMO] = [200, 50, 300, 50];
+
+#[cfg(test)]
+mod test {
+    use super::{Ammo, Inventory, Weapon};
+
+    #[test]
+    fn ammo_is_capped() {
+        let mut inventory = Inventory::new();
+        assert!(inventory.give_ammo(Ammo::Bullets, 500));
+        assert_eq!(inventory.ammo[Ammo::Bullets as usize], 200);
+        assert!(!inventory.give_ammo(Ammo::Bullets, 10));
+
+        inventory.give_backpack();
+        assert_eq!(inventory.ammo[Ammo::Bullets as usize], 210);
+        assert!(inventory.give_ammo(Ammo::Bullets, 500));
+        assert_eq!(inventory.ammo[Ammo::Bullets as usize], 400);
+    }
+
+    #[test]
+    fn weapons_are_picked_up_for_ammo() {
+        let mut inventory = Inventory::new();
+        assert!(inventory.give_weapon(Weapon::Shotgun));
+        assert_eq!(inventory.ammo[Ammo::Shells as usize], 8);
+        assert!(inventory.give_weapon(Weapon::Shotgun));
+
+        inventory.ammo[Ammo::Shells as usize] = 50;
+        assert!(!inventory.give_weapon(Weapon::Shotgun));
+        assert!(!inventory.give_weapon(Weapon::Fist));
+    }
+
+    #[test]
+    fn super_shotgun_and_bfg_use_more_ammo() {
+        let mut inventory = Inventory::new();
+        inventory.give_weapon(Weapon::SuperShotgun);
+        inventory.give_weapon(Weapon::Bfg);
+        inventory.ammo[Ammo::Shells as usize] = 3;
+        assert!(inventory.use_ammo(Weapon::SuperShotgun));
+        assert!(!inventory.use_ammo(Weapon::SuperShotgun));
+        assert_eq!(inventory.ammo[Ammo::Shells as usize], 1);
+
+        inventory.ammo[Ammo::Cells as usize] = 39;
+        assert!(!inventory.use_ammo(Weapon::Bfg));
+        assert_eq!(inventory.best_weapon(), Weapon::Pistol);
+    }
+}
@@ -1,40 +1,78 @@
-use super::game_shaders::{GameShaders, LevelMaterials};
+use super::game_shaders::{GameShaders, LevelMaterials, EXPLOSION_SPRITE, PUFF_SPRITE};
 use super::lights::Lights;
+use super::projectiles::{FiredProjectile, ProjectileKind};
 use super::vertex::{SkyVertex, SpriteVertex, StaticVertex};
 use super::wad_system::WadSystem;
-use super::world::{World, WorldBuilder};
-use anyhow::Result;
+use super::world::{SolidId, World, WorldBuilder};
+use anyhow::{bail, Result};
 use engine::{
-    DependenciesFrom, Entities, EntityId, Meshes, RenderPipeline, Shaders, System, Tick,
-    Transforms, Uniforms, Window,
+    DependenciesFrom, Entities, EntityId, MeshId, Meshes, Random, RenderPipeline, Shaders, System,
+    Tick, Transforms, Uniforms, Window, LIGHTS_COUNT, SWITCH_ALT_COUNT, TEXTURE_ALT_COUNT,
 };
 use log::{debug, error, info, warn};
 use math::prelude::*;
-use math::{vec2, Line2f, Pnt2f, Pnt3f, Rad, Trans3, Vec3f};
+use math::{vec2, Line2f, Pnt2f, Pnt3f, Rad, Sphere, Trans3, Vec3f};
+use serde::{Deserialize, Serialize};
 use std::time::Instant;
 use vec_map::VecMap;
 use wad::tex::Bounds as WadBounds;
+use wad::types::{SectorId, SectorType, ThingType};
+use wad::util::is_secret_sector;
 use wad::{
-    Decor, ExitEffectDef, LevelVisitor, LightInfo, Marker, MoveEffect, ObjectId, SkyPoly, SkyQuad,
-    StaticPoly, StaticQuad, SwitchEffect, TeleportEffect, Trigger, TriggerType, WadMetadata,
+    BlockedBehaviour, ChangeEffect, Decor, ExitEffectDef, Level as WadLevel, LevelVisitor,
+    LightInfo, Marker, MoveEffect, ObjectId, SkyPoly, SkyQuad, StateCursor, StaticPoly, StaticQuad,
+    TeleportEffect, ThingStates, Trigger, TriggerType, WadMetadata, WadName, SPAWN_STATE,
 };
 
 pub struct Level {
     root: EntityId,
     objects: Vec<EntityId>,
     triggers: Vec<Trigger>,
+    /// The index each trigger had when the level was built, to save which ones are left.
+    trigger_indices: Vec<usize>,
     removed: Vec<usize>,
-    effects: VecMap<MoveEffect>,
+    effects: VecMap<ActiveEffect>,
     teleport_effect: Option<TeleportEffect>,
-    switch_effect: Option<SwitchEffect>,
-    exit_trigger: Option<ExitEffectDef>,
+    switch_timers: Vec<SwitchTimer>,
+    pending_changes: Vec<ChangeEffect>,
+    texture_alt_changed: bool,
+    exit_trigger: Option<ExitTrigger>,
+    finished: Option<usize>,
     level_changed: bool,
+    stats: LevelStats,
 
     start_pos: Pnt3f,
     start_yaw: Rad<f32>,
     lights: Lights,
     switches: Vec<SwitchState>,
+    changed_floors: Vec<bool>,
+    sector_types: Vec<SectorType>,
     volume: World,
+
+    player_volume: Option<Sphere>,
+    player_sector: Option<SectorId>,
+
+    pickups: Vec<Option<Pickup>>,
+    touched_pickups: Vec<(usize, ThingType)>,
+    removed_pickups: Vec<EntityId>,
+    player_damage: f32,
+
+    targets: Vec<Option<Target>>,
+    animations: Vec<Animation>,
+    damaged_targets: Vec<TargetDamage>,
+    actor_spawns: Vec<ActorSpawn>,
+    hitscans: Vec<Hitscan>,
+    hits: Vec<(Struck, i32)>,
+    explosions: Vec<Explosion>,
+    fired_projectiles: Vec<FiredProjectile>,
+    flashes: Vec<Flash>,
+    decor_mesh: MeshId,
+    /// Indices of the puff sprite quad in the decor mesh, empty if the sprite is missing.
+    puff_indices: Vec<u32>,
+    /// Indices of the explosion sprite quad in the decor mesh, empty if the sprite is missing.
+    explosion_indices: Vec<u32>,
+    /// A light which is always at full brightness, for sprites which glow.
+    fullbright_light: u8,
 }
 
 #[derive(DependenciesFrom)]
@@ -48,6 +86,7 @@ pub struct Dependencies<'context> {
     tick: &'context Tick,
     transforms: &'context mut Transforms,
     shaders: &'context Shaders,
+    random: &'context mut Random,
 
     game_shaders: &'context GameShaders,
 }
@@ -65,6 +104,232 @@ enum SwitchState {
     On,
 }
 
+/// What the player achieved in the current level, shown on the intermission screen.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct LevelStats {
+    pub kills: usize,
+    pub total_kills: usize,
+    pub items: usize,
+    pub total_items: usize,
+    pub secrets: usize,
+    pub total_secrets: usize,
+    /// Time spent in the level, in seconds.
+    pub time: f32,
+}
+
+/// A thing the player can pick up, with its own entity so that it can be removed on its own.
+struct Pickup {
+    entity: EntityId,
+    thing_type: ThingType,
+    /// The bottom of the sprite, relative to the object it sits on.
+    position: Pnt3f,
+    radius: f32,
+    /// Whether picking it up counts towards the item percentage.
+    counted: bool,
+}
+
+/// A thing which weapons can damage, with its own entity so that it can be removed when killed.
+struct Target {
+    entity: EntityId,
+    /// The bottom of the sprite, relative to the object it sits on.
+    position: Pnt3f,
+    radius: f32,
+    height: f32,
+    health: i32,
+    /// Whether killing it counts towards the kill percentage.
+    counted: bool,
+    /// Whether the level removes its entity when killed, instead of whoever added it.
+    remove_on_death: bool,
+    /// Whether it explodes when killed, like barrels do.
+    explodes: bool,
+    /// The cylinder blocking movement, removed along with the target.
+    solid: Option<SolidId>,
+}
+
+/// A thing sprite stepping through the state table of its type, by changing which of its frame
+/// quads in the decor mesh is drawn.
+struct Animation {
+    mesh: MeshId,
+    states: ThingStates,
+    cursor: StateCursor,
+    quads: Vec<FrameQuad>,
+}
+
+/// The indices in the decor mesh of a frame of an animated thing.
+struct FrameQuad {
+    frame: u8,
+    fullbright: bool,
+    indices: Vec<u32>,
+}
+
+/// Who fired a hitscan, which can't hit its own shooter.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Shooter {
+    Player,
+    Target(TargetId),
+}
+
+/// What a projectile ran into.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Struck {
+    Player,
+    Target(TargetId),
+}
+
+/// Identifies a target added with `Level::add_target`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TargetId(usize);
+
+/// Damage dealt to a target by a hitscan, projectile or explosion during the last update.
+#[derive(Copy, Clone, Debug)]
+pub struct TargetDamage {
+    pub target: TargetId,
+    pub damage: i32,
+    /// The health left after the damage, a target is dead and can't be hit anymore at 0 or less.
+    pub health: i32,
+}
+
+/// A monster thing, which is left to the actors to spawn, animate and move.
+#[derive(Copy, Clone, Debug)]
+pub struct ActorSpawn {
+    pub thing_type: ThingType,
+    pub position: Pnt3f,
+    /// The light of the sector the thing starts in.
+    pub light: u8,
+}
+
+/// A ray fired by a weapon, resolved during the next update.
+struct Hitscan {
+    shooter: Shooter,
+    origin: Pnt3f,
+    direction: Vec3f,
+    range: f32,
+    damage: i32,
+    /// Whether it leaves a puff where it hits, which the BFG's spray doesn't.
+    puffs: bool,
+}
+
+/// An explosion damaging everything around it which it can see, resolved during the next update.
+struct Explosion {
+    position: Pnt3f,
+    damage: i32,
+}
+
+/// A short-lived sprite, shown where a hitscan or projectile hit something.
+///
+/// Expired flashes are hidden rather than removed, and reused by the next ones, so that firing
+/// doesn't create new meshes and index buffers on every tic.
+struct Flash {
+    entity: EntityId,
+    mesh: MeshId,
+    /// The flash is hidden and free to be reused once this runs out.
+    time_left: f32,
+}
+
+#[derive(Copy, Clone, Debug)]
+enum FlashSprite {
+    Puff,
+    Explosion,
+}
+
+/// A move effect which is currently running on an object.
+struct ActiveEffect {
+    /// The remaining legs of the effect.
+    effect: MoveEffect,
+    /// The effect as it was triggered, used to restart repeating effects.
+    initial: MoveEffect,
+    /// The object's offset at the start of the current leg, used to reverse blocked doors.
+    leg_start: Option<f32>,
+}
+
+impl ActiveEffect {
+    fn new(effect: MoveEffect) -> Self {
+        Self {
+            effect,
+            initial: effect,
+            leg_start: None,
+        }
+    }
+}
+
+/// What changed in a level since it was built, for saved games.
+#[derive(Default, Serialize, Deserialize)]
+pub struct LevelState {
+    /// The height offset of every object.
+    offsets: Vec<f32>,
+    /// The running move effects, as object index, remaining effect, initial effect and the
+    /// offset at the start of the current leg.
+    effects: Vec<(usize, MoveEffect, MoveEffect, Option<f32>)>,
+    /// The build indices of the triggers which weren't used up.
+    triggers: Vec<usize>,
+    switches: Vec<bool>,
+    /// The switches waiting to pop back off, as sidedef and time left.
+    switch_timers: Vec<(usize, f32)>,
+    changed_floors: Vec<bool>,
+    sector_types: Vec<SectorType>,
+    /// The floor changes waiting for their move effect to finish.
+    pending_changes: Vec<ChangeEffect>,
+    lights: Vec<LightInfo>,
+    /// The indices of the pickups which were taken.
+    taken_pickups: Vec<usize>,
+    /// The health of every target, including those added by the actors, or `None` once killed.
+    targets: Vec<Option<i32>>,
+    stats: LevelStats,
+}
+
+/// A repeatable switch which is currently on and will pop back off once `time_left` runs out.
+struct SwitchTimer {
+    sidedef: usize,
+    time_left: f32,
+}
+
+/// A pending level exit, delayed so that the exit switch texture gets a chance to be displayed.
+struct ExitTrigger {
+    effect: ExitEffectDef,
+    time_left: f32,
+}
+
+/// Offset of the floor entries in the texture alt buffer, after the ones used by sidedefs.
+const FLAT_ALT_OFFSET: usize = SWITCH_ALT_COUNT;
+
+/// How far above or below the player's center a pickup can be while still being touched.
+const PICKUP_REACH: f32 = 0.56;
+
+/// Monsters are actors, so barrels are the only shootable things the level handles itself.
+const BARREL_HEALTH: i32 = 20;
+
+/// Splash damage of an exploding barrel, the same as a rocket's.
+const BARREL_EXPLOSION_DAMAGE: i32 = 128;
+
+/// How long puffs are shown for, four frames of four tics in the original.
+const PUFF_TIME: f32 = 16.0 / 35.0;
+
+/// How long explosions are shown for, three frames of 8, 6 and 4 tics in the original.
+const EXPLOSION_TIME: f32 = 18.0 / 35.0;
+
+/// Degenerate triangles, drawing nothing, shown by expired flashes.
+const HIDDEN_FLASH_INDICES: [u32; 6] = [0; 6];
+
+/// Puffs are pulled back from the wall they hit, so that they don't clip into it.
+const PUFF_OFFSET: f32 = 0.04;
+
+const TICS_PER_SECOND: f32 = 35.0;
+
+/// Time after which a repeatable switch reverts to its off texture (35 tics in the original).
+const SWITCH_RESET_TIME: f32 = 1.0;
+
+/// Time between activating an exit and actually changing the level.
+const EXIT_DELAY: f32 = 0.5;
+
+/// Time a door waits after being reopened by an obstruction, before trying to close again.
+const BLOCKED_DOOR_WAIT: f32 = 4.0;
+
+/// Crushers deal 10 damage every 4 tics.
+const CRUSH_DAMAGE_PER_SECOND: f32 = 10.0 * 35.0 / 4.0;
+
+/// Crushers slow down by this factor while crushing something.
+const CRUSH_SLOWDOWN: f32 = 8.0;
+
 impl Level {
     pub fn level_changed(&self) -> bool {
         self.level_changed
@@ -86,6 +351,240 @@ impl Level {
         &self.volume
     }
 
+    /// Returns the current type of a sector, which may have been changed by a trigger.
+    pub fn sector_type(&self, sector_id: SectorId) -> SectorType {
+        self.sector_types[sector_id as usize]
+    }
+
+    pub fn stats(&self) -> &LevelStats {
+        &self.stats
+    }
+
+    pub fn record_kill(&mut self) {
+        self.stats.kills += 1;
+    }
+
+    pub fn record_item(&mut self) {
+        self.stats.items += 1;
+    }
+
+    /// Fires a ray along the unit vector `direction`, damaging the first target (or player) it
+    /// hits unless a wall is in the way. It is resolved during the next update.
+    pub fn fire_hitscan(
+        &mut self,
+        shooter: Shooter,
+        origin: Pnt3f,
+        direction: Vec3f,
+        range: f32,
+        damage: i32,
+    ) {
+        self.hitscans.push(Hitscan {
+            shooter,
+            origin,
+            direction,
+            range,
+            damage,
+            puffs: true,
+        });
+    }
+
+    /// Fires a ray of the BFG's spray from the player, which only damages what it hits. It is
+    /// resolved during the next update.
+    pub fn fire_spray(&mut self, origin: Pnt3f, direction: Vec3f, range: f32, damage: i32) {
+        self.hitscans.push(Hitscan {
+            shooter: Shooter::Player,
+            origin,
+            direction,
+            range,
+            damage,
+            puffs: false,
+        });
+    }
+
+    /// Fires a projectile along the unit vector `direction`, spawned by the projectiles during
+    /// their next update.
+    pub fn fire_projectile(
+        &mut self,
+        shooter: Shooter,
+        kind: ProjectileKind,
+        origin: Pnt3f,
+        direction: Vec3f,
+    ) {
+        self.fired_projectiles.push(FiredProjectile {
+            shooter,
+            kind,
+            origin,
+            direction,
+        });
+    }
+
+    /// Returns the projectiles fired since the last call.
+    pub fn take_fired_projectiles(&mut self) -> Vec<FiredProjectile> {
+        std::mem::take(&mut self.fired_projectiles)
+    }
+
+    /// Damages whatever a projectile ran into. It is resolved during the next update.
+    pub fn hit(&mut self, struck: Struck, damage: i32) {
+        self.hits.push((struck, damage));
+    }
+
+    /// Shows an explosion at `position`, which damages everything within `damage` units that it
+    /// can see. It is resolved during the next update.
+    pub fn explode(&mut self, position: Pnt3f, damage: i32) {
+        self.explosions.push(Explosion { position, damage });
+    }
+
+    /// Sweeps `sphere` along `motion` against the player and the targets other than `shooter`.
+    ///
+    /// Returns the first thing struck and when, as a fraction of `motion`.
+    pub fn sweep_targets(
+        &self,
+        transforms: &Transforms,
+        shooter: Shooter,
+        sphere: Sphere,
+        motion: Vec3f,
+    ) -> Option<(f32, Struck)> {
+        let length = motion.magnitude();
+        if length <= 0.0 {
+            return None;
+        }
+        let direction = motion / length;
+        let mut first = None;
+        if let (false, Some(volume)) = (shooter == Shooter::Player, self.player_volume) {
+            let inflated = Sphere {
+                radius: volume.radius + sphere.radius,
+                ..volume
+            };
+            if let Some(distance) = ray_sphere_distance(sphere.center, direction, inflated) {
+                if distance <= length {
+                    first = Some((distance / length, Struck::Player));
+                }
+            }
+        }
+        for (index, target) in self.targets.iter().enumerate() {
+            let Some(target) = target else {
+                continue;
+            };
+            if shooter == Shooter::Target(TargetId(index)) {
+                continue;
+            }
+            let Some(transform) = transforms.get_absolute(target.entity) else {
+                continue;
+            };
+            let base = target.position + transform.disp - Vec3f::new(0.0, sphere.radius, 0.0);
+            let distance = ray_cylinder_distance(
+                sphere.center,
+                direction,
+                base,
+                target.radius + sphere.radius,
+                target.height + 2.0 * sphere.radius,
+            );
+            if let Some(distance) = distance.filter(|&distance| distance <= length) {
+                let time = distance / length;
+                if first.is_none_or(|(first_time, _)| time < first_time) {
+                    first = Some((time, Struck::Target(TargetId(index))));
+                }
+            }
+        }
+        first
+    }
+
+    /// The index of a light which is always at full brightness.
+    pub fn fullbright_light(&self) -> u8 {
+        self.fullbright_light
+    }
+
+    /// Adds a target standing on the origin of `entity`, which hitscans can damage.
+    pub fn add_target(
+        &mut self,
+        entity: EntityId,
+        radius: f32,
+        height: f32,
+        health: i32,
+        counted: bool,
+    ) -> TargetId {
+        self.targets.push(Some(Target {
+            entity,
+            position: Pnt3f::origin(),
+            radius,
+            height,
+            health,
+            counted,
+            remove_on_death: false,
+            explodes: false,
+            solid: None,
+        }));
+        TargetId(self.targets.len() - 1)
+    }
+
+    /// The damage dealt to targets during the last update.
+    pub fn damaged_targets(&self) -> &[TargetDamage] {
+        &self.damaged_targets
+    }
+
+    /// The monsters in the level, to be spawned as actors.
+    pub fn actor_spawns(&self) -> &[ActorSpawn] {
+        &self.actor_spawns
+    }
+
+    /// The sphere the player occupies, as of their last update.
+    pub fn player_volume(&self) -> Option<Sphere> {
+        self.player_volume
+    }
+
+    /// Deals damage to the player, applied when they next take the level's damage.
+    pub fn damage_player(&mut self, damage: f32) {
+        self.player_damage += damage;
+    }
+
+    /// The pickups the player touched during the last update, as pickup index and thing type.
+    pub fn touched_pickups(&self) -> &[(usize, ThingType)] {
+        &self.touched_pickups
+    }
+
+    /// Removes a pickup the player took, counting it as found if it's an item.
+    pub fn remove_pickup(&mut self, index: usize) {
+        if let Some(pickup) = self.pickups[index].take() {
+            if pickup.counted {
+                self.record_item();
+            }
+            self.removed_pickups.push(pickup.entity);
+        }
+    }
+
+    /// Once the player exits, returns the index of the level to change to after the intermission.
+    pub fn finished(&self) -> Option<usize> {
+        self.finished
+    }
+
+    /// Sets the collision volume of the player, which blocks moving ceilings.
+    pub fn set_player_volume(&mut self, volume: Sphere) {
+        self.player_volume = Some(volume);
+    }
+
+    /// Returns the current type of the sector the player is in, or 0 if outside the level.
+    pub fn player_sector_type(&self) -> SectorType {
+        self.player_sector
+            .map_or(0, |sector_id| self.sector_type(sector_id))
+    }
+
+    /// Ends the level as if a normal exit was triggered, used by the E1M8 exit floor.
+    pub fn exit_level(&mut self) {
+        if self.exit_trigger.is_none() {
+            self.exit_trigger = Some(ExitTrigger {
+                effect: ExitEffectDef::Normal,
+                time_left: 0.0,
+            });
+        }
+    }
+
+    /// Returns and resets the damage the level dealt to the player since the last call.
+    pub fn take_player_damage(&mut self) -> u32 {
+        let damage = self.player_damage.floor();
+        self.player_damage -= damage;
+        damage as u32
+    }
+
     pub fn poll_triggers(
         &mut self,
         transform: &Trans3,
@@ -164,7 +663,16 @@ impl Level {
                         "Started effect {} with type {}.",
                         effect_index, trigger.special_type
                     );
-                    self.effects.insert(effect_index, effect);
+                    self.effects.insert(effect_index, ActiveEffect::new(effect));
+                }
+
+                self.pending_changes
+                    .extend_from_slice(&trigger.change_effects);
+
+                for change in &trigger.light_changes {
+                    debug!("Sector {}: light changed.", change.sector_id);
+                    self.lights
+                        .change_sector(change.sector_id, &change.light_info);
                 }
 
                 if let Some(teleport_effect) = trigger.teleport_effect.as_ref() {
@@ -172,7 +680,16 @@ impl Level {
                 }
 
                 if let Some(switch_effect) = trigger.switch_effect.as_ref() {
-                    self.switch_effect = Some(switch_effect.clone());
+                    let sidedef = switch_effect.sidedef as usize;
+                    self.switches[sidedef] = SwitchState::On;
+                    self.texture_alt_changed = true;
+                    self.switch_timers.retain(|timer| timer.sidedef != sidedef);
+                    if !trigger.only_once {
+                        self.switch_timers.push(SwitchTimer {
+                            sidedef,
+                            time_left: SWITCH_RESET_TIME,
+                        });
+                    }
                 }
 
                 if trigger.unimplemented {
@@ -184,13 +701,19 @@ impl Level {
                 }
 
                 if let Some(exit_effect) = trigger.exit_effect {
-                    self.exit_trigger = Some(exit_effect);
+                    if self.exit_trigger.is_none() {
+                        self.exit_trigger = Some(ExitTrigger {
+                            effect: exit_effect,
+                            time_left: EXIT_DELAY,
+                        });
+                    }
                 }
             }
         }
 
         for &i_removed in self.removed.iter().rev() {
             self.triggers.swap_remove(i_removed);
+            self.trigger_indices.swap_remove(i_removed);
         }
         self.removed.clear()
     }
@@ -198,6 +721,403 @@ impl Level {
     pub fn teleport_effect(&mut self) -> Option<TeleportEffect> {
         self.teleport_effect.take()
     }
+
+    pub fn save(&self, transforms: &Transforms) -> LevelState {
+        LevelState {
+            offsets: self
+                .objects
+                .iter()
+                .map(|&object| {
+                    transforms
+                        .get_local(object)
+                        .map_or(0.0, |transform| transform.disp[1])
+                })
+                .collect(),
+            effects: self
+                .effects
+                .iter()
+                .map(|(index, active)| (index, active.effect, active.initial, active.leg_start))
+                .collect(),
+            triggers: self.trigger_indices.clone(),
+            switches: self
+                .switches
+                .iter()
+                .map(|switch| matches!(switch, SwitchState::On))
+                .collect(),
+            switch_timers: self
+                .switch_timers
+                .iter()
+                .map(|timer| (timer.sidedef, timer.time_left))
+                .collect(),
+            changed_floors: self.changed_floors.clone(),
+            sector_types: self.sector_types.clone(),
+            pending_changes: self.pending_changes.clone(),
+            lights: self.lights.save(),
+            taken_pickups: self
+                .pickups
+                .iter()
+                .enumerate()
+                .filter(|(_, pickup)| pickup.is_none())
+                .map(|(index, _)| index)
+                .collect(),
+            targets: self
+                .targets
+                .iter()
+                .map(|target| target.as_ref().map(|target| target.health))
+                .collect(),
+            stats: self.stats,
+        }
+    }
+
+    /// Applies a saved state to the level, which must have just been built and had its actors
+    /// spawned.
+    ///
+    /// Killed targets are removed without exploding or counting as kills again, since the saved
+    /// stats already include them.
+    pub fn load(
+        &mut self,
+        state: &LevelState,
+        transforms: &mut Transforms,
+        entities: &mut Entities,
+    ) -> Result<()> {
+        if state.offsets.len() != self.objects.len()
+            || state.switches.len() != self.switches.len()
+            || state.changed_floors.len() != self.changed_floors.len()
+            || state.sector_types.len() != self.sector_types.len()
+            || state
+                .taken_pickups
+                .iter()
+                .any(|&index| index >= self.pickups.len())
+            || state.targets.len() != self.targets.len()
+        {
+            bail!("Saved level state does not match the level.");
+        }
+        self.lights.load(&state.lights)?;
+
+        for (&object, &offset) in self.objects.iter().zip(&state.offsets) {
+            if let Some(transform) = transforms.get_local_mut(object) {
+                transform.disp[1] = offset;
+            }
+        }
+        self.effects = state
+            .effects
+            .iter()
+            .map(|&(index, effect, initial, leg_start)| {
+                (
+                    index,
+                    ActiveEffect {
+                        effect,
+                        initial,
+                        leg_start,
+                    },
+                )
+            })
+            .collect();
+
+        let mut triggers = Vec::with_capacity(state.triggers.len());
+        let mut trigger_indices = Vec::with_capacity(state.triggers.len());
+        for (trigger, index) in self.triggers.drain(..).zip(self.trigger_indices.drain(..)) {
+            if state.triggers.contains(&index) {
+                triggers.push(trigger);
+                trigger_indices.push(index);
+            }
+        }
+        self.triggers = triggers;
+        self.trigger_indices = trigger_indices;
+
+        for (switch, &on) in self.switches.iter_mut().zip(&state.switches) {
+            *switch = if on {
+                SwitchState::On
+            } else {
+                SwitchState::Off
+            };
+        }
+        self.switch_timers = state
+            .switch_timers
+            .iter()
+            .map(|&(sidedef, time_left)| SwitchTimer { sidedef, time_left })
+            .collect();
+        self.changed_floors.clone_from(&state.changed_floors);
+        self.sector_types.clone_from(&state.sector_types);
+        self.pending_changes.clone_from(&state.pending_changes);
+        self.texture_alt_changed = true;
+
+        for &index in &state.taken_pickups {
+            self.remove_pickup(index);
+        }
+        for (index, &health) in state.targets.iter().enumerate() {
+            if let Some(health) = health {
+                if let Some(target) = self.targets[index].as_mut() {
+                    target.health = health;
+                }
+            } else {
+                self.remove_target(index, entities);
+            }
+        }
+        self.stats = state.stats;
+        Ok(())
+    }
+
+    /// Resolves the hits, hitscans and explosions since the last update and expires old flashes.
+    fn update_hits(&mut self, deps: &mut Dependencies) -> Result<()> {
+        let timestep = deps.tick.timestep();
+        for flash in &mut self.flashes {
+            if flash.time_left <= 0.0 {
+                continue;
+            }
+            flash.time_left -= timestep;
+            if flash.time_left <= 0.0 {
+                write_indices(deps, flash.mesh, &HIDDEN_FLASH_INDICES);
+            }
+        }
+
+        self.damaged_targets.clear();
+        for (struck, damage) in std::mem::take(&mut self.hits) {
+            match struck {
+                Struck::Player => self.player_damage += damage as f32,
+                Struck::Target(TargetId(index)) => {
+                    self.damage_target(index, damage, deps.transforms, deps.entities)
+                }
+            }
+        }
+
+        for hitscan in std::mem::take(&mut self.hitscans) {
+            let wall_distance =
+                self.volume
+                    .cast_ray(hitscan.origin, hitscan.direction, hitscan.range);
+            let mut hit_target = None;
+            let mut hit_player = false;
+            let mut distance = wall_distance.unwrap_or(f32::INFINITY);
+            if let (Shooter::Target(_), Some(volume)) = (hitscan.shooter, self.player_volume) {
+                let player_distance =
+                    ray_sphere_distance(hitscan.origin, hitscan.direction, volume);
+                if let Some(player_distance) = player_distance {
+                    if player_distance < distance && player_distance <= hitscan.range {
+                        distance = player_distance;
+                        hit_player = true;
+                    }
+                }
+            }
+            for (index, target) in self.targets.iter().enumerate() {
+                let Some(target) = target else {
+                    continue;
+                };
+                if hitscan.shooter == Shooter::Target(TargetId(index)) {
+                    continue;
+                }
+                let Some(transform) = deps.transforms.get_absolute(target.entity) else {
+                    continue;
+                };
+                let base = target.position + transform.disp;
+                let target_distance = ray_cylinder_distance(
+                    hitscan.origin,
+                    hitscan.direction,
+                    base,
+                    target.radius,
+                    target.height,
+                );
+                if let Some(target_distance) = target_distance {
+                    if target_distance < distance && target_distance <= hitscan.range {
+                        distance = target_distance;
+                        hit_target = Some(index);
+                        hit_player = false;
+                    }
+                }
+            }
+
+            if hit_player {
+                self.player_damage += hitscan.damage as f32;
+            } else if let Some(index) = hit_target {
+                if hitscan.puffs {
+                    let position = hitscan.origin + hitscan.direction * distance;
+                    self.spawn_flash(deps, FlashSprite::Puff, position)?;
+                }
+                self.damage_target(index, hitscan.damage, deps.transforms, deps.entities);
+            } else if wall_distance.is_some() && hitscan.puffs {
+                let distance = (distance - PUFF_OFFSET).max(0.0);
+                let position = hitscan.origin + hitscan.direction * distance;
+                self.spawn_flash(deps, FlashSprite::Puff, position)?;
+            }
+        }
+
+        // Barrels killed by these explosions queue their own, which go off during the next
+        // update.
+        for explosion in std::mem::take(&mut self.explosions) {
+            self.spawn_flash(deps, FlashSprite::Explosion, explosion.position)?;
+            if explosion.damage <= 0 {
+                continue;
+            }
+            if let Some(volume) = self.player_volume {
+                let damage = self.splash_damage(&explosion, volume.center, volume.radius);
+                self.player_damage += damage as f32;
+            }
+            for index in 0..self.targets.len() {
+                let Some(target) = &self.targets[index] else {
+                    continue;
+                };
+                let Some(transform) = deps.transforms.get_absolute(target.entity) else {
+                    continue;
+                };
+                let center =
+                    target.position + transform.disp + Vec3f::new(0.0, target.height * 0.5, 0.0);
+                let damage = self.splash_damage(&explosion, center, target.radius);
+                if damage > 0 {
+                    self.damage_target(index, damage, deps.transforms, deps.entities);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Deals damage to a target, removing it from the level if it dies.
+    fn damage_target(
+        &mut self,
+        index: usize,
+        damage: i32,
+        transforms: &Transforms,
+        entities: &mut Entities,
+    ) {
+        let Some(target) = self.targets[index].as_mut() else {
+            return;
+        };
+        target.health -= damage;
+        debug!(
+            "Target {} took {} damage, {} health left.",
+            index, damage, target.health
+        );
+        self.damaged_targets.push(TargetDamage {
+            target: TargetId(index),
+            damage,
+            health: target.health,
+        });
+        if target.health > 0 {
+            return;
+        }
+
+        // Found before the target's entity may be removed along with it.
+        let center = transforms.get_absolute(target.entity).map(|transform| {
+            target.position + transform.disp + Vec3f::new(0.0, target.height * 0.5, 0.0)
+        });
+        let target = self
+            .remove_target(index, entities)
+            .expect("killed target missing");
+        if let (true, Some(center)) = (target.explodes, center) {
+            self.explode(center, BARREL_EXPLOSION_DAMAGE);
+        }
+        if target.counted {
+            self.record_kill();
+        }
+    }
+
+    /// Takes a dead target out of the level, along with its solid and, if the level owns it, its
+    /// entity.
+    fn remove_target(&mut self, index: usize, entities: &mut Entities) -> Option<Target> {
+        let target = self.targets[index].take()?;
+        if let Some(solid) = target.solid {
+            self.volume.remove_solid(solid);
+        }
+        if target.remove_on_death {
+            entities.remove(target.entity);
+        }
+        Some(target)
+    }
+
+    /// Returns the damage an explosion deals to something centered on `center`.
+    ///
+    /// Like the original's `PIT_RadiusAttack`, damage falls off by one per unit of the larger
+    /// horizontal distance from the edge of the thing, and walls block it completely.
+    fn splash_damage(&self, explosion: &Explosion, center: Pnt3f, radius: f32) -> i32 {
+        let offset = center - explosion.position;
+        let distance = (offset.x.abs().max(offset.z.abs()) - radius).max(0.0);
+        let damage = explosion.damage - (distance * 100.0) as i32;
+        if damage <= 0 {
+            return 0;
+        }
+        let length = offset.magnitude();
+        if length > 0.0
+            && self
+                .volume
+                .cast_ray(explosion.position, offset / length, length)
+                .is_some()
+        {
+            return 0;
+        }
+        damage
+    }
+
+    fn spawn_flash(
+        &mut self,
+        deps: &mut Dependencies,
+        sprite: FlashSprite,
+        position: Pnt3f,
+    ) -> Result<()> {
+        let (indices, time_left) = match sprite {
+            FlashSprite::Puff => (&self.puff_indices, PUFF_TIME),
+            FlashSprite::Explosion => (&self.explosion_indices, EXPLOSION_TIME),
+        };
+        if indices.is_empty() {
+            return Ok(());
+        }
+        // Puffs and explosions are both a single quad, so any expired flash can show either.
+        if let Some(flash) = self.flashes.iter_mut().find(|flash| flash.time_left <= 0.0) {
+            write_indices(deps, flash.mesh, indices);
+            if let Some(transform) = deps.transforms.get_local_mut(flash.entity) {
+                transform.disp = position.to_vec();
+            }
+            flash.time_left = time_left;
+            return Ok(());
+        }
+
+        let entity = deps.entities.add(self.root, "flash")?;
+        let mesh = deps
+            .meshes
+            .add(
+                deps.entities,
+                entity,
+                "flash_mesh",
+                deps.window.device(),
+                deps.shaders,
+            )
+            .shared(self.decor_mesh)
+            .persistent_indices(indices, deps.window.device())?
+            .build()?;
+        deps.transforms.attach(
+            entity,
+            Trans3 {
+                disp: position.to_vec(),
+                ..Trans3::one()
+            },
+        );
+        deps.render.attach_model(
+            entity,
+            mesh,
+            deps.game_shaders.level_materials().decor.material,
+        );
+        self.flashes.push(Flash {
+            entity,
+            mesh,
+            time_left,
+        });
+        Ok(())
+    }
+
+    /// Finds the sector the player is in and counts it as found if it's a secret.
+    fn update_player_sector(&mut self, level: &WadLevel) {
+        self.player_sector = self.player_volume.and_then(|volume| {
+            level
+                .sector_at(Pnt2f::new(volume.center.x, volume.center.z))
+                .map(|sector| level.sector_id(sector))
+        });
+        let Some(sector_id) = self.player_sector else {
+            return;
+        };
+        let sector_id = sector_id as usize;
+        if is_secret_sector(self.sector_types[sector_id]) {
+            info!("Found secret sector {}.", sector_id);
+            self.sector_types[sector_id] = 0;
+            self.stats.secrets += 1;
+        }
+    }
 }
 
 impl<'context> System<'context> for Level {
@@ -223,32 +1143,19 @@ impl<'context> System<'context> for Level {
             self.level_changed = false;
         }
 
-        if let Some(exit_trigger) = self.exit_trigger.take() {
-            deps.entities.remove(self.root);
-            let current_index = deps.wad.level_index();
-            let new_level_index = match (current_index, exit_trigger) {
-                (n, _) if n % 9 == 8 => match current_index {
-                    8 => 3,
-                    17 => 14,
-                    26 => 24,
-                    _ => unreachable!("Level {} is not a known secret level", current_index),
-                },
-                (n, _) if n % 9 == 7 => current_index + 2,
-                (_, ExitEffectDef::Normal) => current_index + 1,
-                (n, ExitEffectDef::Secret) => n / 9 + 8,
-            };
-            deps.wad.change_level(new_level_index);
-        }
-
         self.volume.update(deps.transforms);
         let timestep = deps.tick.timestep();
-        for (i_effect, effect) in &mut self.effects {
+        let world = &self.volume;
+        let player_volume = self.player_volume;
+        for (i_effect, active) in &mut self.effects {
             let entity_id = self.objects[i_effect];
             let transform = deps
                 .transforms
                 .get_local_mut(entity_id)
                 .expect("no transform on object");
             let current_offset = &mut transform.disp[1];
+            let effect = &mut active.effect;
+            let leg_start = active.leg_start.get_or_insert(*current_offset);
             let mut timestep = timestep;
 
             loop {
@@ -256,8 +1163,43 @@ impl<'context> System<'context> for Level {
                     let offset_difference = effect.first_height_offset - *current_offset;
                     let sign = offset_difference.signum();
                     let time_left = offset_difference.abs() / effect.speed;
+                    let step = if time_left > timestep {
+                        sign * effect.speed * timestep
+                    } else {
+                        offset_difference
+                    };
+                    let blocked = step < 0.0
+                        && player_volume
+                            .is_some_and(|volume| blocks_actor(world, entity_id, volume, step));
+                    if blocked {
+                        match effect.blocked {
+                            BlockedBehaviour::Stop => break,
+                            BlockedBehaviour::Reverse => {
+                                debug!("Effect {}: blocked, reversing.", i_effect);
+                                let target = effect.first_height_offset;
+                                effect.first_height_offset = *leg_start;
+                                effect.second_height_offset = match effect.second_height_offset {
+                                    Some(_) => None,
+                                    None => Some(target),
+                                };
+                                effect.wait = if effect.second_height_offset.is_some() {
+                                    BLOCKED_DOOR_WAIT
+                                } else {
+                                    0.0
+                                };
+                                *leg_start = *current_offset;
+                                continue;
+                            }
+                            BlockedBehaviour::Crush => {
+                                self.player_damage += CRUSH_DAMAGE_PER_SECOND * timestep;
+                                *current_offset += step / CRUSH_SLOWDOWN;
+                                break;
+                            }
+                        }
+                    }
+
                     if time_left > timestep {
-                        *current_offset += sign * effect.speed * timestep;
+                        *current_offset += step;
                         break;
                     } else {
                         *current_offset = effect.first_height_offset;
@@ -278,6 +1220,7 @@ impl<'context> System<'context> for Level {
 
                 if let Some(offset) = effect.second_height_offset.take() {
                     effect.first_height_offset = offset;
+                    *leg_start = *current_offset;
                     debug!(
                         "Effect {}: moved second offset {} into first.",
                         i_effect, offset
@@ -285,18 +1228,55 @@ impl<'context> System<'context> for Level {
                     continue;
                 }
 
+                if effect.repeat {
+                    debug!("Effect {}: done, repeating.", i_effect);
+                    *effect = active.initial;
+                    *leg_start = *current_offset;
+                    break;
+                }
+
                 debug!("Effect {}: done, removing.", i_effect);
                 self.removed.push(i_effect);
                 break;
             }
         }
 
-        if let Some(switch_effect) = self.switch_effect.take() {
-            self.switches[switch_effect.sidedef as usize] =
-                match self.switches[switch_effect.sidedef as usize] {
-                    SwitchState::Off => SwitchState::On,
-                    SwitchState::On => SwitchState::Off,
-                };
+        for &i_removed in &self.removed {
+            self.effects.remove(i_removed);
+        }
+        self.removed.clear();
+
+        // Changes which wait for their floor to finish moving are kept around until then.
+        let effects = &self.effects;
+        let changed_floors = &mut self.changed_floors;
+        let sector_types = &mut self.sector_types;
+        let texture_alt_changed = &mut self.texture_alt_changed;
+        self.pending_changes.retain(|change| {
+            if change.on_finish && effects.contains_key(change.object_id.0 as usize) {
+                return true;
+            }
+            debug!(
+                "Sector {}: floor changed to {} with type {}.",
+                change.sector_id, change.texture, change.sector_type
+            );
+            changed_floors[change.object_id.0 as usize] = true;
+            sector_types[change.sector_id as usize] = change.sector_type;
+            *texture_alt_changed = true;
+            false
+        });
+
+        for timer in &mut self.switch_timers {
+            timer.time_left -= timestep;
+            if timer.time_left <= 0.0 {
+                debug!("Switch {}: reset.", timer.sidedef);
+                self.switches[timer.sidedef] = SwitchState::Off;
+                self.texture_alt_changed = true;
+            }
+        }
+        self.switch_timers.retain(|timer| timer.time_left > 0.0);
+
+        if self.texture_alt_changed {
+            self.texture_alt_changed = false;
             deps.uniforms.map_buffer(
                 deps.game_shaders.texture_alt_buffer(),
                 |buffer| {
@@ -306,21 +1286,101 @@ impl<'context> System<'context> for Level {
                             SwitchState::On => 1,
                         };
                     }
+                    for (i, &changed) in self.changed_floors.iter().enumerate() {
+                        buffer[FLAT_ALT_OFFSET + i] = u32::from(changed);
+                    }
                 },
                 deps.window.queue(),
             );
         }
 
-        for &i_removed in &self.removed {
-            self.effects.remove(i_removed);
+        if self.finished.is_none() {
+            self.stats.time += timestep;
+            self.update_player_sector(&deps.wad.level);
+        }
+
+        for entity in self.removed_pickups.drain(..) {
+            deps.entities.remove(entity);
+        }
+        self.touched_pickups.clear();
+        if let Some(volume) = self.player_volume {
+            for (index, pickup) in self.pickups.iter().enumerate() {
+                let Some(pickup) = pickup else {
+                    continue;
+                };
+                let Some(transform) = deps.transforms.get_absolute(pickup.entity) else {
+                    continue;
+                };
+                let position = pickup.position + transform.disp;
+                let offset = position - volume.center;
+                let reach = pickup.radius + volume.radius;
+                if offset.y.abs() <= PICKUP_REACH
+                    && offset.x * offset.x + offset.z * offset.z <= reach * reach
+                {
+                    self.touched_pickups.push((index, pickup.thing_type));
+                }
+            }
+        }
+
+        self.update_hits(&mut deps)?;
+
+        let tics = timestep * TICS_PER_SECOND;
+        for animation in &mut self.animations {
+            if !animation.cursor.advance(&animation.states, tics) {
+                continue;
+            }
+            let frame = animation.cursor.frame(&animation.states);
+            let Some(quad) = animation
+                .quads
+                .iter()
+                .find(|quad| quad.frame == frame.frame && quad.fullbright == frame.fullbright)
+            else {
+                continue;
+            };
+            // Removed pickups and targets no longer have a mesh.
+            if let Some(indices) = deps
+                .meshes
+                .get_mut(animation.mesh)
+                .and_then(|mesh| mesh.indices)
+            {
+                deps.window
+                    .queue()
+                    .write_buffer(indices, 0, bytemuck::cast_slice(&quad.indices));
+            }
+        }
+
+        if let Some(exit_trigger) = self.exit_trigger.as_mut() {
+            exit_trigger.time_left -= timestep;
+        }
+        if let Some(exit_trigger) = self
+            .exit_trigger
+            .take_if(|exit_trigger| exit_trigger.time_left <= 0.0)
+        {
+            let current_index = deps.wad.level_index();
+            let new_level_index = match (current_index, exit_trigger.effect) {
+                (n, _) if n % 9 == 8 => match current_index {
+                    8 => 3,
+                    17 => 14,
+                    26 => 24,
+                    _ => unreachable!("Level {} is not a known secret level", current_index),
+                },
+                (n, _) if n % 9 == 7 => current_index + 2,
+                (_, ExitEffectDef::Normal) => current_index + 1,
+                (n, ExitEffectDef::Secret) => n / 9 + 8,
+            };
+            info!(
+                "Level finished in {:.1}s, next level {}.",
+                self.stats.time, new_level_index
+            );
+            self.finished = Some(new_level_index);
         }
-        self.removed.clear();
 
         let time = deps.uniforms.time();
         let light_infos = &mut self.lights;
+        let random = &mut *deps.random;
         deps.uniforms.map_buffer(
             deps.game_shaders.lights_buffer(),
-            |buffer| light_infos.fill_buffer_at(time, buffer),
+            |buffer| light_infos.fill_buffer_at(time, buffer, random),
             deps.window.queue(),
         );
         Ok(())
@@ -332,6 +1392,76 @@ impl<'context> System<'context> for Level {
     }
 }
 
+/// Replaces the indices of a mesh built with persistent indices, to change which quad it shows.
+fn write_indices(deps: &mut Dependencies, mesh: MeshId, indices: &[u32]) {
+    if let Some(buffer) = deps.meshes.get_mut(mesh).and_then(|mesh| mesh.indices) {
+        deps.window
+            .queue()
+            .write_buffer(buffer, 0, bytemuck::cast_slice(indices));
+    }
+}
+
+/// Returns the distance along a ray to where it enters a sphere.
+fn ray_sphere_distance(origin: Pnt3f, direction: Vec3f, sphere: Sphere) -> Option<f32> {
+    let offset = origin - sphere.center;
+    let b = offset.dot(direction);
+    let c = offset.magnitude2() - sphere.radius * sphere.radius;
+    if c <= 0.0 {
+        return Some(0.0);
+    }
+    let discriminant = b * b - c;
+    if b >= 0.0 || discriminant < 0.0 {
+        return None;
+    }
+    Some(-b - discriminant.sqrt())
+}
+
+/// Returns the distance along a ray to where it enters a vertical cylinder standing on `base`.
+fn ray_cylinder_distance(
+    origin: Pnt3f,
+    direction: Vec3f,
+    base: Pnt3f,
+    radius: f32,
+    height: f32,
+) -> Option<f32> {
+    let offset = vec2(origin.x - base.x, origin.z - base.z);
+    let planar = vec2(direction.x, direction.z);
+    let a = planar.magnitude2();
+    let b = offset.dot(planar);
+    let c = offset.magnitude2() - radius * radius;
+    let distance = if c <= 0.0 {
+        0.0
+    } else if a <= 0.0 || b >= 0.0 {
+        return None;
+    } else {
+        let discriminant = b * b - a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        (-b - discriminant.sqrt()) / a
+    };
+    let y = origin.y + direction.y * distance;
+    if y >= base.y && y <= base.y + height {
+        Some(distance)
+    } else {
+        None
+    }
+}
+
+/// Checks whether moving `object` down by `-step` would push it into `actor`.
+///
+/// The actor is probed from below, such that ceilings which already cut into it still count.
+fn blocks_actor(world: &World, object: EntityId, actor: Sphere, step: f32) -> bool {
+    let probe_distance = 2.0 * actor.radius;
+    let probe = Sphere {
+        center: actor.center - Vec3f::new(0.0, probe_distance, 0.0),
+        ..actor
+    };
+    world
+        .sweep_object(object, probe, Vec3f::new(0.0, probe_distance - step, 0.0))
+        .is_some_and(|contact| contact.time <= 1.0)
+}
+
 struct Indices {
     wall: Vec<u32>,
     flat: Vec<u32>,
@@ -364,6 +1494,22 @@ impl Indices {
     }
 }
 
+/// The sprite of a pickup, target or animated decoration, which gets its own mesh instead of being
+/// part of its object's decor.
+struct ThingBuilder {
+    object_id: ObjectId,
+    thing_type: ThingType,
+    position: Pnt3f,
+    radius: f32,
+    height: f32,
+    pickup: bool,
+    shootable: bool,
+    solid: bool,
+    indices: Vec<u32>,
+    /// The quads of all the frames in the thing's state table, if it animates.
+    animation: Option<(ThingStates, Vec<FrameQuad>)>,
+}
+
 struct Builder<'a> {
     materials: &'a LevelMaterials,
     meta: &'a WadMetadata,
@@ -377,6 +1523,13 @@ struct Builder<'a> {
     decor_vertices: Vec<SpriteVertex>,
 
     object_indices: VecMap<Indices>,
+    things: Vec<ThingBuilder>,
+    /// The solid things which aren't `things`, as object, base, radius and height.
+    solids: Vec<(ObjectId, Pnt3f, f32, f32)>,
+    actor_spawns: Vec<ActorSpawn>,
+    fullbright_light: Option<u8>,
+    /// Set when the level has more distinct lights than the light buffer holds.
+    lights_overflowed: bool,
 
     num_wall_quads: usize,
     num_floor_polys: usize,
@@ -391,13 +1544,25 @@ impl<'a> Builder<'a> {
     fn build(deps: &mut Dependencies) -> Result<Level> {
         info!("Building new level...");
 
+        let num_sidedefs = deps.wad.level.sidedefs.len();
+        let num_objects = deps.wad.analysis.num_objects();
+        if num_sidedefs > FLAT_ALT_OFFSET || FLAT_ALT_OFFSET + num_objects > TEXTURE_ALT_COUNT {
+            bail!(
+                "Level has {} sidedefs and {} objects, but only {} and {} are supported.",
+                num_sidedefs,
+                num_objects,
+                FLAT_ALT_OFFSET,
+                TEXTURE_ALT_COUNT - FLAT_ALT_OFFSET
+            );
+        }
+
         let start_time = Instant::now();
         let root = deps.entities.add_root("level_root");
 
         let mut objects = Vec::new();
         let world = deps.entities.add(root, "world")?;
         deps.transforms.attach_identity(world);
-        objects.extend((0..deps.wad.analysis.num_objects()).map(|i_object| {
+        objects.extend((0..num_objects).map(|i_object| {
             let entity = deps
                 .entities
                 .add(
@@ -426,6 +1591,11 @@ impl<'a> Builder<'a> {
             decor_vertices: Vec::with_capacity(16_384),
 
             object_indices: VecMap::new(),
+            things: Vec::new(),
+            solids: Vec::new(),
+            actor_spawns: Vec::new(),
+            fullbright_light: None,
+            lights_overflowed: false,
 
             num_wall_quads: 0,
             num_floor_polys: 0,
@@ -437,11 +1607,20 @@ impl<'a> Builder<'a> {
         };
 
         info!("Walking level...");
-        let volume = {
+        let mut volume = {
             let mut world_builder = WorldBuilder::new(&objects);
             deps.wad.walk(&mut builder.chain(&mut world_builder));
             world_builder.build()
         };
+        for &(object_id, base, radius, height) in &builder.solids {
+            volume.add_solid(objects[object_id.0 as usize], base, radius, height);
+        }
+        let fullbright_light = builder.fullbright_light();
+        let puff_indices = builder.flash_quad(PUFF_SPRITE, fullbright_light);
+        let explosion_indices = builder.flash_quad(EXPLOSION_SPRITE, fullbright_light);
+        if builder.lights_overflowed {
+            bail!("Level has more than {} distinct lights.", LIGHTS_COUNT);
+        }
 
         info!(
             "Level built in {:.2}ms:\n\
@@ -599,21 +1778,134 @@ impl<'a> Builder<'a> {
             }
         }
 
+        let mut pickups = Vec::new();
+        let mut targets = Vec::new();
+        let mut animations = Vec::new();
+        for thing in builder.things {
+            let entity = deps
+                .entities
+                .add(objects[thing.object_id.0 as usize], "thing")?;
+            let mesh = deps
+                .meshes
+                .add(
+                    deps.entities,
+                    entity,
+                    "thing_mesh",
+                    deps.window.device(),
+                    deps.shaders,
+                )
+                .shared(global_decor_mesh)
+                .persistent_indices(&thing.indices, deps.window.device())?
+                .build()?;
+            deps.transforms.attach_identity(entity);
+            deps.render
+                .attach_model(entity, mesh, builder.materials.decor.material);
+            let solid = thing.solid.then(|| {
+                volume.add_solid(
+                    objects[thing.object_id.0 as usize],
+                    thing.position,
+                    thing.radius,
+                    thing.height,
+                )
+            });
+            if let Some((states, quads)) = thing.animation {
+                if let Some(cursor) = StateCursor::new(&states, SPAWN_STATE) {
+                    animations.push(Animation {
+                        mesh,
+                        states,
+                        cursor,
+                        quads,
+                    });
+                }
+            }
+            if thing.shootable {
+                targets.push(Some(Target {
+                    entity,
+                    position: thing.position,
+                    radius: thing.radius,
+                    height: thing.height,
+                    health: builder
+                        .meta
+                        .find_thing(thing.thing_type)
+                        .and_then(|meta| meta.health)
+                        .unwrap_or(BARREL_HEALTH),
+                    counted: false,
+                    remove_on_death: true,
+                    explodes: true,
+                    solid,
+                }));
+            } else if thing.pickup {
+                pickups.push(Some(Pickup {
+                    entity,
+                    thing_type: thing.thing_type,
+                    position: thing.position,
+                    radius: thing.radius,
+                    counted: builder.meta.is_counted_item(thing.thing_type),
+                }));
+            }
+        }
+
+        let triggers = deps.wad.analysis.take_triggers();
         Ok(Level {
             root,
             volume,
             objects,
-            triggers: deps.wad.analysis.take_triggers(),
+            trigger_indices: (0..triggers.len()).collect(),
+            triggers,
             removed: Vec::with_capacity(128),
-            effects: VecMap::new(),
+            effects: deps
+                .wad
+                .analysis
+                .take_initial_effects()
+                .into_iter()
+                .map(|effect| (effect.object_id.0 as usize, ActiveEffect::new(effect)))
+                .collect(),
             teleport_effect: None,
-            switch_effect: None,
+            switch_timers: Vec::new(),
+            pending_changes: Vec::new(),
+            texture_alt_changed: true,
             start_pos: builder.start_pos,
             start_yaw: builder.start_yaw,
             lights: builder.lights,
-            switches: vec![SwitchState::Off; deps.wad.level.sidedefs.len()],
+            switches: vec![SwitchState::Off; num_sidedefs],
+            changed_floors: vec![false; num_objects],
+            sector_types: deps
+                .wad
+                .level
+                .sectors
+                .iter()
+                .map(|sector| sector.sector_type)
+                .collect(),
             exit_trigger: None,
+            finished: None,
             level_changed: true,
+            stats: LevelStats {
+                total_kills: deps.wad.analysis.num_monsters(),
+                total_items: deps.wad.analysis.num_items(),
+                total_secrets: deps.wad.analysis.num_secrets(),
+                ..LevelStats::default()
+            },
+            player_volume: None,
+            player_sector: None,
+
+            pickups,
+            touched_pickups: Vec::new(),
+            removed_pickups: Vec::new(),
+            player_damage: 0.0,
+
+            targets,
+            animations,
+            damaged_targets: Vec::new(),
+            actor_spawns: builder.actor_spawns,
+            hitscans: Vec::new(),
+            hits: Vec::new(),
+            explosions: Vec::new(),
+            fired_projectiles: Vec::new(),
+            flashes: Vec::new(),
+            decor_mesh: global_decor_mesh,
+            puff_indices,
+            explosion_indices,
+            fullbright_light,
         })
     }
 
@@ -641,21 +1933,32 @@ impl<'a> Builder<'a> {
             a_row_height: bounds.row_height as f32,
             a_light: light_info as i32,
             a_texture_alt_index: texture_alt_index,
+            a_num_frames_alt: alt_bounds.num_frames as i32,
         });
         self
     }
 
-    fn flat_vertex(&mut self, xz: Pnt2f, y: f32, light_info: u8, bounds: &WadBounds) -> &mut Self {
+    fn flat_vertex(
+        &mut self,
+        xz: Pnt2f,
+        y: f32,
+        light_info: u8,
+        bounds: &WadBounds,
+        alt_bounds: &WadBounds,
+        object_id: ObjectId,
+    ) -> &mut Self {
         self.static_vertices.push(StaticVertex {
             a_pos: [xz[0], y, xz[1]],
             a_atlas_uv: [bounds.pos[0], bounds.pos[1]],
+            a_atlas_uv_alt: [alt_bounds.pos[0], alt_bounds.pos[1]],
             a_tile_uv: [-xz[0] * 100.0, -xz[1] * 100.0],
             a_tile_size: [bounds.size[0], bounds.size[1]],
             a_scroll_rate: 0.0,
             a_num_frames: bounds.num_frames as i32,
             a_row_height: bounds.row_height as f32,
             a_light: light_info as i32,
-            ..Default::default()
+            a_texture_alt_index: (FLAT_ALT_OFFSET + object_id.0 as usize) as u32,
+            a_num_frames_alt: alt_bounds.num_frames as i32,
         });
         self
     }
@@ -727,8 +2030,70 @@ impl<'a> Builder<'a> {
         );
     }
 
+    /// Adds a sprite centered on the origin for flashes to share, returning its indices or none
+    /// if the sprite is missing.
+    fn flash_quad(&mut self, sprite: &[u8], light_info: u8) -> Vec<u32> {
+        let mut indices = Vec::new();
+        let bounds = match WadName::from_bytes(sprite)
+            .ok()
+            .and_then(|name| self.materials.decor.bounds.get(&name))
+        {
+            Some(bounds) => *bounds,
+            None => {
+                warn!(
+                    "No {} sprite, it will not be shown.",
+                    String::from_utf8_lossy(sprite)
+                );
+                return indices;
+            }
+        };
+        let low = Pnt3f::new(0.0, -bounds.size[1] / 200.0, 0.0);
+        self.sprite_quad(low, &bounds, light_info, &mut indices);
+        indices
+    }
+
+    /// Adds a sprite standing on `low` to the decor vertices, with its indices in `indices`.
+    fn sprite_quad(
+        &mut self,
+        low: Pnt3f,
+        bounds: &WadBounds,
+        light_info: u8,
+        indices: &mut Vec<u32>,
+    ) {
+        let half_width = bounds.size[0] / 200.0;
+        let high = low + Vec3f::new(0.0, bounds.size[1] / 100.0, 0.0);
+        self.decor_vertex(low, -half_width, 0.0, bounds.size[1], bounds, light_info)
+            .decor_vertex(
+                low,
+                half_width,
+                bounds.size[0],
+                bounds.size[1],
+                bounds,
+                light_info,
+            )
+            .decor_vertex(high, half_width, bounds.size[0], 0.0, bounds, light_info)
+            .decor_vertex(high, -half_width, 0.0, 0.0, bounds, light_info);
+        Self::any_quad(self.decor_vertices.len(), indices);
+    }
+
+    /// The light for sprites at full brightness, added the first time it's needed.
+    fn fullbright_light(&mut self) -> u8 {
+        match self.fullbright_light {
+            Some(light_info) => light_info,
+            None => {
+                let light_info = self.add_light_info(&LightInfo::constant(1.0));
+                self.fullbright_light = Some(light_info);
+                light_info
+            }
+        }
+    }
+
+    /// Adds a light, or flags the level as having too many of them and returns the first one.
     fn add_light_info(&mut self, light_info: &LightInfo) -> u8 {
-        self.lights.push(light_info)
+        self.lights.push(light_info).unwrap_or_else(|| {
+            self.lights_overflowed = true;
+            0
+        })
     }
 
     fn any_quad(new_length: usize, indices: &mut Vec<u32>) {
@@ -861,6 +2226,7 @@ impl<'a> LevelVisitor for Builder<'a> {
             height,
             light_info,
             tex_name,
+            alt_tex_name,
         } = poly;
         let bounds = if let Some(bounds) = self.materials.flats.bounds.get(&tex_name) {
             *bounds
@@ -868,9 +2234,19 @@ impl<'a> LevelVisitor for Builder<'a> {
             warn!("No such floor texture {}.", tex_name);
             return;
         };
+        let alt_bounds = match alt_tex_name {
+            Some(alt_tex_name) => match self.materials.flats.bounds.get(&alt_tex_name) {
+                Some(alt_bounds) => *alt_bounds,
+                None => {
+                    warn!("No such floor texture {}.", alt_tex_name);
+                    bounds
+                }
+            },
+            None => bounds,
+        };
         let light_info = self.add_light_info(light_info);
         for &vertex in vertices {
-            self.flat_vertex(vertex, height, light_info, &bounds);
+            self.flat_vertex(vertex, height, light_info, &bounds, &alt_bounds, object_id);
         }
         self.flat_poly(object_id, vertices.len());
     }
@@ -883,6 +2259,7 @@ impl<'a> LevelVisitor for Builder<'a> {
             height,
             light_info,
             tex_name,
+            alt_tex_name,
         } = poly;
         let bounds = if let Some(bounds) = self.materials.flats.bounds.get(&tex_name) {
             *bounds
@@ -890,9 +2267,19 @@ impl<'a> LevelVisitor for Builder<'a> {
             warn!("No such ceiling texture {}.", tex_name);
             return;
         };
+        let alt_bounds = match alt_tex_name {
+            Some(alt_tex_name) => match self.materials.flats.bounds.get(&alt_tex_name) {
+                Some(alt_bounds) => *alt_bounds,
+                None => {
+                    warn!("No such ceiling texture {}.", alt_tex_name);
+                    bounds
+                }
+            },
+            None => bounds,
+        };
         let light_info = self.add_light_info(light_info);
         for &vertex in vertices.iter().rev() {
-            self.flat_vertex(vertex, height, light_info, &bounds);
+            self.flat_vertex(vertex, height, light_info, &bounds, &alt_bounds, object_id);
         }
         self.flat_poly(object_id, vertices.len());
     }
@@ -943,8 +2330,28 @@ impl<'a> LevelVisitor for Builder<'a> {
             half_width,
             light_info,
             tex_name,
+            thing_type,
+            radius,
+            pickup,
+            shootable,
+            fullbright,
+            solid,
+            height,
         } = decor;
-        let light_info = self.add_light_info(light_info);
+        let sector_light = self.add_light_info(light_info);
+        if self.meta.is_monster(thing_type) {
+            self.actor_spawns.push(ActorSpawn {
+                thing_type,
+                position: low,
+                light: sector_light,
+            });
+            return;
+        }
+        let light_info = if fullbright {
+            self.fullbright_light()
+        } else {
+            sector_light
+        };
         let bounds = if let Some(bounds) = self.materials.decor.bounds.get(&tex_name) {
             *bounds
         } else {
@@ -961,7 +2368,82 @@ impl<'a> LevelVisitor for Builder<'a> {
                 light_info,
             )
             .decor_vertex(high, half_width, bounds.size[0], 0.0, &bounds, light_info)
-            .decor_vertex(high, -half_width, 0.0, 0.0, &bounds, light_info)
-            .decor_quad(object_id);
+            .decor_vertex(high, -half_width, 0.0, 0.0, &bounds, light_info);
+
+        let meta = self.meta.find_thing(thing_type);
+        let animated = meta
+            .and_then(|meta| meta.states.get(SPAWN_STATE))
+            .is_some_and(|spawn| spawn.frames.len() > 1);
+        let height = height.unwrap_or(high.y - low.y);
+        if !(pickup || shootable || animated) {
+            self.decor_quad(object_id);
+            if solid {
+                // Hanging things block from the ceiling down.
+                let base = if meta.is_some_and(|meta| meta.hanging) {
+                    Pnt3f::new(low.x, high.y - height, low.z)
+                } else {
+                    low
+                };
+                self.solids.push((object_id, base, radius, height));
+            }
+            return;
+        }
+
+        let mut indices = Vec::with_capacity(6);
+        Self::any_quad(self.decor_vertices.len(), &mut indices);
+        let animation = match meta {
+            Some(meta) if animated => {
+                let mut quads = Vec::<FrameQuad>::new();
+                let frames = meta
+                    .states
+                    .get(SPAWN_STATE)
+                    .into_iter()
+                    .flat_map(|spawn| spawn.frames.iter());
+                for frame in frames {
+                    let duplicate = quads.iter().any(|quad| {
+                        quad.frame == frame.frame && quad.fullbright == frame.fullbright
+                    });
+                    if duplicate {
+                        continue;
+                    }
+                    let Some(&bounds) = self.materials.decor.sprite_frame(meta.sprite, frame.frame)
+                    else {
+                        continue;
+                    };
+                    let light_info = if frame.fullbright {
+                        self.fullbright_light()
+                    } else {
+                        sector_light
+                    };
+                    // Frames may differ in size: hanging ones keep their top where it is.
+                    let bottom = if meta.hanging {
+                        Pnt3f::new(low.x, high.y - bounds.size[1] / 100.0, low.z)
+                    } else {
+                        low
+                    };
+                    let mut indices = Vec::with_capacity(6);
+                    self.sprite_quad(bottom, &bounds, light_info, &mut indices);
+                    quads.push(FrameQuad {
+                        frame: frame.frame,
+                        fullbright: frame.fullbright,
+                        indices,
+                    });
+                }
+                Some((meta.states.clone(), quads))
+            }
+            _ => None,
+        };
+        self.things.push(ThingBuilder {
+            object_id,
+            thing_type,
+            position: low,
+            radius,
+            height,
+            pickup,
+            shootable,
+            solid,
+            indices,
+            animation,
+        });
     }
 }
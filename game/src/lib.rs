@@ -1,16 +1,24 @@
 #![cfg_attr(feature = "cargo-clippy", allow(clippy::too_many_arguments))]
 
+mod actors;
 mod game;
 mod game_shaders;
 mod hud;
+mod intermission;
+mod inventory;
 mod level;
 mod lights;
 mod player;
+mod projectiles;
+mod save;
+mod vanilla_demo;
 mod vertex;
 mod wad_system;
+mod weapons;
 mod world;
 
 pub use self::game::{create, Game, GameConfig};
 pub use self::level::Level;
+pub use self::player::CameraPlacement;
 
 pub const SHADER_ROOT: &str = "assets/shaders";
@@ -1,69 +1,118 @@
-use engine::LIGHTS_COUNT;
+use anyhow::{bail, Result};
+use engine::{Random, LIGHTS_COUNT};
+use wad::types::SectorId;
 use wad::{LightEffectKind, LightInfo};
 
 pub struct Lights {
     lights: Vec<LightInfo>,
+    /// For each light, the step of its random flicker it is at and whether it's at its
+    /// alternate level for that step.
+    flickers: Vec<Option<(i64, bool)>>,
 }
 
 impl Lights {
     pub fn new() -> Self {
         Self {
             lights: Vec::with_capacity(LIGHTS_COUNT),
+            flickers: Vec::with_capacity(LIGHTS_COUNT),
         }
     }
 
-    pub fn push(&mut self, light_info: &LightInfo) -> u8 {
-        self.lights
-            .iter()
-            .position(|x| x == light_info)
-            .unwrap_or_else(|| {
-                // TODO(cristicbz): Remove this restriction.
-                assert!(self.lights.len() < 255);
-                self.lights.push(light_info.clone());
-                self.lights.len() - 1
-            }) as u8
+    /// Returns the index of the light, adding it if there's no equal one yet, or `None` if
+    /// there's no room left for it.
+    pub fn push(&mut self, light_info: &LightInfo) -> Option<u8> {
+        if let Some(index) = self.lights.iter().position(|x| x == light_info) {
+            return Some(index as u8);
+        }
+        if self.lights.len() >= LIGHTS_COUNT {
+            return None;
+        }
+        self.lights.push(light_info.clone());
+        self.flickers.push(None);
+        Some((self.lights.len() - 1) as u8)
     }
 
-    pub fn fill_buffer_at(&mut self, time: f32, buffer: &mut [f32]) {
-        for (value, info) in buffer.iter_mut().zip(self.lights.iter()) {
-            *value = clamp(light_level_at(info, time));
+    /// Replaces the level and effect of all the lights belonging to a sector, keeping their
+    /// contrast.
+    pub fn change_sector(&mut self, sector_id: SectorId, light_info: &LightInfo) {
+        for (light, flicker) in self.lights.iter_mut().zip(&mut self.flickers) {
+            if light.sector == Some(sector_id) {
+                light.level = light_info.level;
+                light.effect.clone_from(&light_info.effect);
+                *flicker = None;
+            }
+        }
+    }
+
+    /// The current level and effect of every light, for saved games.
+    pub fn save(&self) -> Vec<LightInfo> {
+        self.lights.clone()
+    }
+
+    /// Restores the lights of a saved game, which must have been saved from the same level.
+    pub fn load(&mut self, lights: &[LightInfo]) -> Result<()> {
+        if lights.len() != self.lights.len() {
+            bail!("Saved lights do not match the level.");
+        }
+        self.lights.clone_from_slice(lights);
+        self.flickers.fill(None);
+        Ok(())
+    }
+
+    /// Fills `buffer` with the light levels at `time`, rolling a new random number for each
+    /// flickering light which reached the next step of its effect.
+    pub fn fill_buffer_at(&mut self, time: f32, buffer: &mut [f32], random: &mut Random) {
+        let lights = self.lights.iter().zip(&mut self.flickers);
+        for (value, (info, flicker)) in buffer.iter_mut().zip(lights) {
+            *value = clamp(light_level_at(info, time, flicker, random));
         }
     }
 }
 
-fn light_level_at(info: &LightInfo, time: f32) -> f32 {
+fn light_level_at(
+    info: &LightInfo,
+    time: f32,
+    flicker: &mut Option<(i64, bool)>,
+    random: &mut Random,
+) -> f32 {
+    let level = clamp(info.level + info.contrast);
     let effect = if let Some(ref effect) = info.effect {
         effect
     } else {
-        return info.level;
+        return level;
     };
     match effect.kind {
         LightEffectKind::Glow => {
-            let scale = info.level - effect.alt_level;
+            let scale = level - effect.alt_level;
             let phase = time * effect.speed / scale;
             (0.5 - fract(phase)).abs() * 2.0 * scale + effect.alt_level
         }
         LightEffectKind::Random => {
-            if noise(effect.sync, (time * effect.speed).floor()) < effect.duration {
+            let step = (time * effect.speed).floor() as i64;
+            let alternate = match *flicker {
+                Some((flicker_step, alternate)) if flicker_step == step => alternate,
+                _ => {
+                    let alternate = (random.play() as f32) < effect.duration * 256.0;
+                    *flicker = Some((step, alternate));
+                    alternate
+                }
+            };
+            if alternate {
                 effect.alt_level
             } else {
-                info.level
+                level
             }
         }
         LightEffectKind::Alternate => {
             if fract(time * effect.speed + effect.sync * 3.5435) < effect.duration {
                 effect.alt_level
             } else {
-                info.level
+                level
             }
         }
     }
 }
 
-fn noise(sync: f32, time: f32) -> f32 {
-    fract(1.0 + ((sync + time / 1000.0) * 12.9898 + sync * 78.233).sin() * 43758.547)
-}
-
 fn fract(x: f32) -> f32 {
     x - x.floor()
 }
@@ -1,13 +1,18 @@
-use super::level::{Level, PlayerAction};
+use super::inventory::{Inventory, Pickup, Powerup, Weapon};
+use super::level::{Level, PlayerAction, Shooter};
+use super::vanilla_demo::VanillaDemo;
+use super::weapons;
 use cgmath::Vector3;
 use engine::{
     Analog2d, DependenciesFrom, Entities, EntityId, Gesture, InfallibleSystem, Input, MouseButton,
-    Projection, Projections, RenderPipeline, Tick, Transforms, Window,
+    Projection, Projections, Random, RenderPipeline, Tick, Transforms, Window,
 };
-use log::{error, info};
+use log::{debug, error, info};
 use math::prelude::*;
-use math::{vec3, Deg, Euler, Pnt3f, Quat, Rad, Sphere, Trans3, Vec3f};
+use math::{vec3, Cylinder, Deg, Euler, Pnt3f, Quat, Rad, Sphere, Trans3, Vec2f, Vec3f};
+use serde::{Deserialize, Serialize};
 use std::f32::consts::FRAC_PI_2;
+use wad::types::SectorType;
 use wad::util::{from_wad_coords, from_wad_height};
 use winit::keyboard::KeyCode;
 
@@ -19,6 +24,13 @@ pub struct Bindings {
     pub clip: Gesture,
     pub push: Gesture,
     pub shoot: Gesture,
+    pub fist: Gesture,
+    pub pistol: Gesture,
+    pub shotgun: Gesture,
+    pub chaingun: Gesture,
+    pub rocket_launcher: Gesture,
+    pub plasma_rifle: Gesture,
+    pub bfg: Gesture,
 }
 
 impl Default for Bindings {
@@ -47,14 +59,85 @@ impl Default for Bindings {
             },
             jump: Gesture::KeyHold(KeyCode::Space),
             push: Gesture::KeyTrigger(KeyCode::KeyE),
-            shoot: Gesture::ButtonTrigger(MouseButton::Left),
+            shoot: Gesture::ButtonHold(MouseButton::Left),
+            fist: Gesture::KeyTrigger(KeyCode::Digit1),
+            pistol: Gesture::KeyTrigger(KeyCode::Digit2),
+            shotgun: Gesture::KeyTrigger(KeyCode::Digit3),
+            chaingun: Gesture::KeyTrigger(KeyCode::Digit4),
+            rocket_launcher: Gesture::KeyTrigger(KeyCode::Digit5),
+            plasma_rifle: Gesture::KeyTrigger(KeyCode::Digit6),
+            bfg: Gesture::KeyTrigger(KeyCode::Digit7),
             fly: Gesture::KeyTrigger(KeyCode::KeyF),
             clip: Gesture::KeyTrigger(KeyCode::KeyC),
         }
     }
 }
 
+/// What the player does during an update, read from the input or from a demo.
+#[derive(Copy, Clone, Debug)]
+pub struct Command {
+    /// Walking, with `x` to the right and `y` backwards.
+    pub movement: Vec2f,
+    /// Turning right and looking down, in radians.
+    pub look: Vec2f,
+    pub jump: bool,
+    pub push: bool,
+    pub shoot: bool,
+    pub weapon: Option<Weapon>,
+}
+
+impl Default for Command {
+    fn default() -> Self {
+        Command {
+            movement: Vec2f::zero(),
+            look: Vec2f::zero(),
+            jump: false,
+            push: false,
+            shoot: false,
+            weapon: None,
+        }
+    }
+}
+
+impl Command {
+    pub fn from_input(input: &Input, bindings: &Bindings) -> Self {
+        let weapon = [
+            (&bindings.fist, Weapon::Fist),
+            (&bindings.pistol, Weapon::Pistol),
+            (&bindings.shotgun, Weapon::Shotgun),
+            (&bindings.chaingun, Weapon::Chaingun),
+            (&bindings.rocket_launcher, Weapon::RocketLauncher),
+            (&bindings.plasma_rifle, Weapon::PlasmaRifle),
+            (&bindings.bfg, Weapon::Bfg),
+        ]
+        .iter()
+        .rev()
+        .find(|(gesture, _)| input.poll_gesture(gesture))
+        .map(|&(_, weapon)| weapon);
+        Command {
+            movement: input.poll_analog2d(&bindings.movement),
+            look: input.poll_analog2d(&bindings.look),
+            jump: input.poll_gesture(&bindings.jump),
+            push: input.poll_gesture(&bindings.push),
+            shoot: input.poll_gesture(&bindings.shoot),
+            weapon,
+        }
+    }
+}
+
+/// How the player collides with the level.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Collision {
+    /// A sphere around the player's head, held above the floor by a spring.
+    Sphere,
+    /// An upright cylinder the size of the original's player, which steps up ledges up to
+    /// `step_height` high and only fits under ceilings high enough for it.
+    Cylinder,
+}
+
 pub struct Config {
+    pub collision: Collision,
+
     move_force: f32,
     spring_const_p: f32,
     spring_const_d: f32,
@@ -64,6 +147,12 @@ pub struct Config {
     ground_drag: f32,
     friction: f32,
 
+    cylinder_radius: f32,
+    cylinder_height: f32,
+    step_height: f32,
+    /// The height of the camera above the cylinder's base.
+    view_height: f32,
+
     fov: Deg<f32>,
     near: f32,
     far: f32,
@@ -75,6 +164,8 @@ pub struct Config {
 impl Default for Config {
     fn default() -> Self {
         Config {
+            collision: Collision::Sphere,
+
             move_force: 60.0,
             spring_const_p: 200.0,
             spring_const_d: 22.4,
@@ -84,6 +175,11 @@ impl Default for Config {
             ground_drag: 0.7,
             friction: 30.0,
 
+            cylinder_radius: from_wad_height(16),
+            cylinder_height: from_wad_height(56),
+            step_height: from_wad_height(24),
+            view_height: from_wad_height(41),
+
             fov: Deg(65.0),
             near: 0.01,
             far: 100.0,
@@ -106,16 +202,110 @@ pub struct Dependencies<'context> {
     transforms: &'context mut Transforms,
     projections: &'context mut Projections,
     render: &'context mut RenderPipeline,
+    random: &'context mut Random,
 
     level: &'context mut Level,
+    vanilla_demo: &'context VanillaDemo,
 }
 
 pub struct Player {
     id: EntityId,
+    camera: EntityId,
     velocity: Vec3f,
     fly: bool,
     clip: bool,
+    grounded: bool,
     last_height_diff: f32,
+
+    health: i32,
+    armour: i32,
+    /// Like the original: 0 for no armour, 1 for green armour which absorbs a third of the
+    /// damage and 2 for blue armour which absorbs half of it.
+    armour_type: u8,
+    inventory: Inventory,
+    weapon: Weapon,
+    /// Time left until the current weapon can attack again.
+    refire_time: f32,
+    /// Whether the trigger has been held since the last attack, which makes bullets spread.
+    refiring: bool,
+    /// The message about the last pickup, until the HUD takes it.
+    message: Option<&'static str>,
+    /// Time spent standing on a hazardous floor since it last dealt damage.
+    hazard_time: f32,
+    /// Seconds since the player died, if dead.
+    dead_time: Option<f32>,
+    /// Where to move the camera to on the next update.
+    placement: Option<CameraPlacement>,
+}
+
+/// Puts the camera somewhere other than where the player is, for rendering a chosen view.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CameraPlacement {
+    /// Where the camera goes, if not at the player's current position.
+    pub position: Option<Pnt3f>,
+    /// Which way the camera faces, if not the player's current direction.
+    pub yaw: Option<Rad<f32>>,
+}
+
+/// What a saved game keeps of the player.
+#[derive(Serialize, Deserialize)]
+pub struct PlayerState {
+    position: [f32; 3],
+    /// The orientation quaternion, scalar first.
+    rotation: [f32; 4],
+    velocity: [f32; 3],
+    fly: bool,
+    clip: bool,
+    health: i32,
+    armour: i32,
+    armour_type: u8,
+    inventory: Inventory,
+    weapon: Weapon,
+}
+
+impl Default for PlayerState {
+    /// A player standing still at the origin, with what they start a new game with.
+    fn default() -> Self {
+        PlayerState {
+            position: [0.0; 3],
+            rotation: [1.0, 0.0, 0.0, 0.0],
+            velocity: [0.0; 3],
+            fly: false,
+            clip: true,
+            health: START_HEALTH,
+            armour: 0,
+            armour_type: 0,
+            inventory: Inventory::new(),
+            weapon: Weapon::Pistol,
+        }
+    }
+}
+
+/// Time between two damage applications from a hazardous floor (32 tics in the original).
+const HAZARD_PERIOD: f32 = 32.0 / 35.0;
+
+/// Time before a dead player can respawn.
+const RESPAWN_DELAY: f32 = 1.0;
+
+const START_HEALTH: i32 = 100;
+const MAX_HEALTH: i32 = 200;
+const MAX_ARMOUR: i32 = 200;
+
+/// How far above the floor a cylinder player still counts as standing on it.
+const GROUNDED_EPSILON: f32 = 1e-3;
+
+/// The health at or below which the E1M8 exit floor ends the level.
+const EXIT_FLOOR_HEALTH: i32 = 10;
+
+/// Damage dealt every `HAZARD_PERIOD` by a sector type and whether the radiation suit blocks it.
+fn hazard_damage(sector_type: SectorType) -> Option<(i32, bool)> {
+    match sector_type {
+        5 => Some((10, true)),
+        7 => Some((5, true)),
+        4 | 16 => Some((20, true)),
+        11 => Some((20, false)),
+        _ => None,
+    }
 }
 
 impl Player {
@@ -133,6 +323,272 @@ impl Player {
 
         self.velocity = Vec3f::zero();
         self.last_height_diff = 0.0;
+        self.hazard_time = 0.0;
+    }
+
+    fn respawn(&mut self, transforms: &mut Transforms, level: &mut Level, config: &Config) {
+        info!("Player respawned.");
+        self.reset(transforms, level);
+        // Forget about anything which hit the corpse.
+        level.take_player_damage();
+        self.health = START_HEALTH;
+        self.armour = 0;
+        self.armour_type = 0;
+        self.inventory = Inventory::new();
+        self.weapon = Weapon::Pistol;
+        self.dead_time = None;
+        if let Some(camera) = transforms.get_local_mut(self.camera) {
+            camera.disp[1] = config.camera_height;
+        }
+    }
+
+    fn damage(&mut self, damage: i32) {
+        let absorbed = match self.armour_type {
+            1 => damage / 3,
+            2 => damage / 2,
+            _ => 0,
+        };
+        let absorbed = if absorbed >= self.armour {
+            self.armour_type = 0;
+            self.armour
+        } else {
+            absorbed
+        };
+        self.armour -= absorbed;
+        self.health -= damage - absorbed;
+        debug!(
+            "Player took {} damage, health={} armour={}.",
+            damage, self.health, self.armour
+        );
+    }
+
+    /// Captures the player's position, vitals and inventory for a saved game.
+    pub fn save(&self, transforms: &Transforms) -> PlayerState {
+        let transform = transforms
+            .get_local(self.id)
+            .expect("player has no transform component: save");
+        PlayerState {
+            position: transform.disp.into(),
+            rotation: [
+                transform.rot.s,
+                transform.rot.v.x,
+                transform.rot.v.y,
+                transform.rot.v.z,
+            ],
+            velocity: self.velocity.into(),
+            fly: self.fly,
+            clip: self.clip,
+            health: self.health,
+            armour: self.armour,
+            armour_type: self.armour_type,
+            inventory: self.inventory.clone(),
+            weapon: self.weapon,
+        }
+    }
+
+    /// Restores the player from a saved game, cancelling any attack, hazard or death in progress.
+    pub fn load(&mut self, state: &PlayerState, transforms: &mut Transforms, config: &Config) {
+        let transform = transforms
+            .get_local_mut(self.id)
+            .expect("player has no transform component: load");
+        transform.disp = state.position.into();
+        let [s, x, y, z] = state.rotation;
+        transform.rot = Quat::new(s, x, y, z);
+        self.velocity = state.velocity.into();
+        self.fly = state.fly;
+        self.clip = state.clip;
+        self.grounded = false;
+        self.last_height_diff = 0.0;
+        self.health = state.health;
+        self.armour = state.armour;
+        self.armour_type = state.armour_type;
+        self.inventory = state.inventory.clone();
+        self.weapon = state.weapon;
+        self.refire_time = 0.0;
+        self.refiring = false;
+        self.hazard_time = 0.0;
+        self.dead_time = None;
+        if let Some(camera) = transforms.get_local_mut(self.camera) {
+            camera.disp[1] = config.camera_height;
+        }
+    }
+
+    /// Moves the camera on the next update. The player is left flying without clipping, so that
+    /// it stays where it was put.
+    pub fn place(&mut self, placement: CameraPlacement) {
+        self.placement = Some(placement);
+    }
+
+    /// Whether the player is alive, and so can be saved.
+    pub fn alive(&self) -> bool {
+        self.dead_time.is_none()
+    }
+
+    /// Returns the message about the last thing the player picked up, if there's a new one.
+    pub fn take_message(&mut self) -> Option<&'static str> {
+        self.message.take()
+    }
+
+    /// Applies a pickup, returning false if the player had no use for it.
+    fn pick_up(&mut self, pickup: Pickup) -> bool {
+        match pickup {
+            Pickup::Health {
+                amount,
+                max,
+                always,
+            } => {
+                if !always && self.health >= max {
+                    return false;
+                }
+                self.health = (self.health + amount).min(max);
+            }
+            Pickup::Armour {
+                amount,
+                armour_type,
+            } => {
+                if self.armour >= amount {
+                    return false;
+                }
+                self.armour = amount;
+                self.armour_type = armour_type;
+            }
+            Pickup::ArmourBonus => {
+                self.armour = (self.armour + 1).min(MAX_ARMOUR);
+                if self.armour_type == 0 {
+                    self.armour_type = 1;
+                }
+            }
+            Pickup::MegaSphere => {
+                self.health = MAX_HEALTH;
+                self.armour = MAX_ARMOUR;
+                self.armour_type = 2;
+            }
+            Pickup::Ammo(ammo, amount) => return self.inventory.give_ammo(ammo, amount),
+            Pickup::Backpack => self.inventory.give_backpack(),
+            Pickup::Weapon(weapon) => return self.inventory.give_weapon(weapon),
+            Pickup::Key(key) => {
+                if !self.inventory.give_key(key) {
+                    debug!("Player already had {:?}.", key);
+                }
+            }
+            Pickup::Powerup(powerup) => {
+                self.inventory.give_powerup(powerup);
+                if powerup == Powerup::Berserk {
+                    self.health = self.health.max(START_HEALTH);
+                }
+            }
+        }
+        true
+    }
+
+    /// Switches weapons and attacks with the current one while the trigger is held.
+    ///
+    /// Returns whether a bullet was fired, which can activate gun-triggered lines.
+    fn attack(
+        &mut self,
+        delta_time: f32,
+        command: &Command,
+        config: &Config,
+        transform: &Trans3,
+        level: &mut Level,
+        random: &mut Random,
+    ) -> bool {
+        if let Some(weapon) = command.weapon.map(|weapon| self.substitute_weapon(weapon)) {
+            if self.inventory.has_weapon(weapon) {
+                debug!("Switched to {:?}.", weapon);
+                self.weapon = weapon;
+            }
+        }
+
+        self.refire_time = (self.refire_time - delta_time).max(0.0);
+        if !command.shoot {
+            self.refiring = false;
+            return false;
+        }
+        if self.refire_time > 0.0 {
+            return false;
+        }
+        if !self.inventory.use_ammo(self.weapon) {
+            self.weapon = self.inventory.best_weapon();
+            info!("Out of ammo, switched to {:?}.", self.weapon);
+            return false;
+        }
+
+        let attack = weapons::attack(
+            self.weapon,
+            self.refiring,
+            self.inventory.has_powerup(Powerup::Berserk),
+            random,
+        );
+        self.refire_time = weapons::refire_time(self.weapon);
+        self.refiring = true;
+
+        let origin = Pnt3f::from_vec(transform.disp) + Vec3f::new(0.0, config.camera_height, 0.0);
+        let aim = transform.rot.rotate_vector(-Vec3f::unit_z());
+        if let Some(kind) = attack.projectile {
+            level.fire_projectile(Shooter::Player, kind, origin, aim);
+            return false;
+        }
+        for bullet in attack.bullets {
+            let sloped = (aim + Vec3f::unit_y() * bullet.slope).normalize();
+            let direction = Quat::from_angle_y(bullet.spread).rotate_vector(sloped);
+            level.fire_hitscan(
+                Shooter::Player,
+                origin,
+                direction,
+                attack.range,
+                bullet.damage,
+            );
+        }
+        !matches!(self.weapon, Weapon::Fist | Weapon::Chainsaw)
+    }
+
+    /// Like the original, switching to the fist or shotgun picks the chainsaw or super shotgun
+    /// instead when the player has them, unless they're already holding it (or the chainsaw
+    /// while berserk, to punch).
+    fn substitute_weapon(&self, weapon: Weapon) -> Weapon {
+        let inventory = &self.inventory;
+        match weapon {
+            Weapon::Fist
+                if inventory.has_weapon(Weapon::Chainsaw)
+                    && !(self.weapon == Weapon::Chainsaw
+                        && inventory.has_powerup(Powerup::Berserk)) =>
+            {
+                Weapon::Chainsaw
+            }
+            Weapon::Shotgun
+                if inventory.has_weapon(Weapon::SuperShotgun)
+                    && self.weapon != Weapon::SuperShotgun =>
+            {
+                Weapon::SuperShotgun
+            }
+            _ => weapon,
+        }
+    }
+
+    /// Applies the damage of the sector the player is standing in, if it's hazardous.
+    fn hazards(&mut self, delta_time: f32, level: &mut Level) {
+        let sector_type = level.player_sector_type();
+        let Some((damage, blocked_by_suit)) = hazard_damage(sector_type) else {
+            self.hazard_time = 0.0;
+            return;
+        };
+        if !self.grounded || self.fly {
+            return;
+        }
+        self.hazard_time += delta_time;
+        if self.hazard_time < HAZARD_PERIOD {
+            return;
+        }
+        self.hazard_time -= HAZARD_PERIOD;
+        if !(blocked_by_suit && self.inventory.has_powerup(Powerup::RadiationSuit)) {
+            self.damage(damage);
+        }
+        if sector_type == 11 && self.health <= EXIT_FLOOR_HEALTH {
+            info!("Exit floor reached.");
+            self.health = self.health.max(1);
+            level.exit_level();
+        }
     }
 
     fn head(&self, config: &Config, transform: &Trans3) -> Sphere {
@@ -147,7 +603,7 @@ impl Player {
         let mut armed = true;
         for _ in 0..100 {
             let displacement = self.velocity * time_left;
-            if let Some(contact) = level.volume().sweep_sphere(*head, displacement) {
+            if let Some(contact) = level.volume().sweep_body(*head, displacement) {
                 let adjusted_time = contact.time - 0.001 / displacement.magnitude();
                 if adjusted_time < 1.0 {
                     let time = clamp(contact.time, (0.0, 1.0));
@@ -168,6 +624,86 @@ impl Player {
         }
     }
 
+    /// The cylinder the player collides as, with the camera at `view_height` above its base.
+    fn body(&self, config: &Config, head: &Sphere) -> Cylinder {
+        Cylinder {
+            base: head.center - Vec3f::new(0.0, config.view_height - config.camera_height, 0.0),
+            radius: config.cylinder_radius,
+            height: config.cylinder_height,
+        }
+    }
+
+    /// Moves the player as a cylinder: sliding along walls horizontally, then stepping up onto
+    /// or falling down to the floor.
+    fn clip_cylinder(
+        &mut self,
+        delta_time: f32,
+        head: &mut Sphere,
+        level: &Level,
+        config: &Config,
+    ) {
+        let volume = level.volume();
+        let mut body = self.body(config, head);
+        let start = body.base;
+
+        let mut time_left = delta_time;
+        let mut armed = true;
+        for _ in 0..100 {
+            let displacement = Vec3f::new(self.velocity.x, 0.0, self.velocity.z) * time_left;
+            let distance = displacement.magnitude();
+            if distance == 0.0 {
+                armed = false;
+                break;
+            }
+            match volume.sweep_cylinder(body, config.step_height, displacement) {
+                Some(contact) if contact.time < 1.0 => {
+                    let adjusted_time = (contact.time - 0.001 / distance).max(0.0);
+                    body.base += displacement * adjusted_time;
+                    self.velocity -= contact.normal * contact.normal.dot(self.velocity);
+                    time_left *= 1.0 - clamp(contact.time, (0.0, 1.0));
+                }
+                _ => {
+                    body.base += displacement;
+                    armed = false;
+                    break;
+                }
+            }
+        }
+        if armed {
+            error!("Failed to compute cylinder collisions.");
+        }
+
+        // Like the original, don't move somewhere the player doesn't fit between the floor and
+        // ceiling, even without touching a wall on the way.
+        let mut floor_and_ceiling = volume.floor_and_ceiling(body, config.step_height);
+        let (floor, ceiling) = floor_and_ceiling;
+        let standing = floor.map_or(body.base.y, |floor| floor.max(body.base.y));
+        if ceiling.is_some_and(|ceiling| ceiling - standing < body.height) {
+            body.base.x = start.x;
+            body.base.z = start.z;
+            self.velocity.x = 0.0;
+            self.velocity.z = 0.0;
+            floor_and_ceiling = volume.floor_and_ceiling(body, config.step_height);
+        }
+
+        // Steps up instantly onto any floor up to `step_height` above, falls onto lower ones.
+        let (floor, ceiling) = floor_and_ceiling;
+        body.base.y += self.velocity.y * delta_time;
+        if let Some(floor) = floor.filter(|&floor| body.base.y <= floor) {
+            body.base.y = floor;
+            self.velocity.y = self.velocity.y.max(0.0);
+        }
+        if let Some(ceiling) = ceiling.filter(|&ceiling| body.top() > ceiling) {
+            body.base.y = floor.map_or(ceiling - body.height, |floor| {
+                (ceiling - body.height).max(floor)
+            });
+            self.velocity.y = self.velocity.y.min(0.0);
+        }
+        self.grounded = floor.is_some_and(|floor| body.base.y <= floor + GROUNDED_EPSILON);
+
+        head.center = body.base + Vec3f::new(0.0, config.view_height - config.camera_height, 0.0);
+    }
+
     fn noclip(&mut self, delta_time: f32, head: &mut Sphere, level: &Level) {
         let old_height = head.center[1];
         head.center += self.velocity * delta_time;
@@ -196,14 +732,16 @@ impl Player {
         &mut self,
         delta_time: f32,
         grounded: bool,
-        input: &Input,
+        command: &Command,
         transform: &mut Trans3,
         config: &Config,
-        bindings: &Bindings,
     ) -> Vec3f {
-        let movement = input.poll_analog2d(&bindings.movement);
-        let look = input.poll_analog2d(&bindings.look);
-        let jump = input.poll_gesture(&bindings.jump);
+        let Command {
+            movement,
+            look,
+            jump,
+            ..
+        } = *command;
 
         // Compute the maximum pitch rotation we're allowed (since we don't want to look
         // upside-down!).
@@ -247,35 +785,31 @@ impl Player {
         &mut self,
         head: &Sphere,
         delta_time: f32,
-        input: &Input,
+        command: &Command,
         level: &Level,
         transform: &mut Trans3,
         config: &Config,
-        bindings: &Bindings,
     ) -> Vec3f {
-        let feet = Sphere {
-            radius: 0.2,
-            ..*head
-        };
-        let feet_probe = Vec3f::new(0.0, -config.height, 0.0);
-        let (height, normal) = if let Some(contact) = level.volume().sweep_sphere(feet, feet_probe)
-        {
-            if contact.time < 1.0 {
-                (config.height * contact.time, Some(contact.normal))
-            } else {
-                (config.height, None)
+        let (height, normal) = match config.collision {
+            Collision::Sphere => {
+                let feet = Sphere {
+                    radius: 0.2,
+                    ..*head
+                };
+                let feet_probe = Vec3f::new(0.0, -config.height, 0.0);
+                match level.volume().sweep_sphere(feet, feet_probe) {
+                    Some(contact) if contact.time < 1.0 => {
+                        (config.height * contact.time, Some(contact.normal))
+                    }
+                    _ => (config.height, None),
+                }
             }
-        } else {
-            (config.height, None)
+            // The cylinder stands right on the floor, as found by its last move.
+            Collision::Cylinder => (config.height, self.grounded.then(Vec3f::unit_y)),
         };
-        let mut force: Vec3f = self.move_force(
-            delta_time,
-            normal.is_some(),
-            input,
-            transform,
-            config,
-            bindings,
-        );
+        self.grounded = normal.is_some();
+        let mut force: Vec3f =
+            self.move_force(delta_time, self.grounded, command, transform, config);
         let speed = self.velocity.magnitude();
         if speed > 0.0 {
             let mut slowdown = if self.fly {
@@ -349,10 +883,24 @@ impl<'context> InfallibleSystem<'context> for Player {
 
         let mut player = Player {
             id: player_entity,
+            camera: camera_entity,
             velocity: Vec3f::zero(),
             fly: false,
             clip: true,
+            grounded: false,
             last_height_diff: 0.0,
+
+            health: START_HEALTH,
+            armour: 0,
+            armour_type: 0,
+            inventory: Inventory::new(),
+            weapon: Weapon::Pistol,
+            refire_time: 0.0,
+            refiring: false,
+            message: None,
+            hazard_time: 0.0,
+            dead_time: None,
+            placement: None,
         };
 
         player.reset(deps.transforms, deps.level);
@@ -362,9 +910,44 @@ impl<'context> InfallibleSystem<'context> for Player {
     fn update(&mut self, deps: Dependencies) {
         if deps.level.level_changed() {
             self.reset(deps.transforms, deps.level);
+            self.inventory.end_level();
+        }
+        if let Some(placement) = self.placement.take() {
+            let transform = deps
+                .transforms
+                .get_local_mut(self.id)
+                .expect("player has no transform component: place");
+            if let Some(position) = placement.position {
+                transform.disp =
+                    position.to_vec() - Vec3f::new(0.0, deps.config.camera_height, 0.0);
+            }
+            if let Some(yaw) = placement.yaw {
+                transform.rot = Quat::from(Euler {
+                    x: Rad(1e-8),
+                    y: yaw,
+                    z: Rad(0.0),
+                });
+            }
+            self.velocity = Vec3f::zero();
+            self.fly = true;
+            self.clip = false;
+        }
+        if deps.level.finished().is_some() {
+            return;
         }
 
         let delta_time = deps.tick.timestep();
+        let command = deps
+            .vanilla_demo
+            .command()
+            .unwrap_or_else(|| Command::from_input(deps.input, deps.bindings));
+        if let Some(dead_time) = self.dead_time.as_mut() {
+            *dead_time += delta_time;
+            if *dead_time >= RESPAWN_DELAY && command.push {
+                self.respawn(deps.transforms, deps.level, deps.config);
+            }
+            return;
+        }
         let transform = deps
             .transforms
             .get_local_mut(self.id)
@@ -382,27 +965,66 @@ impl<'context> InfallibleSystem<'context> for Player {
         let force = self.force(
             &head,
             delta_time,
-            deps.input,
+            &command,
             deps.level,
             transform,
             deps.config,
-            deps.bindings,
         );
-        if self.clip {
-            self.clip(delta_time, &mut head, deps.level);
-        } else {
+        if !self.clip {
             self.noclip(delta_time, &mut head, deps.level);
+        } else if deps.config.collision == Collision::Cylinder {
+            self.clip_cylinder(delta_time, &mut head, deps.level, deps.config);
+        } else {
+            self.clip(delta_time, &mut head, deps.level);
         }
 
         transform.disp = head.center.to_vec();
         self.velocity += force * delta_time;
+        deps.level.set_player_volume(head);
 
+        let damage = deps.level.take_player_damage();
+        if damage > 0 {
+            self.damage(damage as i32);
+        }
+        self.inventory.update(delta_time);
+        self.hazards(delta_time, deps.level);
+        if self.health <= 0 {
+            info!("Player died, press use to respawn.");
+            self.health = 0;
+            self.dead_time = Some(0.0);
+            self.velocity = Vec3f::zero();
+            if let Some(camera) = deps.transforms.get_local_mut(self.camera) {
+                camera.disp[1] = deps.config.camera_height - deps.config.height;
+            }
+            return;
+        }
+
+        let touched_pickups = deps.level.touched_pickups().to_vec();
+        for (index, thing_type) in touched_pickups {
+            let Some((pickup, message)) = Pickup::from_thing_type(thing_type) else {
+                continue;
+            };
+            if self.pick_up(pickup) {
+                info!("{}", message);
+                deps.level.remove_pickup(index);
+                self.message = Some(message);
+            }
+        }
+
+        let fired = self.attack(
+            delta_time,
+            &command,
+            deps.config,
+            transform,
+            deps.level,
+            deps.random,
+        );
         deps.level.poll_triggers(
             transform,
             self.velocity * delta_time,
-            if deps.input.poll_gesture(&deps.bindings.push) {
+            if command.push {
                 Some(PlayerAction::Push)
-            } else if deps.input.poll_gesture(&deps.bindings.shoot) {
+            } else if fired {
                 Some(PlayerAction::Shoot)
             } else {
                 None
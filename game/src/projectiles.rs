@@ -0,0 +1,296 @@
+use super::game_shaders::GameShaders;
+use super::level::{Level, Shooter};
+use super::vertex::{SpriteVertex, SPRITE_QUAD_INDICES};
+use anyhow::Result;
+use engine::{
+    DependenciesFrom, Entities, EntityId, Meshes, Random, RenderPipeline, Shaders, System, Tick,
+    Transforms, Window,
+};
+use log::{debug, warn};
+use math::prelude::*;
+use math::{Pnt3f, Quat, Rad, Sphere, Trans3, Vec3f};
+use std::f32::consts::FRAC_PI_2;
+use wad::util::from_wad_height;
+use wad::WadName;
+
+/// The kinds of projectiles fired by the player's weapons and by monsters.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ProjectileKind {
+    Rocket,
+    ImpFireball,
+    Plasma,
+    CacodemonBall,
+    BaronBall,
+    /// Fired by the mancubus.
+    FatShot,
+    /// Fired by the revenant, without homing in on the player like in the original.
+    Tracer,
+    ArachnotronPlasma,
+    /// Fired by the BFG, spraying rays from the player when it hits something.
+    BfgBall,
+}
+
+impl ProjectileKind {
+    /// Speed in world units per second, from the original's units per tic.
+    fn speed(self) -> f32 {
+        let units_per_tic = match self {
+            ProjectileKind::Rocket | ProjectileKind::FatShot => 20,
+            ProjectileKind::ImpFireball
+            | ProjectileKind::CacodemonBall
+            | ProjectileKind::Tracer => 10,
+            ProjectileKind::Plasma
+            | ProjectileKind::ArachnotronPlasma
+            | ProjectileKind::BfgBall => 25,
+            ProjectileKind::BaronBall => 15,
+        };
+        from_wad_height(units_per_tic) * 35.0
+    }
+
+    fn radius(self) -> f32 {
+        from_wad_height(match self {
+            ProjectileKind::Rocket | ProjectileKind::Tracer => 11,
+            ProjectileKind::ImpFireball
+            | ProjectileKind::CacodemonBall
+            | ProjectileKind::BaronBall
+            | ProjectileKind::FatShot => 6,
+            ProjectileKind::Plasma
+            | ProjectileKind::ArachnotronPlasma
+            | ProjectileKind::BfgBall => 13,
+        })
+    }
+
+    /// Rolls the damage of a direct hit, `(P_Random() % 8 + 1) * damage` in the original.
+    fn damage(self, random: &mut Random) -> i32 {
+        let multiplier = match self {
+            ProjectileKind::Rocket => 20,
+            ProjectileKind::ImpFireball => 3,
+            ProjectileKind::Plasma
+            | ProjectileKind::CacodemonBall
+            | ProjectileKind::ArachnotronPlasma => 5,
+            ProjectileKind::BaronBall | ProjectileKind::FatShot => 8,
+            ProjectileKind::Tracer => 10,
+            ProjectileKind::BfgBall => 100,
+        };
+        (random.play() % 8 + 1) * multiplier
+    }
+
+    /// Damage dealt around the impact, only rockets have splash damage.
+    fn splash_damage(self) -> i32 {
+        match self {
+            ProjectileKind::Rocket => 128,
+            _ => 0,
+        }
+    }
+
+    fn sprite(self) -> &'static [u8] {
+        match self {
+            ProjectileKind::Rocket => b"MISL",
+            ProjectileKind::ImpFireball => b"BAL1",
+            ProjectileKind::Plasma => b"PLSS",
+            ProjectileKind::CacodemonBall => b"BAL2",
+            ProjectileKind::BaronBall => b"BAL7",
+            ProjectileKind::FatShot => b"MANF",
+            ProjectileKind::Tracer => b"FATB",
+            ProjectileKind::ArachnotronPlasma => b"APLS",
+            ProjectileKind::BfgBall => b"BFS1",
+        }
+    }
+}
+
+/// A projectile fired during the last update, waiting to be spawned.
+#[derive(Copy, Clone, Debug)]
+pub struct FiredProjectile {
+    pub shooter: Shooter,
+    pub kind: ProjectileKind,
+    pub origin: Pnt3f,
+    /// Unit vector of the direction of flight.
+    pub direction: Vec3f,
+}
+
+#[derive(DependenciesFrom)]
+pub struct Dependencies<'context> {
+    tick: &'context Tick,
+    window: &'context Window,
+    entities: &'context mut Entities,
+    transforms: &'context mut Transforms,
+    meshes: &'context mut Meshes,
+    render: &'context mut RenderPipeline,
+    shaders: &'context Shaders,
+    random: &'context mut Random,
+
+    game_shaders: &'context GameShaders,
+    level: &'context mut Level,
+}
+
+/// Moves the projectiles fired through the level in a straight line until they hit a wall, the
+/// player or a target, where they explode.
+pub struct Projectiles {
+    projectiles: Vec<Projectile>,
+}
+
+struct Projectile {
+    entity: EntityId,
+    shooter: Shooter,
+    kind: ProjectileKind,
+    center: Pnt3f,
+    velocity: Vec3f,
+    time_left: f32,
+}
+
+impl<'context> System<'context> for Projectiles {
+    type Dependencies = Dependencies<'context>;
+
+    fn debug_name() -> &'static str {
+        "projectiles"
+    }
+
+    fn create(_deps: Dependencies) -> Result<Self> {
+        Ok(Projectiles {
+            projectiles: Vec::new(),
+        })
+    }
+
+    fn update(&mut self, mut deps: Dependencies) -> Result<()> {
+        if deps.level.level_changed() {
+            // The previous level's projectiles were removed along with its root entity.
+            self.projectiles.clear();
+        }
+
+        for fired in deps.level.take_fired_projectiles() {
+            self.spawn(&mut deps, fired)?;
+        }
+
+        let timestep = deps.tick.timestep();
+        let mut index = 0;
+        while index < self.projectiles.len() {
+            let projectile = &mut self.projectiles[index];
+            let sphere = Sphere {
+                center: projectile.center,
+                radius: projectile.kind.radius(),
+            };
+            let motion = projectile.velocity * timestep;
+            let wall_time = deps
+                .level
+                .volume()
+                .sweep_body(sphere, motion)
+                .map(|contact| contact.time)
+                .filter(|&time| time <= 1.0);
+            let struck = deps
+                .level
+                .sweep_targets(deps.transforms, projectile.shooter, sphere, motion)
+                .filter(|&(time, _)| wall_time.is_none_or(|wall_time| time < wall_time));
+
+            let impact = match (struck, wall_time) {
+                (Some((time, struck)), _) => Some((time, Some(struck))),
+                (None, Some(time)) => Some((time, None)),
+                (None, None) => None,
+            };
+            projectile.time_left -= timestep;
+            let Some((time, struck)) = impact else {
+                projectile.center += motion;
+                if projectile.time_left <= 0.0 {
+                    deps.entities.remove(projectile.entity);
+                    self.projectiles.swap_remove(index);
+                } else {
+                    if let Some(transform) = deps.transforms.get_local_mut(projectile.entity) {
+                        transform.disp = projectile.center.to_vec();
+                    }
+                    index += 1;
+                }
+                continue;
+            };
+
+            let projectile = self.projectiles.swap_remove(index);
+            let center = projectile.center + motion * time.max(0.0);
+            debug!(
+                "{:?} from {:?} hit {:?} at {:?}.",
+                projectile.kind, projectile.shooter, struck, center
+            );
+            deps.entities.remove(projectile.entity);
+            if let Some(struck) = struck {
+                deps.level.hit(struck, projectile.kind.damage(deps.random));
+            }
+            deps.level.explode(center, projectile.kind.splash_damage());
+            if (projectile.kind, projectile.shooter) == (ProjectileKind::BfgBall, Shooter::Player) {
+                bfg_spray(deps.level, projectile.velocity, deps.random);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Projectiles {
+    fn spawn(&mut self, deps: &mut Dependencies, fired: FiredProjectile) -> Result<()> {
+        let entity = deps.entities.add(deps.level.root(), "projectile")?;
+        deps.transforms.attach(
+            entity,
+            Trans3 {
+                disp: fired.origin.to_vec(),
+                ..Trans3::one()
+            },
+        );
+
+        let atlas = &deps.game_shaders.level_materials().decor;
+        let bounds = WadName::from_bytes(fired.kind.sprite())
+            .ok()
+            .and_then(|sprite| atlas.sprite_frame(sprite, b'A'));
+        if let Some(bounds) = bounds {
+            // Projectiles are centered on their position, rather than standing on it.
+            let half_height = bounds.size[1] / 200.0;
+            let quad = SpriteVertex::quad(bounds, deps.level.fullbright_light(), -half_height);
+            let mesh = deps
+                .meshes
+                .add(
+                    deps.entities,
+                    entity,
+                    "projectile_mesh",
+                    deps.window.device(),
+                    deps.shaders,
+                )
+                .immutable(&quad, deps.window.device())?
+                .immutable_indices(&SPRITE_QUAD_INDICES, deps.window.device())?
+                .build()?;
+            deps.render.attach_model(entity, mesh, atlas.material);
+        } else {
+            warn!("No sprite for {:?}, it will be invisible.", fired.kind);
+        }
+
+        self.projectiles.push(Projectile {
+            entity,
+            shooter: fired.shooter,
+            kind: fired.kind,
+            center: fired.origin,
+            velocity: fired.direction * fired.kind.speed(),
+            time_left: MAX_LIFETIME,
+        });
+        Ok(())
+    }
+}
+
+/// The original's `A_BFGSpray`: rays fanned out over 90 degrees around the direction the ball
+/// flew in, fired from the player, each dealing 15 rolls of 1 to 8 damage to what it hits.
+fn bfg_spray(level: &mut Level, velocity: Vec3f, random: &mut Random) {
+    let Some(player) = level.player_volume() else {
+        return;
+    };
+    let forward = Vec3f::new(velocity.x, 0.0, velocity.z).normalize_or_zero();
+    for ray in 0..BFG_SPRAY_RAYS {
+        let angle = Rad(FRAC_PI_2 * (ray as f32 / BFG_SPRAY_RAYS as f32 - 0.5));
+        let damage = (0..BFG_SPRAY_ROLLS).map(|_| random.play() % 8 + 1).sum();
+        level.fire_spray(
+            player.center,
+            Quat::from_angle_y(angle).rotate_vector(forward),
+            BFG_SPRAY_RANGE,
+            damage,
+        );
+    }
+}
+
+const BFG_SPRAY_RAYS: usize = 40;
+const BFG_SPRAY_ROLLS: usize = 15;
+/// Range of the spray, 1024 units in the original.
+const BFG_SPRAY_RANGE: f32 = 10.24;
+
+/// Projectiles which never hit anything, like those fired into the sky, are removed after this
+/// long.
+const MAX_LIFETIME: f32 = 10.0;
@@ -0,0 +1,154 @@
+use super::actors::ActorState;
+use super::level::LevelState;
+use super::player::PlayerState;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+pub struct Config {
+    /// Where save files are written to and read from.
+    pub dir: PathBuf,
+}
+
+impl Config {
+    pub fn quick_save_path(&self) -> PathBuf {
+        self.dir.join(QUICK_SAVE_NAME)
+    }
+}
+
+/// The platform's directory for per-user application data, with a subdirectory for saves.
+pub fn default_dir() -> PathBuf {
+    let non_empty = |name: &str| env::var_os(name).filter(|value| !value.is_empty());
+    let data_dir = if cfg!(windows) {
+        non_empty("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        non_empty("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        non_empty("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| non_empty("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+    };
+    data_dir
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("rs_doom")
+        .join("saves")
+}
+
+/// Everything needed to resume a game: the level to load and what changed in it since.
+#[derive(Serialize, Deserialize)]
+pub struct SaveGame {
+    pub level_index: usize,
+    /// Checked on load, so that saves from another WAD aren't applied to the wrong level.
+    pub level_name: String,
+    /// The indices into the random table, as `(play, menu)`.
+    pub random: (u8, u8),
+    pub player: PlayerState,
+    pub level: LevelState,
+    pub actors: Vec<ActorState>,
+}
+
+impl SaveGame {
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Could not create save directory {:?}", dir))?;
+        }
+        let mut writer = BufWriter::new(
+            File::create(path).with_context(|| format!("Could not create save {:?}", path))?,
+        );
+        self.write_to(&mut writer)
+            .with_context(|| format!("Could not write save {:?}", path))?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let mut reader = BufReader::new(
+            File::open(path).with_context(|| format!("Could not open save {:?}", path))?,
+        );
+        Self::read_from(&mut reader).with_context(|| format!("Invalid save {:?}", path))
+    }
+
+    fn write_to(&self, writer: &mut impl Write) -> Result<()> {
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        bincode::serialize_into(writer, self)?;
+        Ok(())
+    }
+
+    fn read_from(reader: &mut impl Read) -> Result<Self> {
+        let mut header = [0; 8];
+        reader.read_exact(&mut header)?;
+        if header[..4] != MAGIC {
+            bail!("Not a save file.");
+        }
+        let version = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        if version != VERSION {
+            bail!(
+                "Unsupported save version {}, expected {}.",
+                version,
+                VERSION
+            );
+        }
+        Ok(bincode::deserialize_from(reader)?)
+    }
+}
+
+const MAGIC: [u8; 4] = *b"RDSV";
+/// Bumped whenever the saved state changes, since old saves can't be read anymore.
+const VERSION: u32 = 2;
+
+const QUICK_SAVE_NAME: &str = "quick.sav";
+
+#[cfg(test)]
+mod test {
+    use super::{SaveGame, VERSION};
+
+    fn test_save() -> SaveGame {
+        SaveGame {
+            level_index: 3,
+            level_name: "MAP04".to_owned(),
+            random: (17, 42),
+            player: Default::default(),
+            level: Default::default(),
+            actors: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn save_round_trips() {
+        let mut bytes = Vec::new();
+        test_save()
+            .write_to(&mut bytes)
+            .expect("test: write failed");
+        let save = SaveGame::read_from(&mut &bytes[..]).expect("test: read failed");
+        assert_eq!(save.level_index, 3);
+        assert_eq!(save.level_name, "MAP04");
+        assert_eq!(save.random, (17, 42));
+
+        let mut rewritten = Vec::new();
+        save.write_to(&mut rewritten).expect("test: rewrite failed");
+        assert_eq!(rewritten, bytes);
+    }
+
+    #[test]
+    fn rejects_other_files_and_versions() {
+        let mut bytes = Vec::new();
+        test_save()
+            .write_to(&mut bytes)
+            .expect("test: write failed");
+
+        let mut wrong_magic = bytes.clone();
+        wrong_magic[..4].copy_from_slice(b"RDMO");
+        assert!(SaveGame::read_from(&mut &wrong_magic[..]).is_err());
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[4..8].copy_from_slice(&(VERSION - 1).to_le_bytes());
+        assert!(SaveGame::read_from(&mut &wrong_version[..]).is_err());
+
+        assert!(SaveGame::read_from(&mut &bytes[..6]).is_err());
+    }
+}
@@ -0,0 +1,228 @@
+use super::inventory::Weapon;
+use super::player::Command;
+use super::wad_system::WadSystem;
+use anyhow::{anyhow, Context as _, Result};
+use engine::{ControlFlow, DependenciesFrom, Random, System, Tick};
+use log::info;
+use math::Vec2f;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use wad::demo::{Demo, TicCmd};
+use wad::WadName;
+
+pub struct Config {
+    pub source: Option<DemoSource>,
+}
+
+/// Where to read an original demo from.
+#[derive(Clone, Debug)]
+pub enum DemoSource {
+    File(PathBuf),
+    /// One of the WAD's `DEMOn` lumps.
+    Lump(WadName),
+}
+
+impl DemoSource {
+    /// Recognises original demos among the paths given for playback: `.lmp` files, or lump
+    /// names like `DEMO1` when there's no such file.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        if path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("lmp"))
+        {
+            return Some(DemoSource::File(path.to_owned()));
+        }
+        if path.exists() {
+            return None;
+        }
+        path.to_str()
+            .filter(|name| name.to_ascii_uppercase().starts_with("DEMO"))
+            .and_then(|name| WadName::from_str(name).ok())
+            .map(DemoSource::Lump)
+    }
+}
+
+#[derive(DependenciesFrom)]
+pub struct Dependencies<'context> {
+    config: &'context Config,
+    tick: &'context Tick,
+    wad: &'context mut WadSystem,
+    random: &'context mut Random,
+    control_flow: &'context mut ControlFlow,
+}
+
+/// Plays back an original demo, turning the recorded tic commands into player commands.
+///
+/// Tics run at 35Hz, so every update applies the tics which started during it: turns add up,
+/// while movement and attacking last until the next tic. The simulation isn't the original's,
+/// so demos desync sooner or later, but they still make for standard input to compare runs
+/// against.
+pub struct VanillaDemo {
+    /// The level the demo plays on, until it's finished.
+    level_index: Option<usize>,
+    started: bool,
+    commands: Vec<TicCmd>,
+    next_tic: usize,
+    /// Time since the last tic started.
+    time: f32,
+    /// Whether use was held on the last tic, since holding it only pushes once.
+    using: bool,
+    command: Option<Command>,
+}
+
+impl VanillaDemo {
+    /// The player's command for this update, if a demo is playing.
+    pub fn command(&self) -> Option<Command> {
+        self.command
+    }
+}
+
+impl<'context> System<'context> for VanillaDemo {
+    type Dependencies = Dependencies<'context>;
+
+    fn debug_name() -> &'static str {
+        "vanilla_demo"
+    }
+
+    fn create(deps: Dependencies) -> Result<Self> {
+        let mut demo = VanillaDemo {
+            level_index: None,
+            started: false,
+            commands: Vec::new(),
+            next_tic: 0,
+            time: 0.0,
+            using: false,
+            command: None,
+        };
+        let Some(ref source) = deps.config.source else {
+            return Ok(demo);
+        };
+
+        let bytes = match *source {
+            DemoSource::File(ref path) => {
+                fs::read(path).with_context(|| format!("Could not read demo {:?}", path))?
+            }
+            DemoSource::Lump(ref name) => deps
+                .wad
+                .archive
+                .named_lump(name)?
+                .ok_or_else(|| anyhow!("No demo lump {} in WAD.", name))?
+                .read_bytes()?,
+        };
+        let parsed =
+            Demo::from_bytes(&bytes).with_context(|| format!("Invalid demo {:?}", source))?;
+        let header = parsed.header;
+
+        let archive = &deps.wad.archive;
+        let level_index = header
+            .level_names()
+            .iter()
+            .find_map(|name| {
+                (0..archive.num_levels()).find(|&index| {
+                    archive
+                        .level_lump(index)
+                        .is_ok_and(|lump| lump.name().as_ref() == name)
+                })
+            })
+            .ok_or_else(|| {
+                anyhow!(
+                    "Demo {:?} plays on episode {} map {}, which is not in the WAD.",
+                    source,
+                    header.episode,
+                    header.map
+                )
+            })?;
+
+        info!(
+            "Playing demo {:?}: {} tics on level {}, skill {}.",
+            source,
+            parsed.num_tics(),
+            level_index,
+            header.skill + 1
+        );
+        if header.num_players() > 1 {
+            info!(
+                "Demo has {} players, following player {} only.",
+                header.num_players(),
+                header.console_player + 1
+            );
+        }
+
+        deps.wad.change_level(level_index);
+        demo.commands = parsed.console_commands().collect();
+        demo.level_index = Some(level_index);
+        demo.command = Some(Command::default());
+        Ok(demo)
+    }
+
+    fn update(&mut self, deps: Dependencies) -> Result<()> {
+        let Some(level_index) = self.level_index else {
+            return Ok(());
+        };
+        if !self.started {
+            if deps.wad.level_index() != level_index {
+                return Ok(());
+            }
+            deps.random.clear();
+            self.started = true;
+        }
+
+        let last = self.command.unwrap_or_default();
+        let mut command = Command {
+            movement: last.movement,
+            shoot: last.shoot,
+            ..Command::default()
+        };
+        self.time += deps.tick.timestep();
+        while self.time >= TIC_TIME {
+            self.time -= TIC_TIME;
+            let Some(&tic) = self.commands.get(self.next_tic) else {
+                info!("Demo finished after {} tics.", self.next_tic);
+                self.level_index = None;
+                self.command = Some(Command::default());
+                deps.control_flow.quit_requested = true;
+                return Ok(());
+            };
+            self.next_tic += 1;
+            if tic.special() {
+                continue;
+            }
+            command.movement = Vec2f::new(
+                f32::from(tic.side) / MAX_SIDE_MOVE,
+                -f32::from(tic.forward) / MAX_FORWARD_MOVE,
+            );
+            command.look.x -= tic.turn_rad();
+            command.shoot = tic.attack();
+            command.push |= tic.use_() && !self.using;
+            self.using = tic.use_();
+            if let Some(weapon) = tic.weapon_change().and_then(weapon_from_number) {
+                command.weapon = Some(weapon);
+            }
+        }
+        self.command = Some(command);
+        Ok(())
+    }
+}
+
+/// The weapons in the order the original numbers them. Tic commands only have 3 bits for it, so
+/// the super shotgun is reached by switching to the shotgun, like the chainsaw from the fist.
+fn weapon_from_number(number: u8) -> Option<Weapon> {
+    Some(match number {
+        0 => Weapon::Fist,
+        1 => Weapon::Pistol,
+        2 => Weapon::Shotgun,
+        3 => Weapon::Chaingun,
+        4 => Weapon::RocketLauncher,
+        5 => Weapon::PlasmaRifle,
+        6 => Weapon::Bfg,
+        7 => Weapon::Chainsaw,
+        _ => return None,
+    })
+}
+
+const TIC_TIME: f32 = 1.0 / 35.0;
+
+/// The movement of a running player, the most a tic command holds.
+const MAX_FORWARD_MOVE: f32 = 50.0;
+const MAX_SIDE_MOVE: f32 = 40.0;
@@ -3,6 +3,7 @@
 use bytemuck::{offset_of, Pod, Zeroable};
 use engine::ShaderVertex;
 use std::sync::OnceLock;
+use wad::tex::Bounds as WadBounds;
 use wgpu::VertexAttribute;
 
 #[repr(C)]
@@ -18,6 +19,7 @@ pub struct StaticVertex {
     pub a_num_frames: i32,
     pub a_light: i32,
     pub a_texture_alt_index: u32,
+    pub a_num_frames_alt: i32,
 }
 
 impl ShaderVertex for StaticVertex {
@@ -78,6 +80,11 @@ impl ShaderVertex for StaticVertex {
                         offset: offset_of!(StaticVertex, a_texture_alt_index) as u64,
                         shader_location: 9,
                     },
+                    wgpu::VertexAttribute {
+                        format: wgpu::VertexFormat::Sint32,
+                        offset: offset_of!(StaticVertex, a_num_frames_alt) as u64,
+                        shader_location: 10,
+                    },
                 ]
             }),
         }
@@ -96,6 +103,32 @@ pub struct SpriteVertex {
     pub a_light: i32,
 }
 
+impl SpriteVertex {
+    /// The vertices of a standalone sprite quad, with its bottom edge `bottom` above the origin.
+    pub fn quad(bounds: &WadBounds, light: u8, bottom: f32) -> [SpriteVertex; 4] {
+        let half_width = bounds.size[0] / 200.0;
+        let top = bottom + bounds.size[1] / 100.0;
+        let vertex = |y: f32, local_x: f32, tile_u: f32, tile_v: f32| SpriteVertex {
+            a_pos: [0.0, y, 0.0],
+            a_local_x: local_x,
+            a_atlas_uv: [bounds.pos[0], bounds.pos[1]],
+            a_tile_uv: [tile_u, tile_v],
+            a_tile_size: [bounds.size[0], bounds.size[1]],
+            a_num_frames: 1,
+            a_light: i32::from(light),
+        };
+        [
+            vertex(bottom, -half_width, 0.0, bounds.size[1]),
+            vertex(bottom, half_width, bounds.size[0], bounds.size[1]),
+            vertex(top, half_width, bounds.size[0], 0.0),
+            vertex(top, -half_width, 0.0, 0.0),
+        ]
+    }
+}
+
+/// Indices of the two triangles of a `SpriteVertex::quad`.
+pub const SPRITE_QUAD_INDICES: [u32; 6] = [0, 1, 3, 1, 2, 3];
+
 impl ShaderVertex for SpriteVertex {
     fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
         static ATTRIBUTES: OnceLock<Vec<VertexAttribute>> = OnceLock::new();
@@ -1,6 +1,7 @@
 use anyhow::{bail, Context as _, Result};
 use engine::{DependenciesFrom, System};
 use log::info;
+use std::mem;
 use std::path::PathBuf;
 use wad::{
     Archive, Level as WadLevel, LevelAnalysis, LevelVisitor, LevelWalker, TextureDirectory, WadName,
@@ -22,6 +23,8 @@ pub struct WadSystem {
     level_name: WadName,
     current_level_index: usize,
     next_level_index: usize,
+    /// Whether to load the next level even if it's the current one, to start it over.
+    reload_requested: bool,
     level_changed: bool,
 }
 
@@ -38,6 +41,12 @@ impl WadSystem {
         self.next_level_index = new_level_index;
     }
 
+    /// Loads a level afresh, even if it's the current one.
+    pub fn reload_level(&mut self, level_index: usize) {
+        self.next_level_index = level_index;
+        self.reload_requested = true;
+    }
+
     pub fn level_changed(&self) -> bool {
         self.level_changed
     }
@@ -99,6 +108,7 @@ impl<'context> System<'context> for WadSystem {
             analysis,
             current_level_index: level_index,
             next_level_index: level_index,
+            reload_requested: false,
             level_changed: false,
             level_name,
         })
@@ -107,7 +117,8 @@ impl<'context> System<'context> for WadSystem {
     fn update(&mut self, _deps: Dependencies) -> Result<()> {
         self.level_changed = false;
 
-        if self.next_level_index != self.current_level_index {
+        let reload = mem::take(&mut self.reload_requested);
+        if reload || self.next_level_index != self.current_level_index {
             if self.next_level_index >= self.archive.num_levels() {
                 info!(
                     "New level index {} is out of bounds, keeping current.",
@@ -0,0 +1,186 @@
+use super::inventory::Weapon;
+use super::projectiles::ProjectileKind;
+use engine::Random;
+use math::Rad;
+use std::f32::consts::PI;
+
+/// A single hitscan ray fired by an attack.
+pub struct Bullet {
+    /// Horizontal angle away from where the player is aiming.
+    pub spread: Rad<f32>,
+    /// Added to the vertical slope of where the player is aiming, only the super shotgun's
+    /// pellets spread vertically.
+    pub slope: f32,
+    pub damage: i32,
+}
+
+pub struct Attack {
+    pub range: f32,
+    pub bullets: Vec<Bullet>,
+    /// The projectile fired instead of bullets, by the rocket launcher, plasma rifle and BFG.
+    pub projectile: Option<ProjectileKind>,
+}
+
+/// Time between two attacks while the trigger is held, from the durations of the original's
+/// weapon states.
+pub fn refire_time(weapon: Weapon) -> f32 {
+    let tics = match weapon {
+        Weapon::Fist => 17,
+        Weapon::Chainsaw => 4,
+        Weapon::Pistol => 14,
+        Weapon::Shotgun => 37,
+        Weapon::SuperShotgun => 44,
+        Weapon::Chaingun => 4,
+        Weapon::PlasmaRifle => 3,
+        Weapon::Bfg => 40,
+        Weapon::RocketLauncher => 20,
+    };
+    tics as f32 / 35.0
+}
+
+/// Rolls the bullets of an attack with `weapon`.
+///
+/// Like the original, the pistol and chaingun are perfectly accurate on the first shot and only
+/// spread while the trigger is held, while shotgun pellets always spread.
+pub fn attack(weapon: Weapon, refiring: bool, berserk: bool, random: &mut Random) -> Attack {
+    match weapon {
+        Weapon::Fist => {
+            let damage = (random.play() % 10 + 1) * 2;
+            Attack {
+                range: MELEE_RANGE,
+                bullets: vec![Bullet {
+                    spread: Rad(spread(random, PLAYER_SPREAD_SHIFT)),
+                    slope: 0.0,
+                    damage: if berserk { damage * 10 } else { damage },
+                }],
+                projectile: None,
+            }
+        }
+        Weapon::Chainsaw => Attack {
+            range: CHAINSAW_RANGE,
+            bullets: vec![Bullet {
+                damage: (random.play() % 10 + 1) * 2,
+                spread: Rad(spread(random, PLAYER_SPREAD_SHIFT)),
+                slope: 0.0,
+            }],
+            projectile: None,
+        },
+        Weapon::Shotgun => Attack {
+            range: MISSILE_RANGE,
+            bullets: (0..SHOTGUN_PELLETS)
+                .map(|_| Bullet {
+                    damage: bullet_damage(random),
+                    spread: Rad(spread(random, PLAYER_SPREAD_SHIFT)),
+                    slope: 0.0,
+                })
+                .collect(),
+            projectile: None,
+        },
+        Weapon::SuperShotgun => Attack {
+            range: MISSILE_RANGE,
+            bullets: (0..SUPER_SHOTGUN_PELLETS)
+                .map(|_| Bullet {
+                    damage: bullet_damage(random),
+                    spread: Rad(spread(random, SUPER_SHOTGUN_SPREAD_SHIFT)),
+                    slope: (random.play() - random.play()) as f32 * SUPER_SHOTGUN_SLOPE_SPREAD,
+                })
+                .collect(),
+            projectile: None,
+        },
+        Weapon::RocketLauncher => projectile_attack(ProjectileKind::Rocket),
+        Weapon::PlasmaRifle => projectile_attack(ProjectileKind::Plasma),
+        Weapon::Bfg => projectile_attack(ProjectileKind::BfgBall),
+        Weapon::Pistol | Weapon::Chaingun => Attack {
+            range: MISSILE_RANGE,
+            bullets: vec![Bullet {
+                damage: bullet_damage(random),
+                spread: Rad(if refiring {
+                    spread(random, PLAYER_SPREAD_SHIFT)
+                } else {
+                    0.0
+                }),
+                slope: 0.0,
+            }],
+            projectile: None,
+        },
+    }
+}
+
+fn projectile_attack(kind: ProjectileKind) -> Attack {
+    Attack {
+        range: 0.0,
+        bullets: Vec::new(),
+        projectile: Some(kind),
+    }
+}
+
+fn bullet_damage(random: &mut Random) -> i32 {
+    (random.play() % 3 + 1) * 5
+}
+
+/// The original's `(P_Random() - P_Random()) << shift` angle in radians, biased towards the
+/// center.
+pub fn spread(random: &mut Random, shift: u32) -> f32 {
+    let difference = random.play() - random.play();
+    difference as f32 * (1u32 << shift) as f32 * PI / 2_147_483_648.0
+}
+
+const SHOTGUN_PELLETS: usize = 7;
+const SUPER_SHOTGUN_PELLETS: usize = 20;
+
+/// The super shotgun's pellets spread twice as wide as the shotgun's.
+const SUPER_SHOTGUN_SPREAD_SHIFT: u32 = 19;
+/// The original's `(P_Random() - P_Random()) << 5` slope, in 16.16 fixed point.
+const SUPER_SHOTGUN_SLOPE_SPREAD: f32 = 32.0 / 65_536.0;
+
+/// The player's bullets spread up to about 5.6 degrees either way.
+const PLAYER_SPREAD_SHIFT: u32 = 18;
+
+/// Range of the fist, 64 units in the original.
+const MELEE_RANGE: f32 = 0.64;
+/// Range of the chainsaw, one unit further than the fist.
+const CHAINSAW_RANGE: f32 = 0.65;
+/// Range of bullets, 2048 units in the original.
+const MISSILE_RANGE: f32 = 20.48;
+
+#[cfg(test)]
+mod test {
+    use super::attack;
+    use crate::inventory::Weapon;
+    use crate::projectiles::ProjectileKind;
+    use engine::Random;
+
+    #[test]
+    fn damage_is_in_vanilla_ranges() {
+        let mut random = Random::new(42);
+        for _ in 0..1000 {
+            let punch = attack(Weapon::Fist, false, false, &mut random);
+            assert!((2..=20).contains(&punch.bullets[0].damage));
+
+            let shotgun = attack(Weapon::Shotgun, false, false, &mut random);
+            assert_eq!(shotgun.bullets.len(), 7);
+            for pellet in &shotgun.bullets {
+                assert!([5, 10, 15].contains(&pellet.damage));
+                assert!(pellet.spread.0.abs() < 0.1);
+            }
+
+            let pistol = attack(Weapon::Pistol, false, false, &mut random);
+            assert_eq!(pistol.bullets[0].spread.0, 0.0);
+
+            let saw = attack(Weapon::Chainsaw, false, true, &mut random);
+            assert!((2..=20).contains(&saw.bullets[0].damage));
+
+            let super_shotgun = attack(Weapon::SuperShotgun, false, false, &mut random);
+            assert_eq!(super_shotgun.bullets.len(), 20);
+            for pellet in &super_shotgun.bullets {
+                assert!([5, 10, 15].contains(&pellet.damage));
+                assert!(pellet.spread.0.abs() < 0.2);
+                assert!(pellet.slope.abs() < 0.125);
+            }
+
+            let bfg = attack(Weapon::Bfg, false, false, &mut random);
+            assert!(bfg.bullets.is_empty());
+            assert_eq!(bfg.projectile, Some(ProjectileKind::BfgBall));
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use engine::{Entity, EntityId, Transforms};
 use idcontain::IdMapVec;
 use math::prelude::*;
-use math::{ContactInfo, Line2f, Pnt2f, Pnt3f, Sphere, Trans3, Vec3f};
+use math::{vec2, ContactInfo, Cylinder, Line2f, Pnt2f, Pnt3f, Sphere, Trans3, Vec3f};
 use std::cell::RefCell;
 use std::{f32, i32};
 use vec_map::VecMap;
@@ -14,10 +14,15 @@ pub struct World {
     verts: Vec<Pnt3f>,
 
     dynamic_chunks: IdMapVec<Entity, DynamicChunk>,
+    solids: Vec<Option<Solid>>,
 
     node_stack: RefCell<Vec<usize>>,
 }
 
+/// Identifies a solid thing added with `World::add_solid`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SolidId(usize);
+
 impl World {
     pub fn update(&mut self, transforms: &Transforms) {
         for index in 0..self.dynamic_chunks.len() {
@@ -81,6 +86,236 @@ impl World {
         }
     }
 
+    /// Like `sweep_sphere`, but also against solid things, for bodies which bump into them.
+    ///
+    /// Rays and hitscans go through solid things, like they did in the original.
+    pub fn sweep_body(&self, sphere: Sphere, vel: Vec3f) -> Option<ContactInfo> {
+        match (
+            self.sweep_sphere(sphere, vel),
+            self.sweep_solids(sphere, vel),
+        ) {
+            (Some(wall), Some(solid)) => Some(if solid.time < wall.time { solid } else { wall }),
+            (wall, solid) => wall.or(solid),
+        }
+    }
+
+    /// Sweeps a sphere against the solid things, returning the first contact.
+    pub fn sweep_solids(&self, sphere: Sphere, vel: Vec3f) -> Option<ContactInfo> {
+        self.solids
+            .iter()
+            .flatten()
+            .filter_map(|solid| {
+                let center = match self.dynamic_chunks.get(solid.object) {
+                    Some(dynamic) => dynamic.inverse_transform.transform_point(sphere.center),
+                    None => sphere.center,
+                };
+                solid.sweep(Sphere { center, ..sphere }, vel)
+            })
+            .min_by(|first, second| first.time.total_cmp(&second.time))
+    }
+
+    /// Sweeps an upright cylinder horizontally against walls and solid things, the way the
+    /// original moved the player. Walls which are entirely less than `step` above the cylinder's
+    /// base don't block it, since it can step onto them.
+    pub fn sweep_cylinder(&self, cylinder: Cylinder, step: f32, vel: Vec3f) -> Option<ContactInfo> {
+        let vel = Vec3f::new(vel.x, 0.0, vel.z);
+        let mut first_contact = ContactInfo {
+            time: f32::INFINITY,
+            normal: Vec3f::zero(),
+        };
+        self.visit_cylinder_chunks(cylinder, vel, |chunk, cylinder, vel| {
+            let tris = &self.triangles[chunk.tri_start as usize..chunk.tri_end as usize];
+            for &tri in tris {
+                let normal = self.verts[tri.normal as usize].to_vec();
+                if normal.y.abs() > FLAT_NORMAL_Y {
+                    continue;
+                }
+                let triangle = self.triangle_verts(tri);
+                let (low, high) = triangle
+                    .iter()
+                    .fold((f32::INFINITY, f32::NEG_INFINITY), |(low, high), vertex| {
+                        (low.min(vertex.y), high.max(vertex.y))
+                    });
+                if high <= cylinder.base.y + step || low >= cylinder.top() {
+                    continue;
+                }
+                // Walls are upright, so they're segments on the horizontal plane.
+                let along = vec2(-normal.z, normal.x);
+                let points = triangle.map(|vertex| Pnt2f::new(vertex.x, vertex.z));
+                let by_offset = |first: &&Pnt2f, second: &&Pnt2f| {
+                    let first = first.to_vec().dot(along);
+                    first.total_cmp(&second.to_vec().dot(along))
+                };
+                let start = *points.iter().min_by(by_offset).expect("no triangle points");
+                let end = *points.iter().max_by(by_offset).expect("no triangle points");
+                if let Some(contact) = cylinder.sweep_segment([start, end], vel) {
+                    if contact.time < first_contact.time {
+                        first_contact = contact;
+                    }
+                }
+            }
+        });
+
+        for solid in self.solids.iter().flatten() {
+            let base = match self.dynamic_chunks.get(solid.object) {
+                Some(dynamic) => dynamic.inverse_transform.transform_point(cylinder.base),
+                None => cylinder.base,
+            };
+            if solid.bottom >= base.y + cylinder.height || solid.bottom + solid.height <= base.y {
+                continue;
+            }
+            let inflated = Cylinder {
+                base,
+                radius: cylinder.radius + solid.radius,
+                ..cylinder
+            };
+            if let Some(contact) = inflated.sweep_segment([solid.center; 2], vel) {
+                if contact.time < first_contact.time {
+                    first_contact = contact;
+                }
+            }
+        }
+
+        if first_contact.time < f32::INFINITY {
+            Some(first_contact)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the highest floor under an upright cylinder which is at most `step` above its
+    /// base, and the lowest ceiling above that.
+    pub fn floor_and_ceiling(&self, cylinder: Cylinder, step: f32) -> (Option<f32>, Option<f32>) {
+        let mut floor = None::<f32>;
+        let mut ceiling = None::<f32>;
+        let world_base = cylinder.base.y;
+        self.visit_cylinder_chunks(cylinder, Vec3f::zero(), |chunk, cylinder, _| {
+            // Dynamic chunks are moved around, so their heights need moving back.
+            let offset = world_base - cylinder.base.y;
+            let tris = &self.triangles[chunk.tri_start as usize..chunk.tri_end as usize];
+            for &tri in tris {
+                let normal = self.verts[tri.normal as usize].to_vec();
+                let triangle = self.triangle_verts(tri);
+                if normal.y > FLAT_NORMAL_Y {
+                    let height = triangle[0].y.max(triangle[1].y).max(triangle[2].y);
+                    if height <= cylinder.base.y + step
+                        && floor.is_none_or(|floor| height + offset > floor)
+                        && cylinder.overlaps_footprint(&triangle)
+                    {
+                        floor = Some(height + offset);
+                    }
+                } else if normal.y < -FLAT_NORMAL_Y {
+                    let height = triangle[0].y.min(triangle[1].y).min(triangle[2].y);
+                    if height > cylinder.base.y + step
+                        && ceiling.is_none_or(|ceiling| height + offset < ceiling)
+                        && cylinder.overlaps_footprint(&triangle)
+                    {
+                        ceiling = Some(height + offset);
+                    }
+                }
+            }
+        });
+        (floor, ceiling)
+    }
+
+    /// Calls `visit` with the static chunks near a cylinder moving by `vel`, then with every
+    /// dynamic chunk, along with the cylinder and velocity in the chunk's coordinates.
+    fn visit_cylinder_chunks(
+        &self,
+        cylinder: Cylinder,
+        vel: Vec3f,
+        mut visit: impl FnMut(Chunk, Cylinder, Vec3f),
+    ) {
+        let bounds = Sphere {
+            center: cylinder.base,
+            radius: cylinder.radius,
+        };
+        let mut stack = self.node_stack.borrow_mut();
+        stack.push(0);
+        while let Some(index) = stack.pop() {
+            for child in self.nodes[index].intersect_sphere(bounds, vel) {
+                match child {
+                    Child::Node(index) => stack.push(index),
+                    Child::Leaf(index) => visit(self.chunks[index], cylinder, vel),
+                }
+            }
+        }
+
+        for dynamic in self.dynamic_chunks.access() {
+            let transformed = Cylinder {
+                base: dynamic.inverse_transform.transform_point(cylinder.base),
+                ..cylinder
+            };
+            let transformed_velocity = dynamic.inverse_transform.transform_vector(vel);
+            visit(dynamic.chunk, transformed, transformed_velocity);
+        }
+    }
+
+    /// Adds a solid thing standing on `base`, relative to the object it belongs to.
+    pub fn add_solid(
+        &mut self,
+        object: EntityId,
+        base: Pnt3f,
+        radius: f32,
+        height: f32,
+    ) -> SolidId {
+        self.solids.push(Some(Solid {
+            object,
+            center: Pnt2f::new(base.x, base.z),
+            bottom: base.y,
+            height,
+            radius,
+        }));
+        SolidId(self.solids.len() - 1)
+    }
+
+    /// Removes a solid thing, like a barrel which exploded.
+    pub fn remove_solid(&mut self, id: SolidId) {
+        self.solids[id.0] = None;
+    }
+
+    /// Casts a ray from `origin` along the unit vector `direction`, returning the distance to the
+    /// first wall, floor or ceiling it hits within `range`.
+    pub fn cast_ray(&self, origin: Pnt3f, direction: Vec3f, range: f32) -> Option<f32> {
+        let ray = Sphere {
+            center: origin,
+            radius: RAY_RADIUS,
+        };
+        self.sweep_sphere(ray, direction * range)
+            .filter(|contact| contact.time <= 1.0)
+            .map(|contact| contact.time * range)
+    }
+
+    /// Sweeps a sphere against the geometry of a single dynamic object, in world coordinates.
+    pub fn sweep_object(
+        &self,
+        object: EntityId,
+        sphere: Sphere,
+        vel: Vec3f,
+    ) -> Option<ContactInfo> {
+        let dynamic = self.dynamic_chunks.get(object)?;
+        let mut first_contact = ContactInfo {
+            time: f32::INFINITY,
+            normal: Vec3f::zero(),
+        };
+        let transformed_sphere = Sphere {
+            center: dynamic.inverse_transform.transform_point(sphere.center),
+            radius: sphere.radius,
+        };
+        self.sweep_chunk(
+            &mut first_contact,
+            dynamic.chunk,
+            transformed_sphere,
+            dynamic.inverse_transform.transform_vector(vel),
+        );
+
+        if first_contact.time < f32::INFINITY {
+            Some(first_contact)
+        } else {
+            None
+        }
+    }
+
     fn sweep_chunk(
         &self,
         first_contact: &mut ContactInfo,
@@ -108,12 +343,61 @@ impl World {
         triangle: Triangle,
     ) -> Option<ContactInfo> {
         let normal = self.verts[triangle.normal as usize].to_vec();
-        let triangle = [
+        sphere.sweep_triangle(&self.triangle_verts(triangle), normal, vel)
+    }
+
+    fn triangle_verts(&self, triangle: Triangle) -> [Pnt3f; 3] {
+        [
             self.verts[triangle.v1 as usize],
             self.verts[triangle.v2 as usize],
             self.verts[triangle.v3 as usize],
-        ];
-        sphere.sweep_triangle(&triangle, normal, vel)
+        ]
+    }
+}
+
+/// A solid thing, which blocks movement as a vertical cylinder.
+struct Solid {
+    object: EntityId,
+    center: Pnt2f,
+    bottom: f32,
+    height: f32,
+    radius: f32,
+}
+
+impl Solid {
+    /// Sweeps a sphere against the side of the cylinder, which is inflated by the sphere's radius
+    /// (including vertically, so spheres just above or below it are still stopped).
+    fn sweep(&self, sphere: Sphere, vel: Vec3f) -> Option<ContactInfo> {
+        let radius = self.radius + sphere.radius;
+        let offset = vec2(
+            sphere.center.x - self.center.x,
+            sphere.center.z - self.center.y,
+        );
+        let planar = vec2(vel.x, vel.z);
+        let a = planar.magnitude2();
+        let b = offset.dot(planar);
+        let c = offset.magnitude2() - radius * radius;
+        if a <= 0.0 || b >= 0.0 {
+            return None;
+        }
+        let time = if c <= 0.0 {
+            0.0
+        } else {
+            let discriminant = b * b - a * c;
+            if discriminant < 0.0 {
+                return None;
+            }
+            (-b - discriminant.sqrt()) / a
+        };
+        let y = sphere.center.y + vel.y * time;
+        if y < self.bottom - sphere.radius || y > self.bottom + self.height + sphere.radius {
+            return None;
+        }
+        let normal = offset + planar * time;
+        Some(ContactInfo {
+            time,
+            normal: Vec3f::new(normal.x, 0.0, normal.y).normalize_or_zero(),
+        })
     }
 }
 
@@ -256,6 +540,7 @@ impl<'a> WorldBuilder<'a> {
             verts: self.verts,
             node_stack: self.node_stack,
             dynamic_chunks,
+            solids: Vec::new(),
             triangles,
         }
     }
@@ -407,3 +692,10 @@ impl<'a> LevelVisitor for WorldBuilder<'a> {
         );
     }
 }
+
+/// Rays are swept as tiny spheres, which reuses the sphere-triangle tests.
+const RAY_RADIUS: f32 = 1e-3;
+
+/// Triangles whose normals have a larger vertical component than this are floors or ceilings,
+/// the rest are walls.
+const FLAT_NORMAL_Y: f32 = 0.5;
@@ -0,0 +1,177 @@
+use super::contact::ContactInfo;
+use super::prelude::*;
+use super::{vec2, Pnt2f, Pnt3f, Vec2f, Vec3f};
+
+/// An upright cylinder, standing on `base`.
+#[derive(Copy, Clone, Debug)]
+pub struct Cylinder {
+    pub base: Pnt3f,
+    pub radius: f32,
+    pub height: f32,
+}
+
+impl Cylinder {
+    pub fn new(base: Pnt3f, radius: f32, height: f32) -> Self {
+        Self {
+            base,
+            radius,
+            height,
+        }
+    }
+
+    pub fn top(&self) -> f32 {
+        self.base.y + self.height
+    }
+
+    /// The center of the cylinder's footprint on the horizontal plane, as `(x, z)`.
+    pub fn center(&self) -> Pnt2f {
+        Pnt2f::new(self.base.x, self.base.z)
+    }
+
+    /// Sweeps the cylinder horizontally against a wall segment, given as its `(x, z)` end
+    /// points. Only the horizontal part of `vel` is used.
+    ///
+    /// Returns `None` if the cylinder doesn't hit the segment or is moving away from it. The
+    /// returned normal is horizontal.
+    pub fn sweep_segment(&self, segment: [Pnt2f; 2], vel: Vec3f) -> Option<ContactInfo> {
+        let planar = vec2(vel.x, vel.z);
+        if planar.magnitude2() == 0.0 {
+            return None;
+        }
+        let center = self.center();
+        let edge = segment[1] - segment[0];
+        let length2 = edge.magnitude2();
+
+        let mut first: Option<(f32, Vec2f)> = None;
+        let mut consider = |time: f32, normal: Vec2f| {
+            if normal.dot(planar) < 0.0 && first.is_none_or(|(first, _)| time < first) {
+                first = Some((time, normal));
+            }
+        };
+
+        if length2 > 0.0 {
+            // Against the side of the segment facing the cylinder.
+            let mut normal = vec2(-edge.y, edge.x) / length2.sqrt();
+            let mut distance = normal.dot(center - segment[0]);
+            if distance < 0.0 {
+                normal = -normal;
+                distance = -distance;
+            }
+            let approach = -normal.dot(planar);
+            let time = if distance <= self.radius {
+                Some(0.0)
+            } else if approach > 0.0 {
+                Some((distance - self.radius) / approach)
+            } else {
+                None
+            };
+            if let Some(time) = time {
+                let touch = center + planar * time - normal * self.radius;
+                let along = (touch - segment[0]).dot(edge);
+                if (0.0..=length2).contains(&along) {
+                    consider(time, normal);
+                }
+            }
+        }
+
+        // Against the end points.
+        for &point in &segment {
+            let offset = center - point;
+            let a = planar.magnitude2();
+            let b = offset.dot(planar);
+            let c = offset.magnitude2() - self.radius * self.radius;
+            let time = if c <= 0.0 {
+                0.0
+            } else {
+                let discriminant = b * b - a * c;
+                if b >= 0.0 || discriminant < 0.0 {
+                    continue;
+                }
+                (-b - discriminant.sqrt()) / a
+            };
+            consider(time, (offset + planar * time).normalize_or_zero());
+        }
+
+        first.map(|(time, normal)| ContactInfo {
+            time,
+            normal: Vec3f::new(normal.x, 0.0, normal.y),
+        })
+    }
+
+    /// Whether the cylinder's footprint overlaps a triangle projected on the horizontal plane.
+    pub fn overlaps_footprint(&self, triangle: &[Pnt3f; 3]) -> bool {
+        let center = self.center();
+        let points = [
+            Pnt2f::new(triangle[0].x, triangle[0].z),
+            Pnt2f::new(triangle[1].x, triangle[1].z),
+            Pnt2f::new(triangle[2].x, triangle[2].z),
+        ];
+        let mut sides = [0.0; 3];
+        for (index, side) in sides.iter_mut().enumerate() {
+            let (start, end) = (points[index], points[(index + 1) % 3]);
+            let edge = end - start;
+            let to_center = center - start;
+            *side = edge.x * to_center.y - edge.y * to_center.x;
+            let along = (to_center.dot(edge) / edge.magnitude2().max(f32::EPSILON)).clamp(0.0, 1.0);
+            if (to_center - edge * along).magnitude2() < self.radius * self.radius {
+                return true;
+            }
+        }
+        sides.iter().all(|&side| side >= 0.0) || sides.iter().all(|&side| side <= 0.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Cylinder;
+    use crate::{Pnt2f, Pnt3f, Vec3f};
+
+    #[test]
+    fn sweeps_segments() {
+        let cylinder = Cylinder::new(Pnt3f::new(0.0, 0.0, 0.0), 1.0, 2.0);
+        let wall = [Pnt2f::new(3.0, -5.0), Pnt2f::new(3.0, 5.0)];
+
+        let contact = cylinder
+            .sweep_segment(wall, Vec3f::new(4.0, 0.0, 0.0))
+            .expect("test: missed wall");
+        assert!((contact.time - 0.5).abs() < 1e-6);
+        assert_eq!(contact.normal, Vec3f::new(-1.0, 0.0, 0.0));
+
+        assert!(cylinder
+            .sweep_segment(wall, Vec3f::new(-4.0, 0.0, 0.0))
+            .is_none());
+        assert!(cylinder
+            .sweep_segment(wall, Vec3f::new(0.0, 0.0, 4.0))
+            .is_none());
+
+        // Clipping the end of a segment.
+        let post = [Pnt2f::new(3.0, 0.5), Pnt2f::new(3.0, 5.0)];
+        let contact = cylinder
+            .sweep_segment(post, Vec3f::new(4.0, 0.0, 0.0))
+            .expect("test: missed post");
+        assert!(contact.time > 0.5 && contact.time < 1.0);
+    }
+
+    #[test]
+    fn overlaps_footprints() {
+        let cylinder = Cylinder::new(Pnt3f::new(0.0, 0.0, 0.0), 1.0, 2.0);
+        let around = [
+            Pnt3f::new(-5.0, 1.0, -5.0),
+            Pnt3f::new(5.0, 1.0, -5.0),
+            Pnt3f::new(0.0, 1.0, 5.0),
+        ];
+        let near = [
+            Pnt3f::new(1.5, 0.0, -5.0),
+            Pnt3f::new(0.5, 0.0, 5.0),
+            Pnt3f::new(5.0, 0.0, 0.0),
+        ];
+        let far = [
+            Pnt3f::new(2.0, 0.0, -5.0),
+            Pnt3f::new(2.0, 0.0, 5.0),
+            Pnt3f::new(5.0, 0.0, 0.0),
+        ];
+        assert!(cylinder.overlaps_footprint(&around));
+        assert!(cylinder.overlaps_footprint(&near));
+        assert!(!cylinder.overlaps_footprint(&far));
+    }
+}
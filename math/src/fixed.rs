@@ -0,0 +1,459 @@
+//! Fixed-point numbers and binary angles, like the original's `fixed_t` and `angle_t`.
+//!
+//! Unlike `f32` math, everything here is plain integer arithmetic, so simulations built on it
+//! give bit-identical results on every platform and build, as demos and network play need.
+
+use super::{Pnt2f, Rad};
+use std::f64::consts::PI;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+/// A 16.16 fixed-point number.
+///
+/// Arithmetic wraps on overflow, like it did in the original, rather than panicking.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fixed(i32);
+
+impl Fixed {
+    pub const FRAC_BITS: u32 = 16;
+    pub const ZERO: Fixed = Fixed(0);
+    pub const ONE: Fixed = Fixed(1 << Self::FRAC_BITS);
+    pub const MIN: Fixed = Fixed(i32::MIN);
+    pub const MAX: Fixed = Fixed(i32::MAX);
+
+    pub const fn from_bits(bits: i32) -> Self {
+        Fixed(bits)
+    }
+
+    pub const fn to_bits(self) -> i32 {
+        self.0
+    }
+
+    pub const fn from_int(value: i32) -> Self {
+        Fixed(value.wrapping_shl(Self::FRAC_BITS))
+    }
+
+    /// The integer part, rounded towards negative infinity.
+    pub const fn to_int(self) -> i32 {
+        self.0 >> Self::FRAC_BITS
+    }
+
+    /// Converts a map coordinate (a `WadCoord`), in the original's units.
+    pub const fn from_coord(coord: i16) -> Self {
+        Self::from_int(coord as i32)
+    }
+
+    /// Converts back to a map coordinate, rounding towards negative infinity.
+    pub const fn to_coord(self) -> i16 {
+        self.to_int() as i16
+    }
+
+    /// Converts from a float, rounding to the nearest representable value and saturating.
+    pub fn from_f32(value: f32) -> Self {
+        Fixed((f64::from(value) * f64::from(1 << Self::FRAC_BITS)).round() as i32)
+    }
+
+    pub fn to_f32(self) -> f32 {
+        (f64::from(self.0) / f64::from(1 << Self::FRAC_BITS)) as f32
+    }
+
+    /// Converts a length in world units (map units divided by 100, see `wad::util`).
+    pub fn from_world(value: f32) -> Self {
+        Self::from_f32(value * WORLD_SCALE)
+    }
+
+    pub fn to_world(self) -> f32 {
+        self.to_f32() / WORLD_SCALE
+    }
+
+    pub const fn abs(self) -> Self {
+        Fixed(self.0.wrapping_abs())
+    }
+}
+
+impl From<i16> for Fixed {
+    fn from(coord: i16) -> Self {
+        Self::from_coord(coord)
+    }
+}
+
+impl Add for Fixed {
+    type Output = Fixed;
+
+    fn add(self, other: Fixed) -> Fixed {
+        Fixed(self.0.wrapping_add(other.0))
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Fixed;
+
+    fn sub(self, other: Fixed) -> Fixed {
+        Fixed(self.0.wrapping_sub(other.0))
+    }
+}
+
+impl Neg for Fixed {
+    type Output = Fixed;
+
+    fn neg(self) -> Fixed {
+        Fixed(self.0.wrapping_neg())
+    }
+}
+
+/// `FixedMul` in the original.
+impl Mul for Fixed {
+    type Output = Fixed;
+
+    fn mul(self, other: Fixed) -> Fixed {
+        Fixed(((i64::from(self.0) * i64::from(other.0)) >> Self::FRAC_BITS) as i32)
+    }
+}
+
+impl Mul<i32> for Fixed {
+    type Output = Fixed;
+
+    fn mul(self, other: i32) -> Fixed {
+        Fixed(self.0.wrapping_mul(other))
+    }
+}
+
+/// `FixedDiv` in the original, which saturates rather than overflowing.
+impl Div for Fixed {
+    type Output = Fixed;
+
+    fn div(self, other: Fixed) -> Fixed {
+        if (self.0.unsigned_abs() >> 14) >= other.0.unsigned_abs() {
+            if (self.0 ^ other.0) < 0 {
+                Fixed::MIN
+            } else {
+                Fixed::MAX
+            }
+        } else {
+            Fixed(((i64::from(self.0) << Self::FRAC_BITS) / i64::from(other.0)) as i32)
+        }
+    }
+}
+
+impl Div<i32> for Fixed {
+    type Output = Fixed;
+
+    fn div(self, other: i32) -> Fixed {
+        Fixed(self.0.wrapping_div(other))
+    }
+}
+
+impl AddAssign for Fixed {
+    fn add_assign(&mut self, other: Fixed) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Fixed {
+    fn sub_assign(&mut self, other: Fixed) {
+        *self = *self - other;
+    }
+}
+
+impl MulAssign for Fixed {
+    fn mul_assign(&mut self, other: Fixed) {
+        *self = *self * other;
+    }
+}
+
+impl DivAssign for Fixed {
+    fn div_assign(&mut self, other: Fixed) {
+        *self = *self / other;
+    }
+}
+
+/// A point in map coordinates, like the original's `x` and `y` pairs.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FixedPnt2 {
+    pub x: Fixed,
+    pub y: Fixed,
+}
+
+impl FixedPnt2 {
+    pub const fn new(x: Fixed, y: Fixed) -> Self {
+        Self { x, y }
+    }
+
+    /// Converts a pair of map coordinates (`WadCoord`s).
+    pub const fn from_coords(x: i16, y: i16) -> Self {
+        Self::new(Fixed::from_coord(x), Fixed::from_coord(y))
+    }
+
+    /// Converts a point in world coordinates, undoing `wad::util::from_wad_coords`.
+    pub fn from_world(point: Pnt2f) -> Self {
+        Self::new(Fixed::from_world(-point.y), Fixed::from_world(-point.x))
+    }
+
+    /// Converts to world coordinates, like `wad::util::from_wad_coords`.
+    pub fn to_world(self) -> Pnt2f {
+        Pnt2f::new(-self.y.to_world(), -self.x.to_world())
+    }
+}
+
+/// A binary angle measurement, like the original's `angle_t`: the full circle is 2^32, so
+/// angles wrap around on their own.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Bam(pub u32);
+
+impl Bam {
+    pub const ANG45: Bam = Bam(0x2000_0000);
+    pub const ANG90: Bam = Bam(0x4000_0000);
+    pub const ANG180: Bam = Bam(0x8000_0000);
+    pub const ANG270: Bam = Bam(0xc000_0000);
+
+    pub fn from_rad(angle: Rad<f32>) -> Self {
+        let turns = f64::from(angle.0) / (2.0 * PI);
+        Bam(((turns - turns.floor()) * BAM_PER_TURN) as u64 as u32)
+    }
+
+    pub fn to_rad(self) -> Rad<f32> {
+        Rad((f64::from(self.0) / BAM_PER_TURN * 2.0 * PI) as f32)
+    }
+
+    /// The index of the angle in the fine tables, `angle >> ANGLETOFINESHIFT` in the original.
+    pub const fn fine(self) -> usize {
+        (self.0 >> ANGLE_TO_FINE_SHIFT) as usize
+    }
+
+    pub fn sin(self) -> Fixed {
+        FINE_SINE[self.fine()]
+    }
+
+    pub fn cos(self) -> Fixed {
+        FINE_SINE[self.fine() + FINE_ANGLES / 4]
+    }
+
+    /// The angle of the direction from `from` to `to`, `R_PointToAngle2` in the original.
+    pub fn between(from: FixedPnt2, to: FixedPnt2) -> Self {
+        let (x, y) = ((to.x - from.x).0, (to.y - from.y).0);
+        if x == 0 && y == 0 {
+            return Bam(0);
+        }
+        let (ax, ay) = (x.unsigned_abs(), y.unsigned_abs());
+        let angle = match (x >= 0, y >= 0, ax > ay) {
+            (true, true, true) => TAN_TO_ANGLE[slope_div(ay, ax)],
+            (true, true, false) => 0x3fff_ffff - TAN_TO_ANGLE[slope_div(ax, ay)],
+            (true, false, true) => TAN_TO_ANGLE[slope_div(ay, ax)].wrapping_neg(),
+            (true, false, false) => 0xc000_0000 + TAN_TO_ANGLE[slope_div(ax, ay)],
+            (false, true, true) => 0x7fff_ffff - TAN_TO_ANGLE[slope_div(ay, ax)],
+            (false, true, false) => 0x4000_0000 + TAN_TO_ANGLE[slope_div(ax, ay)],
+            (false, false, true) => 0x8000_0000 + TAN_TO_ANGLE[slope_div(ay, ax)],
+            (false, false, false) => 0xbfff_ffff - TAN_TO_ANGLE[slope_div(ax, ay)],
+        };
+        Bam(angle)
+    }
+}
+
+impl From<Bam> for Rad<f32> {
+    fn from(angle: Bam) -> Self {
+        angle.to_rad()
+    }
+}
+
+impl Add for Bam {
+    type Output = Bam;
+
+    fn add(self, other: Bam) -> Bam {
+        Bam(self.0.wrapping_add(other.0))
+    }
+}
+
+impl Sub for Bam {
+    type Output = Bam;
+
+    fn sub(self, other: Bam) -> Bam {
+        Bam(self.0.wrapping_sub(other.0))
+    }
+}
+
+impl Neg for Bam {
+    type Output = Bam;
+
+    fn neg(self) -> Bam {
+        Bam(self.0.wrapping_neg())
+    }
+}
+
+impl AddAssign for Bam {
+    fn add_assign(&mut self, other: Bam) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Bam {
+    fn sub_assign(&mut self, other: Bam) {
+        *self = *self - other;
+    }
+}
+
+/// The number of angles in the fine tables, `FINEANGLES` in the original.
+pub const FINE_ANGLES: usize = 8192;
+
+/// The sine of every fine angle, followed by another quarter circle so cosines can index into
+/// it too, `finesine` in the original.
+pub static FINE_SINE: [Fixed; FINE_ANGLES * 5 / 4] = fine_sine_table();
+
+/// The number of slopes in `TAN_TO_ANGLE`, `SLOPERANGE` in the original.
+pub const SLOPE_RANGE: usize = 2048;
+
+/// The angle of every slope from 0 to 1 in `SLOPE_RANGE` steps, `tantoangle` in the original.
+pub static TAN_TO_ANGLE: [u32; SLOPE_RANGE + 1] = tan_to_angle_table();
+
+const ANGLE_TO_FINE_SHIFT: u32 = 19;
+
+/// World units are map units divided by 100.
+const WORLD_SCALE: f32 = 100.0;
+
+const BAM_PER_TURN: f64 = 4_294_967_296.0;
+
+/// The index of the slope `num / den` (at most one) in `TAN_TO_ANGLE`, `SlopeDiv` in the
+/// original.
+fn slope_div(num: u32, den: u32) -> usize {
+    if den < 512 {
+        return SLOPE_RANGE;
+    }
+    let slope = ((num << 3) / (den >> 8)) as usize;
+    slope.min(SLOPE_RANGE)
+}
+
+// The tables are generated the way the original's were, but at compile time, so they don't
+// depend on the platform's floating point library.
+
+const fn fine_sine_table() -> [Fixed; FINE_ANGLES * 5 / 4] {
+    let mut table = [Fixed(0); FINE_ANGLES * 5 / 4];
+    let mut index = 0;
+    while index < table.len() {
+        let angle = (index as f64 + 0.5) * 2.0 * PI / FINE_ANGLES as f64;
+        table[index] = Fixed((const_sin(angle) * 65536.0) as i32);
+        index += 1;
+    }
+    table
+}
+
+const fn tan_to_angle_table() -> [u32; SLOPE_RANGE + 1] {
+    let mut table = [0; SLOPE_RANGE + 1];
+    let mut index = 0;
+    while index < table.len() {
+        let angle = const_atan(index as f64 / SLOPE_RANGE as f64);
+        table[index] = (angle / (2.0 * PI) * BAM_PER_TURN) as u32;
+        index += 1;
+    }
+    table
+}
+
+/// Sine of an angle in `[0, 4π)`, folded into `[0, π/2]` and summed as a Taylor series.
+const fn const_sin(mut angle: f64) -> f64 {
+    if angle >= 2.0 * PI {
+        angle -= 2.0 * PI;
+    }
+    let (angle, sign) = if angle >= PI {
+        (angle - PI, -1.0)
+    } else {
+        (angle, 1.0)
+    };
+    let angle = if angle > PI / 2.0 { PI - angle } else { angle };
+
+    let square = angle * angle;
+    let mut term = angle;
+    let mut sum = angle;
+    let mut power = 1;
+    while power < 31 {
+        term = -term * square / ((power + 1) * (power + 2)) as f64;
+        sum += term;
+        power += 2;
+    }
+    sign * sum
+}
+
+/// Arctangent of a value in `[0, 1]`, halving the angle twice before summing its Taylor series.
+const fn const_atan(value: f64) -> f64 {
+    let mut value = value;
+    let mut halvings = 0;
+    while halvings < 2 {
+        value /= 1.0 + const_sqrt(1.0 + value * value);
+        halvings += 1;
+    }
+
+    let square = value * value;
+    let mut term = value;
+    let mut sum = value;
+    let mut power = 1;
+    while power < 41 {
+        term = -term * square;
+        sum += term / (power + 2) as f64;
+        power += 2;
+    }
+    sum * 4.0
+}
+
+/// Square root of a value in `[1, 2]` by Newton's method.
+const fn const_sqrt(value: f64) -> f64 {
+    let mut root = value;
+    let mut iteration = 0;
+    while iteration < 8 {
+        root = (root + value / root) / 2.0;
+        iteration += 1;
+    }
+    root
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Bam, Fixed, FixedPnt2, FINE_SINE, TAN_TO_ANGLE};
+    use crate::Pnt2f;
+
+    #[test]
+    fn fixed_arithmetic() {
+        let half = Fixed::from_bits(0x8000);
+        assert_eq!(Fixed::from_int(3) * half, Fixed::from_bits(0x1_8000));
+        assert_eq!(Fixed::ONE / Fixed::from_int(4), Fixed::from_bits(0x4000));
+        assert_eq!(Fixed::from_int(-3).to_int(), -3);
+        assert_eq!(Fixed::from_bits(-1).to_int(), -1);
+        assert_eq!(Fixed::from_int(1 << 14) / half, Fixed::MAX);
+        assert_eq!(-Fixed::from_int(1 << 14) / half, Fixed::MIN);
+        assert_eq!(Fixed::MAX + Fixed::from_bits(1), Fixed::MIN);
+        assert_eq!(Fixed::from_f32(1.5), Fixed::from_bits(0x1_8000));
+        assert_eq!(Fixed::from(-128i16).to_coord(), -128);
+    }
+
+    #[test]
+    fn world_points_round_trip() {
+        let point = FixedPnt2::from_coords(1056, -3616);
+        assert_eq!(point.to_world(), Pnt2f::new(36.16, -10.56));
+        assert_eq!(FixedPnt2::from_world(point.to_world()), point);
+    }
+
+    #[test]
+    fn tables_match_the_original() {
+        assert_eq!(FINE_SINE[0], Fixed::from_bits(25));
+        assert_eq!(FINE_SINE[1], Fixed::from_bits(75));
+        assert_eq!(FINE_SINE[2047], Fixed::from_bits(65535));
+        assert_eq!(FINE_SINE[4096], Fixed::from_bits(-25));
+        assert_eq!(TAN_TO_ANGLE[0], 0);
+        assert_eq!(TAN_TO_ANGLE[1], 333_772);
+        assert_eq!(TAN_TO_ANGLE[2048], 0x2000_0000);
+    }
+
+    #[test]
+    fn angles() {
+        let origin = FixedPnt2::default();
+        let east = FixedPnt2::from_coords(64, 0);
+        let north = FixedPnt2::from_coords(0, 64);
+        let west = FixedPnt2::from_coords(-64, 0);
+        let south_east = FixedPnt2::from_coords(64, -64);
+        assert_eq!(Bam::between(origin, east), Bam(0));
+        assert_eq!(Bam::between(origin, north), Bam(0x3fff_ffff));
+        assert_eq!(Bam::between(origin, west), Bam(0x7fff_ffff));
+        assert_eq!(
+            Bam::between(origin, south_east),
+            Bam(0u32.wrapping_sub(0x2000_0000))
+        );
+        assert_eq!(Bam::ANG90.sin(), FINE_SINE[2048]);
+        assert_eq!(Bam(0).cos(), FINE_SINE[2048]);
+        assert_eq!(Bam::ANG270 + Bam::ANG180, Bam::ANG90);
+    }
+}
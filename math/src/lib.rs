@@ -24,10 +24,14 @@ pub use cgmath::{
 pub use num_traits::{Float, NumCast};
 
 pub mod contact;
+pub mod cylinder;
+pub mod fixed;
 pub mod line;
 pub mod sphere;
 
 pub use self::contact::ContactInfo;
+pub use self::cylinder::Cylinder;
+pub use self::fixed::{Bam, Fixed, FixedPnt2};
 pub use self::line::Line2;
 pub use self::sphere::Sphere;
 
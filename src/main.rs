@@ -1,12 +1,14 @@
 use anyhow::{bail, Result};
-use game::{self, Game, GameConfig};
+use engine::{list_adapters, Backend, PowerPreference};
+use game::{self, CameraPlacement, Game, GameConfig};
 use log::{error, info};
-use math::DurationExt;
+use math::{Deg, DurationExt, Pnt3f, Rad};
 use std::env;
 use std::path::PathBuf;
 use std::process;
 use std::time::Instant;
 use structopt::StructOpt;
+use wad::util::{from_wad_coords, from_wad_height};
 use wad::Archive;
 
 #[derive(StructOpt)]
@@ -64,11 +66,61 @@ struct App {
     /// Horizontal field of view.
     fov: f32,
 
+    #[structopt(long = "cylinder-collision")]
+    /// Collide the player as a cylinder with the original's size and step height.
+    cylinder_collision: bool,
+
+    #[structopt(long = "seed", value_name = "N")]
+    /// Seed for all random numbers, making runs reproducible.
+    seed: Option<u64>,
+
+    #[structopt(long = "record", value_name = "FILE", parse(from_os_str))]
+    /// Record the input to a demo file, to reproduce the run later.
+    record: Option<PathBuf>,
+
+    #[structopt(long = "playdemo", value_name = "FILE", parse(from_os_str))]
+    /// Play back a demo file recorded with `--record`, an original `.lmp` demo, or one of the
+    /// WAD's demo lumps, like `DEMO1`.
+    playdemo: Option<PathBuf>,
+
+    #[structopt(long = "headless")]
+    /// Run without a window, e.g. to play back demos where there's no display.
+    headless: bool,
+
+    #[structopt(long = "fullscreen")]
+    /// Start in borderless fullscreen; F11 toggles it while playing.
+    fullscreen: bool,
+
+    #[structopt(long = "msaa", default_value = "4", value_name = "SAMPLES")]
+    /// Multisampling samples per pixel: 1 (off), 2, 4 or 8.
+    msaa: u32,
+
+    #[structopt(long = "no-vsync")]
+    /// Present frames as soon as they're rendered, without waiting for vertical sync.
+    no_vsync: bool,
+
+    #[structopt(long = "render-scale", default_value = "1", value_name = "SCALE")]
+    /// Render the scene at this fraction of the window's resolution and upscale it without
+    /// filtering, e.g. 0.25 for a chunky look.
+    render_scale: f32,
+
+    #[structopt(long = "backend", default_value = "auto", value_name = "BACKEND")]
+    /// Graphics API: auto, vulkan, metal, dx12, gl, or software for a CPU fallback adapter.
+    backend: Backend,
+
+    #[structopt(long = "power", default_value = "any", value_name = "PREFERENCE")]
+    /// Prefer a low or high power adapter when there are several, or any.
+    power_preference: PowerPreference,
+
+    #[structopt(long = "list-adapters")]
+    /// List the graphics adapters available with the chosen backend, then exit.
+    list_adapters: bool,
+
     #[structopt(subcommand)]
     command: Option<Command>,
 }
 
-#[derive(StructOpt, Copy, Clone)]
+#[derive(StructOpt, Clone)]
 enum Command {
     /// Load metadata and all levels in WAD, then exit.
     #[structopt(name = "check")]
@@ -77,6 +129,32 @@ enum Command {
     /// List the names and indices of all the leves in the WAD, then exit.
     #[structopt(name = "list-levels")]
     ListLevelNames,
+
+    /// Render one frame of the level offscreen to a PNG file, then exit.
+    #[structopt(name = "render")]
+    Render {
+        #[structopt(
+            short = "o",
+            long = "output",
+            default_value = "render.png",
+            value_name = "FILE",
+            parse(from_os_str)
+        )]
+        /// The PNG file to write.
+        output: PathBuf,
+
+        #[structopt(
+            long = "position",
+            value_name = "X,Y,Z",
+            parse(try_from_str = parse_position)
+        )]
+        /// Camera position in map units, with Z the height. Defaults to the player start.
+        position: Option<(i16, i16, i16)>,
+
+        #[structopt(long = "angle", value_name = "DEGREES")]
+        /// Camera angle like a thing's, with 0 facing east. Defaults to the player start's.
+        angle: Option<f32>,
+    },
 }
 
 impl App {
@@ -86,7 +164,7 @@ impl App {
     }
 
     /// Either play the game (if no `Command` was passed), or perform the command.
-    pub fn run(self) -> Result<(), anyhow::Error> {
+    pub fn run(mut self) -> Result<(), anyhow::Error> {
         // Init logging, with default `info` level.
         env_logger::Builder::from_env(
             env_logger::Env::default().filter_or(env_logger::DEFAULT_FILTER_ENV, "info"),
@@ -94,13 +172,33 @@ impl App {
         .format_timestamp(None)
         .init();
 
-        match self.command {
+        if self.list_adapters {
+            let adapters = list_adapters(self.backend);
+            if adapters.is_empty() {
+                bail!("No graphics adapters found for backend {:?}.", self.backend);
+            }
+            for (index, adapter) in adapters.iter().enumerate() {
+                println!(
+                    "{:3} {} ({:?}, {:?}, driver: {} {})",
+                    index,
+                    adapter.name,
+                    adapter.backend,
+                    adapter.device_type,
+                    adapter.driver,
+                    adapter.driver_info
+                );
+            }
+            return Ok(());
+        }
+
+        match self.command.take() {
             None => {
                 game::create(&self.into_config())?.run()?;
             }
             Some(Command::Check) => {
                 let mut game = game::create(&GameConfig {
                     initial_level_index: 0,
+                    headless: true,
                     ..self.into_config()
                 })?;
                 info!("Loading all levels...");
@@ -119,6 +217,25 @@ impl App {
                     println!("{:3} {:8}", i_level, wad.level_lump(i_level)?.name());
                 }
             }
+            Some(Command::Render {
+                output,
+                position,
+                angle,
+            }) => {
+                let mut game = game::create(&GameConfig {
+                    headless: true,
+                    ..self.into_config()
+                })?;
+                let placement = CameraPlacement {
+                    position: position.map(|(x, y, z)| {
+                        let xz = from_wad_coords(x, y);
+                        Pnt3f::new(xz.x, from_wad_height(z), xz.y)
+                    }),
+                    yaw: angle.map(|angle| Rad::from(Deg(angle))),
+                };
+                game.render(placement, &output)?;
+                info!("Rendered {:?}.", output);
+            }
         }
         Ok(())
     }
@@ -133,10 +250,33 @@ impl App {
             height: self.resolution.1,
             version: env!("CARGO_PKG_VERSION"),
             initial_level_index: self.level_index,
+            cylinder_collision: self.cylinder_collision,
+            seed: self.seed,
+            record_demo: self.record,
+            play_demo: self.playdemo,
+            headless: self.headless,
+            fullscreen: self.fullscreen,
+            sample_count: self.msaa,
+            vsync: !self.no_vsync,
+            render_scale: self.render_scale,
+            backend: self.backend,
+            power_preference: self.power_preference,
         }
     }
 }
 
+/// Parse a position string like `X,Y,Z` into `(x, y, z)`.
+fn parse_position(position_str: &str) -> anyhow::Result<(i16, i16, i16)> {
+    let coords = position_str
+        .split(',')
+        .map(|coord| coord.trim().parse::<i16>())
+        .collect::<Result<Vec<_>, _>>();
+    match coords.as_deref() {
+        Ok(&[x, y, z]) => Ok((x, y, z)),
+        _ => bail!("Position format must be X,Y,Z"),
+    }
+}
+
 /// Parse a resolution string like `WIDTHxHEIGHT` into `(width, height)`.
 fn parse_resolution(size_str: &str) -> anyhow::Result<(u32, u32)> {
     let size_if_ok = size_str
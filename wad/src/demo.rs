@@ -0,0 +1,248 @@
+//! The original's demos, as `.lmp` files or the `DEMOn` lumps of IWADs.
+
+use anyhow::{bail, Result};
+use std::f32::consts::PI;
+
+/// The first version byte with the long header; older demos start straight with the skill.
+const LONG_HEADER_VERSION: u8 = 104;
+/// The last version of the original.
+const LAST_VERSION: u8 = 109;
+
+/// Marks the end of the tics.
+const DEMO_MARKER: u8 = 0x80;
+
+const MAX_PLAYERS: usize = 4;
+
+const BT_ATTACK: u8 = 1;
+const BT_USE: u8 = 2;
+const BT_CHANGE: u8 = 4;
+const BT_WEAPON_MASK: u8 = 8 | 16 | 32;
+const BT_WEAPON_SHIFT: u8 = 3;
+const BT_SPECIAL: u8 = 128;
+
+/// The settings a demo was recorded with.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DemoHeader {
+    /// The game version, `None` for demos older than 1.4 which don't store it.
+    pub version: Option<u8>,
+    pub skill: u8,
+    pub episode: u8,
+    pub map: u8,
+    pub deathmatch: u8,
+    pub respawn: bool,
+    pub fast: bool,
+    pub no_monsters: bool,
+    /// The player whose view was recorded.
+    pub console_player: u8,
+    pub players: [bool; MAX_PLAYERS],
+}
+
+impl DemoHeader {
+    /// The names the demo's level may have, `ExMy` first and `MAPxx` second.
+    pub fn level_names(&self) -> [String; 2] {
+        [
+            format!("E{}M{}", self.episode, self.map),
+            format!("MAP{:02}", self.map),
+        ]
+    }
+
+    pub fn num_players(&self) -> usize {
+        self.players.iter().filter(|&&playing| playing).count()
+    }
+}
+
+/// A player's input during one tic, `ticcmd_t` in the original.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TicCmd {
+    /// Forwards movement, up to 50 when running, negative backwards.
+    pub forward: i8,
+    /// Sideways movement, up to 40 when running, positive to the right.
+    pub side: i8,
+    /// Turning as the upper 16 bits of an angle, positive to the left. Demos only keep the
+    /// upper byte.
+    pub turn: i16,
+    pub buttons: u8,
+}
+
+impl TicCmd {
+    /// The turn in radians, positive to the left.
+    pub fn turn_rad(&self) -> f32 {
+        f32::from(self.turn) / 32768.0 * PI
+    }
+
+    pub fn attack(&self) -> bool {
+        self.buttons & (BT_SPECIAL | BT_ATTACK) == BT_ATTACK
+    }
+
+    pub fn use_(&self) -> bool {
+        self.buttons & (BT_SPECIAL | BT_USE) == BT_USE
+    }
+
+    /// The original's number of the weapon switched to, if any: fist, pistol, shotgun,
+    /// chaingun, rocket launcher, plasma rifle, BFG and chainsaw, in order.
+    pub fn weapon_change(&self) -> Option<u8> {
+        (self.buttons & (BT_SPECIAL | BT_CHANGE) == BT_CHANGE)
+            .then_some((self.buttons & BT_WEAPON_MASK) >> BT_WEAPON_SHIFT)
+    }
+
+    /// Special commands, like pausing or saving, which don't move the player.
+    pub fn special(&self) -> bool {
+        self.buttons & BT_SPECIAL != 0
+    }
+}
+
+/// A parsed demo: its header, then one command per player in the game for every tic.
+#[derive(Clone, Debug)]
+pub struct Demo {
+    pub header: DemoHeader,
+    commands: Vec<TicCmd>,
+}
+
+impl Demo {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (header, rest) = match bytes {
+            [skill @ 0..=4, episode, map, players @ ..] if players.len() >= MAX_PLAYERS => (
+                DemoHeader {
+                    version: None,
+                    skill: *skill,
+                    episode: *episode,
+                    map: *map,
+                    deathmatch: 0,
+                    respawn: false,
+                    fast: false,
+                    no_monsters: false,
+                    console_player: 0,
+                    players: read_players(players),
+                },
+                &players[MAX_PLAYERS..],
+            ),
+            [version @ LONG_HEADER_VERSION..=LAST_VERSION, skill, episode, map, deathmatch, respawn, fast, no_monsters, console_player, players @ ..]
+                if players.len() >= MAX_PLAYERS =>
+            {
+                (
+                    DemoHeader {
+                        version: Some(*version),
+                        skill: *skill,
+                        episode: *episode,
+                        map: *map,
+                        deathmatch: *deathmatch,
+                        respawn: *respawn != 0,
+                        fast: *fast != 0,
+                        no_monsters: *no_monsters != 0,
+                        console_player: *console_player,
+                        players: read_players(players),
+                    },
+                    &players[MAX_PLAYERS..],
+                )
+            }
+            [version, ..]
+                if (5..LONG_HEADER_VERSION).contains(version) || *version > LAST_VERSION =>
+            {
+                bail!("Unsupported demo version {}.", version)
+            }
+            _ => bail!("Demo header is truncated."),
+        };
+
+        let num_players = header.num_players();
+        if num_players == 0 {
+            bail!("Demo has no players.");
+        }
+        if !header
+            .players
+            .get(usize::from(header.console_player))
+            .copied()
+            .unwrap_or(false)
+        {
+            bail!(
+                "Demo's console player {} is not in the game.",
+                header.console_player
+            );
+        }
+
+        let mut commands = Vec::with_capacity(rest.len() / 4);
+        let mut tics = rest.chunks(4 * num_players);
+        loop {
+            match tics.next() {
+                Some([DEMO_MARKER, ..]) => break,
+                Some(tic) if tic.len() == 4 * num_players => {
+                    commands.extend(tic.chunks(4).map(|command| TicCmd {
+                        forward: command[0] as i8,
+                        side: command[1] as i8,
+                        turn: i16::from_le_bytes([0, command[2]]),
+                        buttons: command[3],
+                    }))
+                }
+                Some(_) => bail!("Demo ends in the middle of a tic."),
+                None => bail!("Demo is missing its end marker."),
+            }
+        }
+        Ok(Demo { header, commands })
+    }
+
+    pub fn num_tics(&self) -> usize {
+        self.commands.len() / self.header.num_players()
+    }
+
+    /// The commands of the console player, one per tic.
+    pub fn console_commands(&self) -> impl Iterator<Item = TicCmd> + '_ {
+        let slot = self.header.players[..usize::from(self.header.console_player)]
+            .iter()
+            .filter(|&&playing| playing)
+            .count();
+        self.commands
+            .iter()
+            .skip(slot)
+            .step_by(self.header.num_players())
+            .copied()
+    }
+}
+
+fn read_players(bytes: &[u8]) -> [bool; MAX_PLAYERS] {
+    let mut players = [false; MAX_PLAYERS];
+    for (playing, &byte) in players.iter_mut().zip(bytes) {
+        *playing = byte != 0;
+    }
+    players
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Demo, TicCmd};
+
+    #[test]
+    fn parses_demos() {
+        // Players 0 and 1 are in the game and the demo follows player 1.
+        let header = [109, 2, 1, 3, 0, 0, 0, 0, 1, 1, 1, 0, 0];
+        let first_tic = [25, 0, 0, 0, 50, 0xf8, 0xff, 1];
+        let second_tic = [0, 0, 0, 0, 0, 0, 0, 4 | 2 << 3];
+        let bytes = [&header[..], &first_tic, &second_tic, &[0x80]].concat();
+        let demo = Demo::from_bytes(&bytes).expect("test: invalid demo");
+        assert_eq!(demo.header.version, Some(109));
+        assert_eq!(demo.header.skill, 2);
+        assert_eq!(demo.header.level_names()[0], "E1M3");
+        assert_eq!(demo.num_tics(), 2);
+
+        let commands = demo.console_commands().collect::<Vec<_>>();
+        assert_eq!(
+            commands[0],
+            TicCmd {
+                forward: 50,
+                side: -8,
+                turn: -256,
+                buttons: 1,
+            }
+        );
+        assert!(commands[0].attack() && !commands[0].use_());
+        assert!(commands[0].turn_rad() < 0.0);
+        assert_eq!(commands[1].weapon_change(), Some(2));
+
+        // Old demos have no version and start with the skill.
+        let old = Demo::from_bytes(&[3, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0x80])
+            .expect("test: invalid old demo");
+        assert_eq!(old.header.version, None);
+        assert_eq!(old.num_tics(), 1);
+
+        assert!(Demo::from_bytes(&[110, 2, 1, 3]).is_err());
+        assert!(Demo::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -1,7 +1,7 @@
 use super::archive::Archive;
-use super::types::{LightLevel, SectorId, VertexId, WadNode, WadSector};
+use super::types::{ChildId, LightLevel, SectorId, VertexId, WadNode, WadSector};
 use super::types::{WadCoord, WadLinedef, WadSeg, WadSidedef, WadSubsector, WadThing, WadVertex};
-use super::util::from_wad_coords;
+use super::util::{from_wad_coords, parse_child_id, partition_line};
 use crate::types::SidedefId;
 use anyhow::Result;
 use geo::{coord, point, Contains, Polygon};
@@ -150,6 +150,24 @@ impl Level {
         }
     }
 
+    /// Returns the ids of the sectors on the right and left sides of a two-sided linedef.
+    pub fn linedef_sector_ids(&self, linedef: &WadLinedef) -> Option<(SectorId, SectorId)> {
+        Some((
+            self.right_sidedef(linedef)?.sector,
+            self.left_sidedef(linedef)?.sector,
+        ))
+    }
+
+    /// Iterates over the linedefs with the given sector on either side, in map order.
+    pub fn sector_linedefs(&self, sector_id: SectorId) -> impl Iterator<Item = &WadLinedef> {
+        self.linedefs.iter().filter(move |linedef| {
+            self.right_sidedef(linedef)
+                .into_iter()
+                .chain(self.left_sidedef(linedef))
+                .any(|sidedef| sidedef.sector == sector_id)
+        })
+    }
+
     pub fn sidedef_sector(&self, sidedef: &WadSidedef) -> Option<&WadSector> {
         self.sectors.get(sidedef.sector as usize)
     }
@@ -168,6 +186,26 @@ impl Level {
         }
     }
 
+    /// Finds the sector containing a (world space) point, by walking down the BSP tree.
+    pub fn sector_at(&self, pos: Pnt2f) -> Option<&WadSector> {
+        let mut child_id = self.nodes.len().checked_sub(1)? as ChildId;
+        loop {
+            let (id, is_leaf) = parse_child_id(child_id);
+            if is_leaf {
+                let segs = self
+                    .ssector(id)
+                    .and_then(|ssector| self.ssector_segs(ssector))?;
+                return segs.first().and_then(|seg| self.seg_sector(seg));
+            }
+            let node = self.nodes.get(id)?;
+            child_id = if partition_line(node).signed_distance(pos) > 0.0 {
+                node.left
+            } else {
+                node.right
+            };
+        }
+    }
+
     pub fn sector_id(&self, sector: &WadSector) -> SectorId {
         let sector_id = (sector as *const _ as usize - self.sectors.as_ptr() as usize)
             / mem::size_of::<WadSector>();
@@ -374,3 +412,156 @@ impl<'a> Iterator for AdjacentSectorsIter<'a> {
         None
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::Level;
+    use crate::types::{SectorId, SidedefId, WadCoord, WadLinedef, WadSector, WadSidedef};
+    use crate::types::{SpecialType, WadVertex};
+    use crate::util::from_wad_height;
+    use crate::visitor::LevelAnalysis;
+    use crate::{WadMetadata, WadName};
+    use std::collections::HashMap;
+    use std::str::FromStr;
+
+    fn name(name: &str) -> WadName {
+        WadName::from_str(name).expect("test: bad name")
+    }
+
+    fn sector(floor_height: WadCoord, floor_texture: &str, tag: u16) -> WadSector {
+        WadSector {
+            floor_height,
+            ceiling_height: 128,
+            floor_texture: name(floor_texture),
+            ceiling_texture: name("CEIL1_1"),
+            light: 160,
+            sector_type: 0,
+            tag,
+        }
+    }
+
+    /// Builds a level out of sectors and the two-sided linedefs between them, as special type,
+    /// tag and the sectors on their right and left.
+    fn level(sectors: Vec<WadSector>, lines: &[(SpecialType, u16, SectorId, SectorId)]) -> Level {
+        let mut sidedefs = Vec::new();
+        let mut side = |sector: SectorId| {
+            sidedefs.push(WadSidedef {
+                x_offset: 0,
+                y_offset: 0,
+                upper_texture: name("-"),
+                lower_texture: name("-"),
+                middle_texture: name("-"),
+                sector,
+            });
+            (sidedefs.len() - 1) as SidedefId
+        };
+        let linedefs = lines
+            .iter()
+            .map(|&(special_type, sector_tag, right, left)| WadLinedef {
+                start_vertex: 0,
+                end_vertex: 1,
+                flags: 0x0004,
+                special_type,
+                sector_tag,
+                right_side: side(right),
+                left_side: side(left),
+            })
+            .collect();
+        Level {
+            things: Vec::new(),
+            linedefs,
+            sidedefs,
+            vertices: vec![WadVertex { x: 0, y: 0 }, WadVertex { x: 64, y: 0 }],
+            segs: Vec::new(),
+            subsectors: Vec::new(),
+            nodes: Vec::new(),
+            sectors,
+            things_by_sector: HashMap::new(),
+        }
+    }
+
+    fn metadata() -> WadMetadata {
+        WadMetadata::from_text(
+            r#"
+            sky = []
+            switches = []
+            [animations]
+                flats = []
+                walls = []
+            [things]
+                decorations = []
+                weapons = []
+                powerups = []
+                artifacts = []
+                ammo = []
+                keys = []
+                monsters = []
+
+            [[linedef]]
+            special_type = 8
+            trigger = "WalkOver"
+            only_once = true
+            stairs = { step = 8, speed = 2 }
+
+            [[linedef]]
+            special_type = 9
+            trigger = "WalkOver"
+            only_once = true
+            donut = { speed = 4 }
+        "#,
+        )
+        .expect("test: could not parse test metadata")
+    }
+
+    #[test]
+    fn stairs_rise_by_a_step_each() {
+        let level = level(
+            vec![
+                sector(0, "FLOOR4_8", 0),
+                sector(0, "STEP1", 1),
+                sector(0, "STEP1", 0),
+                sector(16, "STEP1", 0),
+                sector(0, "FLOOR4_8", 0),
+            ],
+            &[(8, 1, 0, 1), (0, 0, 1, 2), (0, 0, 2, 3), (0, 0, 3, 4)],
+        );
+        let mut analysis = LevelAnalysis::new(&level, &metadata());
+        let triggers = analysis.take_triggers();
+        let offsets = triggers[0]
+            .move_effects
+            .iter()
+            .map(|effect| effect.first_height_offset)
+            .collect::<Vec<_>>();
+        // Steps end up at 8, 16 and 24, the last one starting out at 16.
+        assert_eq!(
+            offsets,
+            vec![from_wad_height(8), from_wad_height(16), from_wad_height(8)]
+        );
+    }
+
+    #[test]
+    fn donut_takes_the_outside_floor() {
+        let level = level(
+            vec![
+                sector(0, "FLOOR4_8", 0),
+                sector(64, "FLOOR4_8", 2),
+                sector(0, "FLOOR4_8", 0),
+                sector(32, "NUKAGE1", 0),
+            ],
+            &[(9, 2, 0, 3), (0, 0, 1, 2), (0, 0, 2, 3)],
+        );
+        let mut analysis = LevelAnalysis::new(&level, &metadata());
+        let triggers = analysis.take_triggers();
+        let offsets = triggers[0]
+            .move_effects
+            .iter()
+            .map(|effect| effect.first_height_offset)
+            .collect::<Vec<_>>();
+        // The ring rises from 0 and the pillar lowers from 64, both to the outside's 32.
+        assert_eq!(offsets, vec![from_wad_height(32), from_wad_height(-32)]);
+        let change = triggers[0].change_effects[0];
+        assert_eq!(change.sector_id, 2);
+        assert_eq!(change.texture, name("NUKAGE1"));
+        assert!(change.on_finish);
+    }
+}
@@ -4,8 +4,10 @@ mod level;
 mod light;
 mod meta;
 mod name;
+mod states;
 mod visitor;
 
+pub mod demo;
 pub mod tex;
 pub mod types;
 pub mod util;
@@ -15,11 +17,15 @@ pub use self::image::Image;
 pub use self::level::Level;
 pub use self::light::{LightEffect, LightEffectKind, LightInfo};
 pub use self::meta::{
-    ExitEffectDef, MoveEffectDef, SkyMetadata, ThingMetadata, TriggerType, WadMetadata,
+    BlockedBehaviour, ChangeEffectDef, ChangeModel, DonutEffectDef, ExitEffectDef, LightEffectDef,
+    MoveEffectDef, SkyMetadata, StairsEffectDef, ThingFlag, ThingMetadata, TriggerType,
+    WadMetadata,
 };
 pub use self::name::WadName;
+pub use self::states::{StateCursor, StateFrame, StateSequence, ThingStates, SPAWN_STATE};
 pub use self::tex::{OpaqueImage, TextureDirectory, TransparentImage};
 pub use self::visitor::{
-    Branch, Decor, LevelAnalysis, LevelVisitor, LevelWalker, Marker, MoveEffect, ObjectId, SkyPoly,
-    SkyQuad, StaticPoly, StaticQuad, SwitchEffect, TeleportEffect, Trigger,
+    Branch, ChangeEffect, Decor, LevelAnalysis, LevelVisitor, LevelWalker, LightChange, Marker,
+    MoveEffect, ObjectId, SkyPoly, SkyQuad, StaticPoly, StaticQuad, SwitchEffect, TeleportEffect,
+    Trigger,
 };
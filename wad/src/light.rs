@@ -1,14 +1,21 @@
 use super::level::Level;
-use super::types::{LightLevel, SectorType, WadSector};
-use std::f32::EPSILON;
+use super::meta::LightEffectDef;
+use super::types::{LightLevel, SectorId, SectorType, WadSector};
+use serde::{Deserialize, Serialize};
+use std::cmp;
 
-#[derive(PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct LightInfo {
     pub level: f32,
     pub effect: Option<LightEffect>,
+    /// Added to `level` for walls which are brightened or darkened depending on orientation.
+    pub contrast: f32,
+    /// Set for sectors whose light can be changed by triggers, to the first sector whose light
+    /// starts and changes the same way, so that they only share light entries with those.
+    pub sector: Option<SectorId>,
 }
 
-#[derive(PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct LightEffect {
     pub alt_level: f32,
     pub speed: f32,
@@ -17,7 +24,7 @@ pub struct LightEffect {
     pub kind: LightEffectKind,
 }
 
-#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum LightEffectKind {
     Glow,
     Random,
@@ -30,20 +37,14 @@ pub fn new_light(level: &Level, sector: &WadSector) -> LightInfo {
         FLASH | FAST_STROBE_1 | FAST_STROBE_2 | FAST_STROBE_SYNC | SLOW_STROBE
         | SLOW_STROBE_SYNC | GLOW | FLICKER => {
             let alt_level = light_to_f32(level.sector_min_light(sector));
-            if (alt_level - base_level).abs() < EPSILON {
-                return LightInfo {
-                    level: base_level,
-                    effect: None,
-                };
+            if (alt_level - base_level).abs() < f32::EPSILON {
+                return LightInfo::constant(base_level);
             } else {
                 alt_level
             }
         }
         _ => {
-            return LightInfo {
-                level: base_level,
-                effect: None,
-            };
+            return LightInfo::constant(base_level);
         }
     };
     let sync = match sector.sector_type {
@@ -75,6 +76,44 @@ pub fn new_light(level: &Level, sector: &WadSector) -> LightInfo {
             sync,
             kind,
         }),
+        contrast: 0.0,
+        sector: None,
+    }
+}
+
+/// Computes the light a sector switches to when a linedef with a light effect is triggered.
+pub fn changed_light(level: &Level, sector: &WadSector, effect_def: LightEffectDef) -> LightInfo {
+    match effect_def {
+        LightEffectDef::Brightest => LightInfo::constant(light_to_f32(
+            level
+                .adjacent_sectors(sector)
+                .map(|sector| sector.light)
+                .fold(0, cmp::max),
+        )),
+        LightEffectDef::Darkest => {
+            LightInfo::constant(light_to_f32(level.sector_min_light(sector)))
+        }
+        LightEffectDef::Level(light) => LightInfo::constant(light_to_f32(light)),
+        LightEffectDef::Strobe => {
+            let base_level = light_to_f32(sector.light);
+            let alt_level = light_to_f32(level.sector_min_light(sector));
+            LightInfo {
+                level: base_level,
+                effect: Some(LightEffect {
+                    alt_level: if (alt_level - base_level).abs() < f32::EPSILON {
+                        0.0
+                    } else {
+                        alt_level
+                    },
+                    speed: SLOW_STROBE_SPEED,
+                    duration: SLOW_STROBE_DURATION,
+                    sync: id_to_sync(level.sector_id(sector)),
+                    kind: LightEffectKind::Alternate,
+                }),
+                contrast: 0.0,
+                sector: None,
+            }
+        }
     }
 }
 
@@ -85,18 +124,20 @@ pub fn with_contrast(light_info: &LightInfo, contrast: Contrast) -> LightInfo {
         Contrast::Brighten => 2.0 / 31.0,
     };
     LightInfo {
-        level: clamp(light_info.level + contrast),
+        contrast,
         ..light_info.clone()
     }
 }
 
-fn clamp(level: f32) -> f32 {
-    if level > 1.0 {
-        1.0
-    } else if level < 0.0 {
-        0.0
-    } else {
-        level
+impl LightInfo {
+    /// A light which doesn't change and doesn't belong to a sector, e.g. for fullbright sprites.
+    pub fn constant(level: f32) -> Self {
+        LightInfo {
+            level,
+            effect: None,
+            contrast: 0.0,
+            sector: None,
+        }
     }
 }
 
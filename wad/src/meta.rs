@@ -1,10 +1,11 @@
 use super::name::WadName;
-use super::types::{SpecialType, ThingType, WadCoord};
+use super::states::{StateFrame, StateSequence, ThingStates, SPAWN_STATE};
+use super::types::{LightLevel, SpecialType, ThingType, WadCoord};
 use anyhow::{Context, Result};
 use indexmap::IndexMap;
 use log::{error, warn};
 use regex::Regex;
-use serde::{de::Error as SerdeDeError, Deserialize, Deserializer};
+use serde::{de::Error as SerdeDeError, Deserialize, Deserializer, Serialize};
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
@@ -28,14 +29,94 @@ pub struct AnimationMetadata {
     pub walls: Vec<Vec<WadName>>,
 }
 
+/// Behaviour flags of a thing type, a subset of the original's `MF_*` flags.
+#[derive(Debug, Deserialize, Copy, Clone, PartialEq, Eq)]
+pub enum ThingFlag {
+    /// Blocks movement.
+    Solid,
+    /// Can be damaged by weapons.
+    Shootable,
+    /// Drawn with the fuzz effect, like spectres.
+    Shadow,
+    /// Killing it counts towards the kill percentage.
+    CountKill,
+    /// Can move up and down freely, like cacodemons.
+    Float,
+    NoGravity,
+    /// Spawns hanging from the ceiling.
+    SpawnCeiling,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ThingMetadata {
     pub thing_type: ThingType,
     #[serde(deserialize_with = "deserialize_name_from_str")]
     pub sprite: WadName,
+    /// The frame letters of a looping spawn sequence, used when `states` doesn't have one.
     pub sequence: String,
     pub hanging: bool,
     pub radius: u32,
+    /// Whether the thing blocks movement.
+    #[serde(default = "Default::default", alias = "solid")]
+    pub obstacle: bool,
+
+    pub height: Option<WadCoord>,
+    pub health: Option<i32>,
+    /// How far the thing moves with each step, in units.
+    pub speed: Option<i32>,
+    /// Out of 256, the chance of a monster flinching when it's hurt.
+    #[serde(default = "Default::default")]
+    pub pain_chance: i32,
+
+    #[serde(default = "Default::default")]
+    pub flags: Vec<ThingFlag>,
+
+    #[serde(default = "Default::default")]
+    pub states: ThingStates,
+}
+
+impl ThingMetadata {
+    pub fn has_flag(&self, flag: ThingFlag) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    /// Whether the thing blocks movement, being an obstacle or flagged as solid.
+    pub fn is_solid(&self) -> bool {
+        self.obstacle || self.has_flag(ThingFlag::Solid)
+    }
+
+    /// The frame the thing spawns with.
+    pub fn spawn_frame(&self) -> Option<&StateFrame> {
+        self.states.get(SPAWN_STATE)?.frames.first()
+    }
+
+    /// Fills in a spawn sequence from `sequence` for things which don't have one in `states`.
+    fn add_sequence_state(&mut self) {
+        if self.states.get(SPAWN_STATE).is_some() {
+            return;
+        }
+        let frames = self
+            .sequence
+            .bytes()
+            .map(|frame| StateFrame {
+                frame,
+                tics: if self.sequence.len() > 1 {
+                    SEQUENCE_TICS
+                } else {
+                    -1
+                },
+                fullbright: false,
+                action: None,
+            })
+            .collect::<Vec<_>>();
+        let next = if frames.len() > 1 {
+            Some(SPAWN_STATE.to_owned())
+        } else {
+            None
+        };
+        self.states
+            .insert(SPAWN_STATE, StateSequence { frames, next });
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -84,6 +165,14 @@ pub struct HeightEffectDef {
     pub second: Option<HeightDef>,
 }
 
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlockedBehaviour {
+    #[default]
+    Stop,
+    Reverse,
+    Crush,
+}
+
 #[derive(Debug, Copy, Clone, Deserialize)]
 pub struct MoveEffectDef {
     pub floor: Option<HeightEffectDef>,
@@ -100,6 +189,45 @@ pub struct MoveEffectDef {
         deserialize_with = "deserialize_move_speed"
     )]
     pub speed: f32,
+
+    #[serde(default = "Default::default")]
+    pub blocked: BlockedBehaviour,
+}
+
+#[derive(Debug, Copy, Clone, Deserialize)]
+pub struct StairsEffectDef {
+    pub step: WadCoord,
+
+    #[serde(deserialize_with = "deserialize_move_speed")]
+    pub speed: f32,
+}
+
+#[derive(Debug, Copy, Clone, Deserialize)]
+pub struct DonutEffectDef {
+    #[serde(deserialize_with = "deserialize_move_speed")]
+    pub speed: f32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+pub enum ChangeModel {
+    Trigger,
+    Numeric,
+}
+
+#[derive(Debug, Copy, Clone, Deserialize)]
+pub struct ChangeEffectDef {
+    pub model: ChangeModel,
+
+    #[serde(default = "Default::default")]
+    pub copy_type: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+pub enum LightEffectDef {
+    Brightest,
+    Darkest,
+    Level(LightLevel),
+    Strobe,
 }
 
 #[derive(Debug, Deserialize, Copy, Clone)]
@@ -128,6 +256,18 @@ pub struct LinedefMetadata {
     #[serde(rename = "move")]
     pub move_effect: Option<MoveEffectDef>,
 
+    #[serde(rename = "stairs")]
+    pub stairs_effect: Option<StairsEffectDef>,
+
+    #[serde(rename = "donut")]
+    pub donut_effect: Option<DonutEffectDef>,
+
+    #[serde(rename = "change")]
+    pub change_effect: Option<ChangeEffectDef>,
+
+    #[serde(rename = "light")]
+    pub light_effect: Option<LightEffectDef>,
+
     #[serde(rename = "exit")]
     pub exit_effect: Option<ExitEffectDef>,
 
@@ -169,7 +309,21 @@ impl WadMetadata {
     }
 
     pub fn from_text(text: &str) -> Result<WadMetadata> {
-        toml::from_str(text).context("Corrupt metadata file")
+        let mut metadata: WadMetadata = toml::from_str(text).context("Corrupt metadata file")?;
+        let things = &mut metadata.things;
+        for thing in things
+            .decorations
+            .iter_mut()
+            .chain(&mut things.weapons)
+            .chain(&mut things.powerups)
+            .chain(&mut things.artifacts)
+            .chain(&mut things.ammo)
+            .chain(&mut things.keys)
+            .chain(&mut things.monsters)
+        {
+            thing.add_sequence_state();
+        }
+        Ok(metadata)
     }
 
     pub fn sky_for(&self, name: WadName) -> Option<&SkyMetadata> {
@@ -222,8 +376,50 @@ impl WadMetadata {
                     .find(|t| t.thing_type == thing_type)
             })
     }
+
+    /// Whether picking up a thing counts towards the item percentage.
+    pub fn is_counted_item(&self, thing_type: ThingType) -> bool {
+        self.things
+            .artifacts
+            .iter()
+            .any(|t| t.thing_type == thing_type)
+    }
+
+    pub fn is_monster(&self, thing_type: ThingType) -> bool {
+        self.things
+            .monsters
+            .iter()
+            .any(|t| t.thing_type == thing_type)
+    }
+
+    /// Whether a thing can be damaged by weapons: monsters and things flagged as shootable, like
+    /// exploding barrels.
+    pub fn is_shootable(&self, thing_type: ThingType) -> bool {
+        self.is_monster(thing_type)
+            || self
+                .find_thing(thing_type)
+                .is_some_and(|thing| thing.has_flag(ThingFlag::Shootable))
+    }
+
+    /// Whether a thing can be picked up by the player: weapons, powerups, artifacts, ammo and keys.
+    pub fn is_pickup(&self, thing_type: ThingType) -> bool {
+        let things = &self.things;
+        [
+            &things.weapons,
+            &things.powerups,
+            &things.artifacts,
+            &things.ammo,
+            &things.keys,
+        ]
+        .iter()
+        .any(|category| category.iter().any(|t| t.thing_type == thing_type))
+    }
 }
 
+/// Frame duration of spawn sequences made from `sequence`, most pickups use 6 tics in the
+/// original.
+const SEQUENCE_TICS: i32 = 6;
+
 fn deserialize_regex_from_str<'de, D>(deserializer: D) -> StdResult<Regex, D::Error>
 where
     D: Deserializer<'de>,
@@ -279,7 +475,7 @@ where
 
 #[cfg(test)]
 mod test {
-    use super::WadMetadata;
+    use super::{LightEffectDef, WadMetadata};
 
     #[test]
     fn test_wad_metadata() {
@@ -382,4 +578,164 @@ mod test {
         )
         .expect("test: could not parse test metadata");
     }
+
+    #[test]
+    fn test_thing_states() {
+        let metadata = WadMetadata::from_text(
+            r#"
+            sky = []
+            switches = []
+            [animations]
+                flats = []
+                walls = []
+            [things]
+                weapons = []
+                powerups = []
+                ammo = []
+                keys = []
+                [[things.decorations]]
+                    # Tall techno pillar
+                    thing_type = 48
+                    radius = 16
+                    sprite = "ELEC"
+                    sequence = "A"
+                    solid = true
+                    hanging = false
+                [[things.artifacts]]
+                    # Computer map
+                    thing_type = 2026
+                    radius = 20
+                    sprite = "PMAP"
+                    sequence = "ABCDCB"
+                    hanging = false
+                [[things.monsters]]
+                    # Imp
+                    thing_type = 3001
+                    radius = 20
+                    height = 56
+                    health = 60
+                    speed = 8
+                    pain_chance = 200
+                    flags = ["Solid", "Shootable", "CountKill"]
+                    sprite = "TROO"
+                    sequence = "A"
+                    hanging = false
+                    [things.monsters.states]
+                        spawn = { frames = ["A 10 Look", "B 10 Look"], next = "spawn" }
+                        missile = { frames = ["E 8 FaceTarget", "F 8 FaceTarget", "G 6 bright TroopAttack"], next = "see" }
+                        death = { frames = ["I 8", "J 8 Scream", "K 6", "L 6 Fall", "M -1"] }
+        "#,
+        )
+        .expect("test: could not parse test metadata");
+
+        let map = metadata.find_thing(2026).expect("test: no computer map");
+        assert!(!map.is_solid());
+        let pillar = metadata.find_thing(48).expect("test: no pillar");
+        assert!(pillar.is_solid());
+        let spawn = map.states.get("spawn").expect("test: no sequence state");
+        assert_eq!(spawn.frames.len(), 6);
+        assert_eq!(spawn.next.as_deref(), Some("spawn"));
+
+        let imp = metadata.find_thing(3001).expect("test: no imp");
+        assert_eq!(imp.height, Some(56));
+        assert_eq!(imp.health, Some(60));
+        assert_eq!(imp.speed, Some(8));
+        assert_eq!(imp.pain_chance, 200);
+        assert_eq!(imp.spawn_frame().map(|frame| frame.tics), Some(10));
+        let attack = &imp.states.get("missile").expect("test: no missile").frames[2];
+        assert!(attack.fullbright);
+        assert_eq!(attack.action.as_deref(), Some("TroopAttack"));
+        let death = imp.states.get("death").expect("test: no death");
+        assert_eq!(death.frames.last().map(|frame| frame.tics), Some(-1));
+        assert_eq!(death.next, None);
+        assert!(metadata.is_shootable(3001));
+        assert!(!metadata.is_shootable(2026));
+    }
+
+    #[test]
+    fn test_stairs_and_donut() {
+        let metadata = WadMetadata::from_text(
+            r#"
+            sky = []
+            switches = []
+            [animations]
+                flats = []
+                walls = []
+            [things]
+                decorations = []
+                weapons = []
+                powerups = []
+                artifacts = []
+                ammo = []
+                keys = []
+                monsters = []
+
+            [[linedef]]
+            special_type = 7
+            trigger = "Switch"
+            only_once = true
+            stairs = { step = 8, speed = 2 }
+
+            [[linedef]]
+            special_type = 9
+            trigger = "Switch"
+            only_once = true
+            donut = { speed = 4 }
+        "#,
+        )
+        .expect("test: could not parse test metadata");
+
+        let stairs = metadata.linedef[&7]
+            .stairs_effect
+            .expect("test: no stairs effect");
+        assert_eq!(stairs.step, 8);
+        assert!(metadata.linedef[&7].donut_effect.is_none());
+        let donut = metadata.linedef[&9]
+            .donut_effect
+            .expect("test: no donut effect");
+        assert!(donut.speed > stairs.speed);
+    }
+
+    #[test]
+    fn test_light_effects() {
+        let metadata = WadMetadata::from_text(
+            r#"
+            sky = []
+            switches = []
+            [animations]
+                flats = []
+                walls = []
+            [things]
+                decorations = []
+                weapons = []
+                powerups = []
+                artifacts = []
+                ammo = []
+                keys = []
+                monsters = []
+
+            [[linedef]]
+            special_type = 13
+            trigger = "WalkOver"
+            only_once = true
+            light = { Level = 255 }
+
+            [[linedef]]
+            special_type = 104
+            trigger = "WalkOver"
+            only_once = true
+            light = "Darkest"
+        "#,
+        )
+        .expect("test: could not parse test metadata");
+
+        assert_eq!(
+            metadata.linedef[&13].light_effect,
+            Some(LightEffectDef::Level(255))
+        );
+        assert_eq!(
+            metadata.linedef[&104].light_effect,
+            Some(LightEffectDef::Darkest)
+        );
+    }
 }
@@ -1,5 +1,6 @@
 use anyhow::{bail, Result};
 use serde::de::{Deserialize, Deserializer, Error as SerdeDeError};
+use serde::ser::{Serialize, Serializer};
 use std::borrow::Borrow;
 use std::fmt;
 use std::fmt::Debug;
@@ -129,6 +130,15 @@ impl<'de> Deserialize<'de> for WadName {
     }
 }
 
+impl Serialize for WadName {
+    fn serialize<S>(&self, serializer: S) -> StdResult<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
 pub trait IntoWadName {
     fn into_wad_name(self) -> Result<WadName>;
 }
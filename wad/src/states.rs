@@ -0,0 +1,255 @@
+use anyhow::{anyhow, bail, Context, Error, Result};
+use indexmap::IndexMap;
+use serde::{de::Error as SerdeDeError, Deserialize, Deserializer};
+use std::result::Result as StdResult;
+use std::str::FromStr;
+
+/// The state sequence things start in.
+pub const SPAWN_STATE: &str = "spawn";
+
+/// A frame of a state sequence, like the original's `state_t`.
+///
+/// In metadata, frames are written as `"<frame> <tics> [bright] [action]"`, e.g. `"F 8 bright
+/// PosAttack"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateFrame {
+    /// The sprite frame letter.
+    pub frame: u8,
+    /// How long the frame lasts for, negative for forever.
+    pub tics: i32,
+    pub fullbright: bool,
+    /// The original's action function run on entering the frame, without the `A_` prefix.
+    pub action: Option<String>,
+}
+
+impl StateFrame {
+    /// How long the frame lasts for in seconds, infinite for frames which last forever.
+    pub fn duration(&self) -> f32 {
+        if self.tics < 0 {
+            f32::INFINITY
+        } else {
+            self.tics as f32 / TICS_PER_SECOND
+        }
+    }
+}
+
+impl FromStr for StateFrame {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let mut parts = text.split_whitespace();
+        let frame = match parts.next().map(str::as_bytes) {
+            Some(&[frame]) if frame.is_ascii_uppercase() || frame == b'[' || frame == b'\\' => {
+                frame
+            }
+            _ => bail!("Invalid frame letter in state frame {:?}.", text),
+        };
+        let tics = parts
+            .next()
+            .ok_or_else(|| anyhow!("Missing tics in state frame {:?}.", text))?
+            .parse()
+            .with_context(|| format!("Invalid tics in state frame {:?}.", text))?;
+        let mut next = parts.next();
+        let fullbright = next == Some("bright");
+        if fullbright {
+            next = parts.next();
+        }
+        let action = next.map(str::to_owned);
+        if parts.next().is_some() {
+            bail!("Trailing text in state frame {:?}.", text);
+        }
+        Ok(StateFrame {
+            frame,
+            tics,
+            fullbright,
+            action,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for StateFrame {
+    fn deserialize<D>(deserializer: D) -> StdResult<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+/// A labelled run of frames, like the original's `S_POSS_RUN1` to `S_POSS_RUN8`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct StateSequence {
+    pub frames: Vec<StateFrame>,
+    /// The sequence to go to after the last frame. Without one, the last frame lasts forever.
+    #[serde(default)]
+    pub next: Option<String>,
+}
+
+/// The state sequences of a thing type, by label (`spawn`, `see`, `melee`, `missile`, `pain`,
+/// `death`, ...).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct ThingStates(IndexMap<String, StateSequence>);
+
+impl ThingStates {
+    pub fn get(&self, label: &str) -> Option<&StateSequence> {
+        self.0.get(label)
+    }
+
+    pub fn insert(&mut self, label: &str, sequence: StateSequence) {
+        self.0.insert(label.to_owned(), sequence);
+    }
+
+    /// All the frame letters used by any sequence, to know which sprites need loading.
+    pub fn frames(&self) -> impl Iterator<Item = u8> + '_ {
+        self.0
+            .values()
+            .flat_map(|sequence| sequence.frames.iter().map(|frame| frame.frame))
+    }
+}
+
+/// Where a thing is in its state table, advanced in tics.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateCursor {
+    label: String,
+    index: usize,
+    tics_left: f32,
+}
+
+impl StateCursor {
+    /// Starts at the first frame of `label`, returning `None` if there is no such sequence.
+    pub fn new(states: &ThingStates, label: &str) -> Option<Self> {
+        let first = states.get(label)?.frames.first()?;
+        Some(StateCursor {
+            label: label.to_owned(),
+            index: 0,
+            tics_left: tics_left(first),
+        })
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn frame<'a>(&self, states: &'a ThingStates) -> &'a StateFrame {
+        &states
+            .get(&self.label)
+            .expect("state cursor label missing from its states")
+            .frames[self.index]
+    }
+
+    /// Advances the cursor by `tics`, returning whether it moved on from its frame.
+    pub fn advance(&mut self, states: &ThingStates, tics: f32) -> bool {
+        let mut moved = false;
+        self.tics_left -= tics;
+        for _ in 0..MAX_FRAMES_PER_ADVANCE {
+            if self.tics_left > 0.0 {
+                break;
+            }
+            let sequence = states
+                .get(&self.label)
+                .expect("state cursor label missing from its states");
+            if self.index + 1 < sequence.frames.len() {
+                self.index += 1;
+            } else if let Some(first) = sequence
+                .next
+                .as_deref()
+                .and_then(|next| Some((next, states.get(next)?.frames.first()?)))
+            {
+                self.label = first.0.to_owned();
+                self.index = 0;
+            } else {
+                self.tics_left = f32::INFINITY;
+                break;
+            }
+            moved = true;
+            self.tics_left += tics_left(self.frame(states));
+        }
+        moved
+    }
+}
+
+fn tics_left(frame: &StateFrame) -> f32 {
+    if frame.tics < 0 {
+        f32::INFINITY
+    } else {
+        frame.tics as f32
+    }
+}
+
+const TICS_PER_SECOND: f32 = 35.0;
+
+/// Bounds the frames skipped in one advance, in case of a loop of zero tic frames.
+const MAX_FRAMES_PER_ADVANCE: usize = 64;
+
+#[cfg(test)]
+mod test {
+    use super::{StateCursor, StateFrame, StateSequence, ThingStates};
+
+    fn frame(text: &str) -> StateFrame {
+        text.parse().expect("test: invalid frame")
+    }
+
+    #[test]
+    fn parses_frames() {
+        assert_eq!(
+            frame("F 8 bright PosAttack"),
+            StateFrame {
+                frame: b'F',
+                tics: 8,
+                fullbright: true,
+                action: Some("PosAttack".to_owned()),
+            }
+        );
+        assert_eq!(
+            frame("L -1"),
+            StateFrame {
+                frame: b'L',
+                tics: -1,
+                fullbright: false,
+                action: None,
+            }
+        );
+        assert!("AB 10".parse::<StateFrame>().is_err());
+        assert!("A".parse::<StateFrame>().is_err());
+        assert!("A 10 Look Chase".parse::<StateFrame>().is_err());
+    }
+
+    #[test]
+    fn cursor_follows_next_and_stops() {
+        let mut states = ThingStates::default();
+        states.insert(
+            "spawn",
+            StateSequence {
+                frames: vec![frame("A 10"), frame("B 10")],
+                next: Some("spawn".to_owned()),
+            },
+        );
+        states.insert(
+            "death",
+            StateSequence {
+                frames: vec![frame("C 5"), frame("D -1")],
+                next: None,
+            },
+        );
+
+        let mut cursor = StateCursor::new(&states, "spawn").expect("test: no spawn");
+        assert!(!cursor.advance(&states, 9.0));
+        assert!(cursor.advance(&states, 1.0));
+        assert_eq!(cursor.frame(&states).frame, b'B');
+        assert!(cursor.advance(&states, 10.0));
+        assert_eq!(cursor.frame(&states).frame, b'A');
+
+        let mut cursor = StateCursor::new(&states, "death").expect("test: no death");
+        assert!(cursor.advance(&states, 100.0));
+        assert_eq!(cursor.frame(&states).frame, b'D');
+        assert!(!cursor.advance(&states, 1000.0));
+        assert!(StateCursor::new(&states, "pain").is_none());
+    }
+}
@@ -39,6 +39,23 @@ pub struct WadThing {
     pub flags: ThingFlags,
 }
 
+impl WadThing {
+    pub fn multiplayer_only(&self) -> bool {
+        self.flags & 0x0010 != 0
+    }
+
+    /// Whether the thing appears in a single player game at `skill`, from 0 (I'm too young to
+    /// die) to 4 (Nightmare!).
+    pub fn spawns_at(&self, skill: u8) -> bool {
+        let mask = match skill {
+            0 | 1 => 0x0001,
+            2 => 0x0002,
+            _ => 0x0004,
+        };
+        !self.multiplayer_only() && self.flags & mask != 0
+    }
+}
+
 #[derive(Copy, Clone, Deserialize)]
 pub struct WadVertex {
     pub x: WadCoord,
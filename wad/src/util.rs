@@ -1,5 +1,5 @@
-use super::types::{ChildId, WadCoord, WadName};
-use math::Pnt2f;
+use super::types::{ChildId, SectorType, WadCoord, WadName, WadNode};
+use math::{Line2f, Pnt2f};
 
 pub fn is_untextured(name: WadName) -> bool {
     name[0] == b'-' && name[1] == b'\0'
@@ -9,6 +9,10 @@ pub fn is_sky_flat(name: WadName) -> bool {
     &name == b"F_SKY1\0\0"
 }
 
+pub fn is_secret_sector(sector_type: SectorType) -> bool {
+    sector_type == 9
+}
+
 pub fn from_wad_height(x: WadCoord) -> f32 {
     f32::from(x) / 100.0
 }
@@ -24,3 +28,10 @@ pub fn from_wad_coords(x: WadCoord, y: WadCoord) -> Pnt2f {
 pub fn parse_child_id(id: ChildId) -> (usize, bool) {
     ((id & 0x7fff) as usize, id & 0x8000 != 0)
 }
+
+pub fn partition_line(node: &WadNode) -> Line2f {
+    Line2f::from_two_points(
+        from_wad_coords(node.line_x, node.line_y),
+        from_wad_coords(node.line_x + node.step_x, node.line_y + node.step_y),
+    )
+}
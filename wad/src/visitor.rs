@@ -3,24 +3,27 @@ use crate::types::SidedefId;
 use super::level::{Level, NeighbourHeights};
 use super::light::{self, Contrast, LightInfo};
 use super::meta::{
-    ExitEffectDef, HeightDef, HeightEffectDef, HeightRef, LinedefMetadata, MoveEffectDef,
-    TriggerType, WadMetadata,
+    BlockedBehaviour, ChangeEffectDef, ChangeModel, DonutEffectDef, ExitEffectDef, HeightDef,
+    HeightEffectDef, HeightRef, LightEffectDef, LinedefMetadata, MoveEffectDef, StairsEffectDef,
+    ThingMetadata, TriggerType, WadMetadata,
 };
 use super::tex::TextureDirectory;
 use super::types::{
-    ChildId, SectorId, SpecialType, ThingType, WadCoord, WadLinedef, WadName, WadNode, WadSector,
-    WadSeg, WadThing,
+    ChildId, SectorId, SectorType, SpecialType, ThingType, WadCoord, WadLinedef, WadName, WadNode,
+    WadSector, WadSeg, WadThing,
 };
 use super::util::{
-    from_wad_coords, from_wad_height, is_sky_flat, is_untextured, parse_child_id, to_wad_height,
+    from_wad_coords, from_wad_height, is_secret_sector, is_sky_flat, is_untextured, parse_child_id,
+    partition_line, to_wad_height,
 };
 use indexmap::IndexMap;
 use log::{debug, error, info, warn};
 use math::prelude::*;
 use math::{Deg, Line2f, Pnt2f, Pnt3f, Radf, Vec2f};
+use serde::{Deserialize, Serialize};
 use std::cmp;
 use std::cmp::Ordering;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::f32::EPSILON;
 use std::mem;
 use vec_map::VecMap;
@@ -44,6 +47,7 @@ pub struct StaticPoly<'a> {
     pub height: f32,
     pub light_info: &'a LightInfo,
     pub tex_name: WadName,
+    pub alt_tex_name: Option<WadName>,
 }
 
 pub struct SkyQuad {
@@ -65,6 +69,19 @@ pub struct Decor<'a> {
     pub half_width: f32,
     pub light_info: &'a LightInfo,
     pub tex_name: WadName,
+    pub thing_type: ThingType,
+    /// The radius of the thing, used to pick it up.
+    pub radius: f32,
+    /// Whether the player can pick up the thing, meaning it must be removable on its own.
+    pub pickup: bool,
+    /// Whether weapons can damage the thing, meaning it must be removable on its own too.
+    pub shootable: bool,
+    /// Whether the spawn frame is drawn at full brightness, regardless of the sector's light.
+    pub fullbright: bool,
+    /// Whether the thing blocks movement.
+    pub solid: bool,
+    /// The height of the thing from its metadata, if it has one, in world units.
+    pub height: Option<f32>,
 }
 
 pub trait LevelVisitor: Sized {
@@ -144,7 +161,7 @@ pub enum Marker {
     TeleportEnd,
 }
 
-#[derive(Eq, PartialEq, Debug, Copy, Clone, Default)]
+#[derive(Eq, PartialEq, Debug, Copy, Clone, Default, Serialize, Deserialize)]
 pub struct ObjectId(pub u32);
 
 struct SectorInfo {
@@ -152,6 +169,7 @@ struct SectorInfo {
     ceiling_id: ObjectId,
     floor_range: (WadCoord, WadCoord),
     ceiling_range: (WadCoord, WadCoord),
+    floor_alt_texture: Option<WadName>,
 }
 
 impl SectorInfo {
@@ -167,6 +185,7 @@ struct DynamicSectorInfo {
     neighbour_heights: Option<NeighbourHeights>,
     floor_range: Option<(WadCoord, WadCoord)>,
     ceiling_range: Option<(WadCoord, WadCoord)>,
+    floor_alt_texture: Option<WadName>,
 }
 
 impl DynamicSectorInfo {
@@ -175,13 +194,10 @@ impl DynamicSectorInfo {
         next_dynamic_object_id: &mut ObjectId,
         level: &Level,
         sector_id: SectorId,
-        trigger: &mut Trigger,
+        effect_def: MoveEffectDef,
+        move_effects: &mut Vec<MoveEffect>,
     ) {
         let sector = &level.sectors[sector_id as usize];
-        let effect_def = match trigger.move_effect_def {
-            Some(effect_def) => effect_def,
-            None => return,
-        };
 
         let heights = if let Some(heights) = self.neighbour_heights {
             heights
@@ -196,11 +212,55 @@ impl DynamicSectorInfo {
             return;
         };
 
-        let (first_floor, second_floor) =
-            HeightEffectDef::option_to_heights(effect_def.floor, sector, &heights);
-        let (first_ceiling, second_ceiling) =
-            HeightEffectDef::option_to_heights(effect_def.ceiling, sector, &heights);
-        let repeat = effect_def.repeat;
+        let floor = HeightEffectDef::option_to_heights(effect_def.floor, sector, &heights);
+        let ceiling = HeightEffectDef::option_to_heights(effect_def.ceiling, sector, &heights);
+        self.add_moves(
+            next_dynamic_object_id,
+            sector,
+            floor,
+            ceiling,
+            &effect_def,
+            move_effects,
+        );
+    }
+
+    /// Makes the floor of `sector` switchable to `texture` at runtime, returning its object.
+    fn change_floor_texture(
+        &mut self,
+        next_dynamic_object_id: &mut ObjectId,
+        sector: &WadSector,
+        texture: WadName,
+    ) -> ObjectId {
+        if self.floor_range.is_none() {
+            self.floor_range = Some((sector.floor_height, sector.floor_height));
+        }
+        if self.floor_id == ObjectId(0) {
+            self.floor_id = *next_dynamic_object_id;
+            next_dynamic_object_id.0 += 1;
+        }
+        match self.floor_alt_texture {
+            Some(alt_texture) if alt_texture != texture => warn!(
+                "Floor {:?} already changes to {}, ignoring change to {}.",
+                self.floor_id, alt_texture, texture
+            ),
+            _ => self.floor_alt_texture = Some(texture),
+        }
+        self.floor_id
+    }
+
+    /// Adds effects moving the floor and/or ceiling of `sector` to the given absolute heights.
+    fn add_moves(
+        &mut self,
+        next_dynamic_object_id: &mut ObjectId,
+        sector: &WadSector,
+        (first_floor, second_floor): (Option<WadCoord>, Option<WadCoord>),
+        (first_ceiling, second_ceiling): (Option<WadCoord>, Option<WadCoord>),
+        effect_def: &MoveEffectDef,
+        move_effects: &mut Vec<MoveEffect>,
+    ) {
+        let MoveEffectDef {
+            repeat, blocked, ..
+        } = *effect_def;
 
         merge_range(
             &mut self.floor_range,
@@ -224,7 +284,7 @@ impl DynamicSectorInfo {
 
         if let Some(first_floor) = first_floor {
             let offset = from_wad_height(first_floor - sector.floor_height);
-            trigger.move_effects.push(MoveEffect {
+            move_effects.push(MoveEffect {
                 object_id: self.floor_id,
                 wait: effect_def.wait,
                 speed: effect_def.speed,
@@ -232,11 +292,12 @@ impl DynamicSectorInfo {
                 second_height_offset: second_floor
                     .map(|floor| from_wad_height(floor - sector.floor_height)),
                 repeat,
+                blocked,
             });
         }
 
         if let Some(first_ceiling) = first_ceiling {
-            trigger.move_effects.push(MoveEffect {
+            move_effects.push(MoveEffect {
                 object_id: self.ceiling_id,
                 wait: effect_def.wait,
                 speed: effect_def.speed,
@@ -244,11 +305,19 @@ impl DynamicSectorInfo {
                 second_height_offset: second_ceiling
                     .map(|ceiling| from_wad_height(ceiling - sector.ceiling_height)),
                 repeat,
+                blocked,
             });
         }
     }
 }
 
+/// Sector type of doors which close 30 seconds after the level starts.
+const DOOR_CLOSE_IN_30: SectorType = 10;
+/// Sector type of doors which open 5 minutes after the level starts.
+const DOOR_RAISE_IN_5_MINS: SectorType = 14;
+/// Speed of the timed doors above, matching a `speed = 8` door in the metadata.
+const TIMED_DOOR_SPEED: f32 = 0.7;
+
 fn merge_range<I: IntoIterator<Item = WadCoord>>(
     range: &mut Option<(WadCoord, WadCoord)>,
     current: WadCoord,
@@ -265,7 +334,7 @@ fn merge_range<I: IntoIterator<Item = WadCoord>>(
         .map(|(min, max)| (min.min(current), max.max(current)));
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct MoveEffect {
     pub object_id: ObjectId,
     pub first_height_offset: f32,
@@ -273,6 +342,27 @@ pub struct MoveEffect {
     pub speed: f32,
     pub wait: f32,
     pub repeat: bool,
+    pub blocked: BlockedBehaviour,
+}
+
+/// Changes the floor texture and sector type of a sector, with the alternative texture being
+/// baked into the floor of `object_id`.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct ChangeEffect {
+    pub sector_id: SectorId,
+    pub object_id: ObjectId,
+    pub texture: WadName,
+    pub sector_type: SectorType,
+    /// Whether the change waits for the move effect on `object_id` to finish.
+    pub on_finish: bool,
+}
+
+/// Replaces the light of a sector, including the brightened and darkened variants of its walls.
+#[derive(Debug, Clone)]
+pub struct LightChange {
+    /// The sector the changed lights belong to, see `LightInfo::sector`.
+    pub sector_id: SectorId,
+    pub light_info: LightInfo,
 }
 
 #[derive(Debug, Clone)]
@@ -288,6 +378,12 @@ pub struct SwitchEffect {
     pub texture_off: WadName,
 }
 
+impl From<HeightRef> for HeightDef {
+    fn from(to: HeightRef) -> Self {
+        HeightDef { to, offset: 0 }
+    }
+}
+
 impl HeightDef {
     fn to_height(self, sector: &WadSector, heights: &NeighbourHeights) -> Option<WadCoord> {
         let base = match self.to {
@@ -327,8 +423,14 @@ pub struct Trigger {
 
     pub unimplemented: bool,
     pub move_effect_def: Option<MoveEffectDef>,
+    pub stairs_effect_def: Option<StairsEffectDef>,
+    pub donut_effect_def: Option<DonutEffectDef>,
+    pub change_effect_def: Option<ChangeEffectDef>,
+    pub light_effect_def: Option<LightEffectDef>,
     pub exit_effect: Option<ExitEffectDef>,
     pub move_effects: Vec<MoveEffect>,
+    pub change_effects: Vec<ChangeEffect>,
+    pub light_changes: Vec<LightChange>,
     pub teleport_effect: Option<TeleportEffect>,
     pub switch_effect: Option<SwitchEffect>,
 }
@@ -336,7 +438,13 @@ pub struct Trigger {
 pub struct LevelAnalysis {
     dynamic_info: IndexMap<SectorId, DynamicSectorInfo>,
     triggers: Vec<Trigger>,
+    initial_effects: Vec<MoveEffect>,
+    /// Maps the sectors whose light can be changed to the first sector sharing their lights.
+    light_groups: HashMap<SectorId, SectorId>,
     num_objects: usize,
+    num_monsters: usize,
+    num_items: usize,
+    num_secrets: usize,
 }
 
 impl LevelAnalysis {
@@ -344,9 +452,16 @@ impl LevelAnalysis {
         let mut this = Self {
             dynamic_info: IndexMap::new(),
             triggers: Vec::new(),
+            initial_effects: Vec::new(),
+            light_groups: HashMap::new(),
             num_objects: 0,
+            num_monsters: 0,
+            num_items: 0,
+            num_secrets: 0,
         };
         this.compute_dynamic_sectors(level, meta);
+        this.group_dynamic_lights(level);
+        this.count_totals(level, meta);
         this
     }
 
@@ -354,10 +469,96 @@ impl LevelAnalysis {
         self.num_objects
     }
 
+    /// The number of monsters in the level, used for the kill percentage.
+    pub fn num_monsters(&self) -> usize {
+        self.num_monsters
+    }
+
+    /// The number of items in the level which count towards the item percentage.
+    pub fn num_items(&self) -> usize {
+        self.num_items
+    }
+
+    pub fn num_secrets(&self) -> usize {
+        self.num_secrets
+    }
+
     pub fn take_triggers(&mut self) -> Vec<Trigger> {
         mem::take(&mut self.triggers)
     }
 
+    /// Effects which start as soon as the level is loaded, like the doors in sectors which close
+    /// after 30 seconds.
+    pub fn take_initial_effects(&mut self) -> Vec<MoveEffect> {
+        mem::take(&mut self.initial_effects)
+    }
+
+    /// Lets sectors whose lights start out the same and are changed the same way by the same
+    /// triggers share their light entries, since there's only room for a limited number of them.
+    fn group_dynamic_lights(&mut self, level: &Level) {
+        let mut changes: IndexMap<SectorId, Vec<(usize, &LightInfo)>> = IndexMap::new();
+        for (i_trigger, trigger) in self.triggers.iter().enumerate() {
+            for change in &trigger.light_changes {
+                changes
+                    .entry(change.sector_id)
+                    .or_default()
+                    .push((i_trigger, &change.light_info));
+            }
+        }
+
+        // The first sector of each group, with the light and changes all of its sectors have.
+        let mut groups: Vec<(SectorId, LightInfo, &Vec<_>)> = Vec::new();
+        for (&sector_id, sector_changes) in &changes {
+            let initial = light::new_light(level, &level.sectors[sector_id as usize]);
+            let group = groups
+                .iter()
+                .find(|(_, group_initial, group_changes)| {
+                    *group_initial == initial && *group_changes == sector_changes
+                })
+                .map(|&(group, _, _)| group);
+            let group = group.unwrap_or_else(|| {
+                groups.push((sector_id, initial, sector_changes));
+                sector_id
+            });
+            self.light_groups.insert(sector_id, group);
+        }
+        info!(
+            "{} sectors with changing lights, in {} groups.",
+            changes.len(),
+            groups.len()
+        );
+
+        let light_groups = &self.light_groups;
+        for trigger in &mut self.triggers {
+            let mut changed_groups = HashSet::new();
+            trigger.light_changes.retain_mut(|change| {
+                change.sector_id = light_groups[&change.sector_id];
+                changed_groups.insert(change.sector_id)
+            });
+        }
+    }
+
+    fn count_totals(&mut self, level: &Level, meta: &WadMetadata) {
+        let things = &meta.things;
+        for thing in level.things.iter().filter(|thing| thing.spawns_at(SKILL)) {
+            let is_type = |thing_meta: &ThingMetadata| thing_meta.thing_type == thing.thing_type;
+            if things.monsters.iter().any(is_type) {
+                self.num_monsters += 1;
+            } else if meta.is_counted_item(thing.thing_type) {
+                self.num_items += 1;
+            }
+        }
+        self.num_secrets = level
+            .sectors
+            .iter()
+            .filter(|sector| is_secret_sector(sector.sector_type))
+            .count();
+        info!(
+            "Level totals: {} monsters, {} items, {} secrets.",
+            self.num_monsters, self.num_items, self.num_secrets
+        );
+    }
+
     fn compute_dynamic_sectors(&mut self, level: &Level, meta: &WadMetadata) {
         info!("Computing dynamic sectors...");
         let mut num_dynamic_linedefs = 0;
@@ -377,11 +578,9 @@ impl LevelAnalysis {
             .collect::<Vec<_>>();
         sector_tags_and_ids.sort_unstable();
 
-        let max_tag = if let Some(&(max_tag, _)) = sector_tags_and_ids.last() {
-            max_tag
-        } else {
-            return;
-        };
+        let max_tag = sector_tags_and_ids
+            .last()
+            .map_or(0, |&(max_tag, _)| max_tag);
         let mut tag_to_first_index = VecMap::with_capacity(max_tag as usize);
         let mut last_tag = !0usize;
         for (i_sector_tag_and_id, &(tag, _)) in sector_tags_and_ids.iter().enumerate() {
@@ -403,7 +602,9 @@ impl LevelAnalysis {
 
             let tag = linedef.sector_tag;
             if tag == 0 {
-                if let Some(sidedef) = level.left_sidedef(linedef) {
+                if let (Some(sidedef), Some(effect_def)) =
+                    (level.left_sidedef(linedef), trigger.move_effect_def)
+                {
                     let left_sector_id = sidedef.sector;
                     debug!(
                         "Sector {} with zero tag marked as dynamic, required by manual linedef {}.",
@@ -416,7 +617,8 @@ impl LevelAnalysis {
                             &mut next_dynamic_object_id,
                             level,
                             left_sector_id,
-                            &mut trigger,
+                            effect_def,
+                            &mut trigger.move_effects,
                         );
                 }
                 self.triggers.push(trigger);
@@ -424,6 +626,7 @@ impl LevelAnalysis {
             }
 
             if let Some(first_index) = tag_to_first_index.get(tag as usize) {
+                let mut stairs_visited = HashSet::new();
                 for &(current_tag, current_sector_id) in &sector_tags_and_ids[*first_index..] {
                     if current_tag != tag {
                         break;
@@ -432,15 +635,57 @@ impl LevelAnalysis {
                         "Sector {} with the tag {} marked as dynamic, required by linedef {}.",
                         current_sector_id, tag, i_linedef
                     );
-                    self.dynamic_info
-                        .entry(current_sector_id)
-                        .or_insert_with(DynamicSectorInfo::default)
-                        .update(
+                    if let Some(effect_def) = trigger.move_effect_def {
+                        self.dynamic_info
+                            .entry(current_sector_id)
+                            .or_default()
+                            .update(
+                                &mut next_dynamic_object_id,
+                                level,
+                                current_sector_id,
+                                effect_def,
+                                &mut trigger.move_effects,
+                            );
+                    }
+                    if let Some(stairs_def) = trigger.stairs_effect_def {
+                        self.build_stairs(
                             &mut next_dynamic_object_id,
                             level,
                             current_sector_id,
+                            stairs_def,
+                            &mut stairs_visited,
+                            &mut trigger.move_effects,
+                        );
+                    }
+                    if let Some(donut_def) = trigger.donut_effect_def {
+                        self.build_donut(
+                            &mut next_dynamic_object_id,
+                            level,
+                            current_sector_id,
+                            donut_def,
                             &mut trigger,
                         );
+                    }
+                    if let Some(change_def) = trigger.change_effect_def {
+                        self.add_change(
+                            &mut next_dynamic_object_id,
+                            level,
+                            linedef,
+                            current_sector_id,
+                            change_def,
+                            &mut trigger,
+                        );
+                    }
+                    if let Some(light_def) = trigger.light_effect_def {
+                        trigger.light_changes.push(LightChange {
+                            sector_id: current_sector_id,
+                            light_info: light::changed_light(
+                                level,
+                                &level.sectors[current_sector_id as usize],
+                                light_def,
+                            ),
+                        });
+                    }
                 }
             } else {
                 warn!(
@@ -450,6 +695,43 @@ impl LevelAnalysis {
             }
             self.triggers.push(trigger);
         }
+
+        for (i_sector, sector) in level.sectors.iter().enumerate() {
+            let (wait, second) = match sector.sector_type {
+                DOOR_CLOSE_IN_30 => (30.0, HeightRef::Floor.into()),
+                DOOR_RAISE_IN_5_MINS => (
+                    300.0,
+                    HeightDef {
+                        to: HeightRef::LowestCeiling,
+                        offset: -4,
+                    },
+                ),
+                _ => continue,
+            };
+            let sector_id = i_sector as SectorId;
+            debug!(
+                "Sector {} with type {} marked as dynamic.",
+                sector_id, sector.sector_type
+            );
+            self.dynamic_info.entry(sector_id).or_default().update(
+                &mut next_dynamic_object_id,
+                level,
+                sector_id,
+                MoveEffectDef {
+                    floor: None,
+                    ceiling: Some(HeightEffectDef {
+                        first: HeightRef::Ceiling.into(),
+                        second: Some(second),
+                    }),
+                    repeat: false,
+                    wait,
+                    speed: TIMED_DOOR_SPEED,
+                    blocked: BlockedBehaviour::Reverse,
+                },
+                &mut self.initial_effects,
+            );
+        }
+
         for (i_trigger, trigger) in self.triggers.iter().enumerate() {
             debug!("Trigger {}: {:#?}", i_trigger, trigger);
         }
@@ -463,6 +745,183 @@ impl LevelAnalysis {
         );
     }
 
+    /// Raises a chain of stairs, starting at `first_sector_id` and continuing through the
+    /// neighbouring sectors which share its floor texture. Each step is `step` higher than the
+    /// previous one.
+    fn build_stairs(
+        &mut self,
+        next_dynamic_object_id: &mut ObjectId,
+        level: &Level,
+        first_sector_id: SectorId,
+        stairs_def: StairsEffectDef,
+        visited: &mut HashSet<SectorId>,
+        move_effects: &mut Vec<MoveEffect>,
+    ) {
+        if !visited.insert(first_sector_id) {
+            return;
+        }
+        let effect_def = MoveEffectDef {
+            floor: None,
+            ceiling: None,
+            repeat: false,
+            wait: 0.0,
+            speed: stairs_def.speed,
+            blocked: BlockedBehaviour::Stop,
+        };
+        let texture = level.sectors[first_sector_id as usize].floor_texture;
+        let mut height = level.sectors[first_sector_id as usize].floor_height;
+        let mut sector_id = first_sector_id;
+        loop {
+            height += stairs_def.step;
+            debug!("Stair sector {} raised to {}.", sector_id, height);
+            self.dynamic_info.entry(sector_id).or_default().add_moves(
+                next_dynamic_object_id,
+                &level.sectors[sector_id as usize],
+                (Some(height), None),
+                (None, None),
+                &effect_def,
+                move_effects,
+            );
+
+            // The next step is behind a linedef which faces away from the current one.
+            let next_sector_id = level
+                .sector_linedefs(sector_id)
+                .filter_map(|linedef| level.linedef_sector_ids(linedef))
+                .filter(|&(right, left)| right == sector_id && left != sector_id)
+                .map(|(_, left)| left)
+                .find(|&left| {
+                    level.sectors[left as usize].floor_texture == texture
+                        && !visited.contains(&left)
+                });
+            match next_sector_id {
+                Some(next_sector_id) => {
+                    visited.insert(next_sector_id);
+                    sector_id = next_sector_id;
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Lowers the `pillar_id` sector and raises the ring around it, both to the floor height of the
+    /// sector around the ring.
+    fn build_donut(
+        &mut self,
+        next_dynamic_object_id: &mut ObjectId,
+        level: &Level,
+        pillar_id: SectorId,
+        donut_def: DonutEffectDef,
+        trigger: &mut Trigger,
+    ) {
+        let other_side = |linedef: &WadLinedef, of: SectorId| {
+            level
+                .linedef_sector_ids(linedef)
+                .map(|(right, left)| if right == of { left } else { right })
+        };
+        let Some(ring_id) = level
+            .sector_linedefs(pillar_id)
+            .next()
+            .and_then(|linedef| other_side(linedef, pillar_id))
+        else {
+            warn!("Donut pillar sector {} has no ring around it.", pillar_id);
+            return;
+        };
+        let Some(outside_id) = level
+            .sector_linedefs(ring_id)
+            .filter_map(|linedef| other_side(linedef, ring_id))
+            .find(|&id| id != pillar_id && id != ring_id)
+        else {
+            warn!("Donut ring sector {} has no sector around it.", ring_id);
+            return;
+        };
+        debug!(
+            "Donut with pillar {}, ring {} and outside {}.",
+            pillar_id, ring_id, outside_id
+        );
+
+        let effect_def = MoveEffectDef {
+            floor: None,
+            ceiling: None,
+            repeat: false,
+            wait: 0.0,
+            speed: donut_def.speed,
+            blocked: BlockedBehaviour::Stop,
+        };
+        let outside = &level.sectors[outside_id as usize];
+        for sector_id in [ring_id, pillar_id] {
+            self.dynamic_info.entry(sector_id).or_default().add_moves(
+                next_dynamic_object_id,
+                &level.sectors[sector_id as usize],
+                (Some(outside.floor_height), None),
+                (None, None),
+                &effect_def,
+                &mut trigger.move_effects,
+            );
+        }
+
+        // Once raised, the ring takes on the outside floor texture.
+        let object_id = self.dynamic_info[&ring_id].change_floor_texture(
+            next_dynamic_object_id,
+            &level.sectors[ring_id as usize],
+            outside.floor_texture,
+        );
+        trigger.change_effects.push(ChangeEffect {
+            sector_id: ring_id,
+            object_id,
+            texture: outside.floor_texture,
+            sector_type: 0,
+            on_finish: true,
+        });
+    }
+
+    /// Changes the floor texture and type of `sector_id` to the ones of a model sector.
+    fn add_change(
+        &mut self,
+        next_dynamic_object_id: &mut ObjectId,
+        level: &Level,
+        linedef: &WadLinedef,
+        sector_id: SectorId,
+        change_def: ChangeEffectDef,
+        trigger: &mut Trigger,
+    ) {
+        let sector = &level.sectors[sector_id as usize];
+        let model = match change_def.model {
+            ChangeModel::Trigger => level
+                .right_sidedef(linedef)
+                .and_then(|sidedef| level.sidedef_sector(sidedef)),
+            ChangeModel::Numeric => trigger
+                .move_effect_def
+                .and_then(|effect_def| effect_def.floor)
+                .zip(level.neighbour_heights(sector))
+                .and_then(|(floor, heights)| floor.first.to_height(sector, &heights))
+                .and_then(|height| {
+                    level
+                        .adjacent_sectors(sector)
+                        .find(|adjacent| adjacent.floor_height == height)
+                }),
+        };
+        let Some(model) = model else {
+            warn!("No model sector found to change sector {}.", sector_id);
+            return;
+        };
+        let object_id = self
+            .dynamic_info
+            .entry(sector_id)
+            .or_default()
+            .change_floor_texture(next_dynamic_object_id, sector, model.floor_texture);
+        trigger.change_effects.push(ChangeEffect {
+            sector_id,
+            object_id,
+            texture: model.floor_texture,
+            sector_type: if change_def.copy_type {
+                model.sector_type
+            } else {
+                0
+            },
+            on_finish: change_def.model == ChangeModel::Numeric,
+        });
+    }
+
     fn linedef_to_trigger(
         &self,
         level: &Level,
@@ -498,12 +957,18 @@ impl LevelAnalysis {
 
                     only_once: linedef_meta.only_once,
                     move_effect_def: linedef_meta.move_effect,
+                    stairs_effect_def: linedef_meta.stairs_effect,
+                    donut_effect_def: linedef_meta.donut_effect,
+                    change_effect_def: linedef_meta.change_effect,
+                    light_effect_def: linedef_meta.light_effect,
                     exit_effect: linedef_meta.exit_effect,
                     unimplemented: false,
                     special_type,
 
                     line,
                     move_effects: Vec::new(),
+                    change_effects: Vec::new(),
+                    light_changes: Vec::new(),
                     teleport_effect,
                     switch_effect,
                 }
@@ -514,12 +979,18 @@ impl LevelAnalysis {
 
                     only_once: false,
                     move_effect_def: None,
+                    stairs_effect_def: None,
+                    donut_effect_def: None,
+                    change_effect_def: None,
+                    light_effect_def: None,
                     exit_effect: None,
                     unimplemented: true,
                     special_type,
 
                     line,
                     move_effects: Vec::new(),
+                    change_effects: Vec::new(),
+                    light_changes: Vec::new(),
                     teleport_effect: None,
                     switch_effect: None,
                 }
@@ -606,6 +1077,7 @@ pub struct LevelWalker<'a, V: LevelVisitor + 'a> {
     bsp_lines: Vec<Line2f>,
 
     dynamic_info: &'a IndexMap<SectorId, DynamicSectorInfo>,
+    light_groups: &'a HashMap<SectorId, SectorId>,
 
     // The vector contains all (2D) points which are part of the subsector:
     // implicit (intersection of BSP lines) and explicit (seg vertices).
@@ -636,6 +1108,7 @@ impl<'a, V: LevelVisitor> LevelWalker<'a, V> {
             light_cache: VecMap::with_capacity(level.sectors.len()),
 
             dynamic_info: &analysis.dynamic_info,
+            light_groups: &analysis.light_groups,
         }
     }
 
@@ -678,12 +1151,14 @@ impl<'a, V: LevelVisitor> LevelWalker<'a, V> {
                     ceiling_id: ObjectId(0),
                     floor_range,
                     ceiling_range,
+                    floor_alt_texture: None,
                 },
                 |dynamic_info| SectorInfo {
                     floor_id: dynamic_info.floor_id,
                     ceiling_id: dynamic_info.ceiling_id,
                     floor_range: dynamic_info.floor_range.unwrap_or(floor_range),
                     ceiling_range: dynamic_info.ceiling_range.unwrap_or(ceiling_range),
+                    floor_alt_texture: dynamic_info.floor_alt_texture,
                 },
             )
     }
@@ -992,7 +1467,7 @@ impl<'a, V: LevelVisitor> LevelWalker<'a, V> {
         };
 
         let light_info_with_contrast;
-        let light_info = light_info(&mut self.light_cache, self.level, sector);
+        let light_info = light_info(&mut self.light_cache, self.level, self.light_groups, sector);
         let light_info = if light_info.effect.is_none() {
             if (v1[0] - v2[0]).abs() < EPSILON {
                 light_info_with_contrast = light::with_contrast(light_info, Contrast::Brighten);
@@ -1045,7 +1520,7 @@ impl<'a, V: LevelVisitor> LevelWalker<'a, V> {
     }
 
     fn flat_poly(&mut self, sector: &WadSector, info: &SectorInfo) {
-        let light_info = light_info(&mut self.light_cache, self.level, sector);
+        let light_info = light_info(&mut self.light_cache, self.level, self.light_groups, sector);
         let (floor_tex, ceil_tex) = (sector.floor_texture, sector.ceiling_texture);
         let (floor_sky, ceil_sky) = (is_sky_flat(floor_tex), is_sky_flat(ceil_tex));
         let floor_y = from_wad_height(if floor_sky {
@@ -1072,6 +1547,7 @@ impl<'a, V: LevelVisitor> LevelWalker<'a, V> {
                 height: floor_y,
                 light_info,
                 tex_name: floor_tex,
+                alt_tex_name: info.floor_alt_texture,
             });
         }
 
@@ -1088,6 +1564,7 @@ impl<'a, V: LevelVisitor> LevelWalker<'a, V> {
                 height: ceil_y,
                 light_info,
                 tex_name: ceil_tex,
+                alt_tex_name: None,
             });
         }
     }
@@ -1127,7 +1604,7 @@ impl<'a, V: LevelVisitor> LevelWalker<'a, V> {
             if let Some(marker) = Marker::from(thing.thing_type) {
                 let pos = Pnt3f::new(pos[0], from_wad_height(sector.floor_height), pos[1]);
                 self.visitor.visit_marker(pos, yaw.into(), marker);
-            } else if let Some(sector) = self.sector_at(pos) {
+            } else if thing.spawns_at(SKILL) {
                 self.decor(thing, pos, sector);
             }
         }
@@ -1175,10 +1652,14 @@ impl<'a, V: LevelVisitor> LevelWalker<'a, V> {
                 return;
             }
         };
+        let Some(spawn_frame) = meta.spawn_frame() else {
+            warn!("No spawn frame for thing type {}", thing.thing_type);
+            return;
+        };
         let (name, size) = {
             let mut sprite0 = meta.sprite;
             // Ignore the error: if this fails, so will the `sprite0` and `sprite1` pushes below.
-            let _ = sprite0.push(meta.sequence.as_bytes()[0]);
+            let _ = sprite0.push(spawn_frame.frame);
             let mut sprite1 = sprite0;
             let sprite0 = sprite0.push(b'0').ok().map(|_| sprite0);
             let sprite1 = sprite1.push(b'1').ok().map(|_| sprite1);
@@ -1239,8 +1720,15 @@ impl<'a, V: LevelVisitor> LevelWalker<'a, V> {
             low,
             high,
             half_width,
-            light_info: light_info(&mut self.light_cache, self.level, sector),
+            light_info: light_info(&mut self.light_cache, self.level, self.light_groups, sector),
             tex_name: name,
+            thing_type: thing.thing_type,
+            radius: from_wad_height(meta.radius as WadCoord),
+            pickup: self.meta.is_pickup(thing.thing_type),
+            shootable: self.meta.is_shootable(thing.thing_type),
+            fullbright: spawn_frame.fullbright,
+            solid: meta.is_solid(),
+            height: meta.height.map(from_wad_height),
         });
     }
 }
@@ -1248,21 +1736,22 @@ impl<'a, V: LevelVisitor> LevelWalker<'a, V> {
 fn light_info<'a>(
     cache: &'a mut VecMap<LightInfo>,
     level: &Level,
+    light_groups: &HashMap<SectorId, SectorId>,
     sector: &WadSector,
 ) -> &'a LightInfo {
+    let sector_id = level.sector_id(sector);
     cache
-        .entry(level.sector_id(sector) as usize)
-        .or_insert_with(|| light::new_light(level, sector))
-}
-
-fn partition_line(node: &WadNode) -> Line2f {
-    Line2f::from_two_points(
-        from_wad_coords(node.line_x, node.line_y),
-        from_wad_coords(node.line_x + node.step_x, node.line_y + node.step_y),
-    )
+        .entry(sector_id as usize)
+        .or_insert_with(|| LightInfo {
+            sector: light_groups.get(&sector_id).copied(),
+            ..light::new_light(level, sector)
+        })
 }
 
 // Distance on the wrong side of a BSP and seg line allowed.
+/// Things are spawned, and counted towards the level totals, as on Hurt Me Plenty.
+const SKILL: u8 = 2;
+
 const BSP_TOLERANCE: f32 = 1e-3;
 const SEG_TOLERANCE: f32 = 0.1;
 